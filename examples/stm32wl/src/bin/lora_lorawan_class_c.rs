@@ -131,7 +131,7 @@ async fn lora_task(
     loop {
         info!("Sending uplink...");
         let result = device.send(&[0x01, 0x02, 0x03, 0x04], 1, true).await;
-        if let Ok(SendResponse::DownlinkReceived(_)) = result {
+        if let Ok(SendResponse::DownlinkReceived { .. }) = result {
             // After an uplink with Class C enabled, it is important to check for multiple downlinks.
             // It is theoretically possible to receive a Class A downlink and any number of Class C
             // downlinks during the Class C windows.
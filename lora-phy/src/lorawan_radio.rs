@@ -120,9 +120,15 @@ where
             config.rf.bb.cr,
             config.rf.frequency,
         )?;
-        let rx_pkt_params = self
-            .lora
-            .create_rx_packet_params(8, false, 255, true, true, &mdltn_params)?;
+        let rx_pkt_params = match config.mode {
+            LorawanRxMode::Beacon { len, .. } => {
+                self.lora
+                    .create_rx_packet_params(10, true, len, false, false, &mdltn_params)?
+            }
+            _ => self
+                .lora
+                .create_rx_packet_params(8, false, 255, true, true, &mdltn_params)?,
+        };
         self.lora
             .prepare_for_rx(RxMode::from(config.mode, config.rf.bb), &mdltn_params, &rx_pkt_params)
             .await?;
@@ -165,7 +171,7 @@ impl RxMode {
     fn from(mode: LorawanRxMode, bb: BaseBandModulationParams) -> Self {
         match mode {
             LorawanRxMode::Continuous => RxMode::Continuous,
-            LorawanRxMode::Single { ms } => {
+            LorawanRxMode::Single { ms } | LorawanRxMode::Beacon { ms, .. } => {
                 // Since both sx126x and sx127x have a preamble-based timeout, we translate
                 // the additional millisecond delay into symbols and add it to the amount of preamble symbols.
                 const PREAMBLE_SYMBOLS: u16 = 13; // 12.25
@@ -2,9 +2,12 @@
 //! allowing for asynchronous radio implementations. Requires the `async` feature.
 use super::mac::{self, FcntDown, Frame, Mac, Window};
 pub use super::{
-    mac::{NetworkCredentials, SendData, Session},
+    mac::{
+        BatteryLevel, ConfirmedRetryPolicy, DevNonceMode, GpsTime, LinkCheck, NetworkCredentials,
+        RxSettings, SendData, Session, Snapshot, SnapshotError,
+    },
     region::{self, Region},
-    Downlink, JoinMode,
+    Downlink, DownlinkMetadata, JoinMode, RxWindow,
 };
 use core::marker::PhantomData;
 use heapless::Vec;
@@ -16,6 +19,10 @@ use crate::{
     radio::{RadioBuffer, RxConfig},
     rng,
 };
+pub use lorawan::types::RejoinType;
+
+#[cfg(feature = "class-b")]
+pub use crate::mac::Beacon;
 
 pub mod radio;
 
@@ -77,6 +84,8 @@ where
     mac: Mac,
     radio_buffer: RadioBuffer<N>,
     downlink: Vec<Downlink, D>,
+    /// Maximum number of uplinks sent to drain the downlinks queued by the network.
+    f_pending_uplinks: u8,
     #[cfg(feature = "class-c")]
     class_c: bool,
 }
@@ -91,9 +100,25 @@ pub enum Error<R> {
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
 #[derive(Debug)]
 pub enum SendResponse {
-    DownlinkReceived(FcntDown),
+    /// A downlink was received. For confirmed uplinks this includes the acknowledgment: downlinks
+    /// without ACK bit don't acknowledge them, which are then retransmitted according to the
+    /// retry policy or end with `NoAck`.
+    DownlinkReceived {
+        fcnt_down: FcntDown,
+        /// Number of transmissions of the uplink frame.
+        attempts: u8,
+        /// The ACK bit of the downlink.
+        ack: bool,
+        /// The FPending bit of the downlink: the network has more downlinks queued, which it
+        /// sends after the next uplink. See [`Device::set_f_pending_uplinks`].
+        f_pending: bool,
+    },
     SessionExpired,
-    NoAck,
+    /// A confirmed uplink was not acknowledged after all attempts of the retry policy.
+    NoAck {
+        /// Number of transmissions of the uplink frame.
+        attempts: u8,
+    },
     RxComplete,
     #[cfg(feature = "multicast")]
     Multicast(MulticastResponse),
@@ -110,9 +135,22 @@ pub enum JoinResponse {
 #[derive(Debug)]
 pub enum ListenResponse {
     SessionExpired,
-    DownlinkReceived(FcntDown),
+    DownlinkReceived {
+        fcnt_down: FcntDown,
+        /// The ACK bit of the downlink.
+        ack: bool,
+        /// The FPending bit of the downlink.
+        f_pending: bool,
+    },
     #[cfg(feature = "multicast")]
     Multicast(MulticastResponse),
+    /// A valid beacon was received in a beacon window.
+    #[cfg(feature = "class-b")]
+    BeaconReceived(Beacon),
+    /// No beacon has been received for too long: the device is back to Class A and Class B
+    /// has to be enabled again.
+    #[cfg(feature = "class-b")]
+    BeaconLost,
 }
 
 #[cfg(feature = "multicast")]
@@ -206,6 +244,7 @@ where
             radio_buffer: RadioBuffer::new(),
             timer,
             downlink: Vec::new(),
+            f_pending_uplinks: 0,
             #[cfg(feature = "class-c")]
             class_c: false,
         }
@@ -280,6 +319,22 @@ where
         self.class_c = false;
     }
 
+    /// Enables Class B behavior with ping slots every 2^`periodicity` seconds (0 to 7). The
+    /// beacon is acquired based on the network time, so DeviceTimeReq is sent with the next
+    /// uplink if the network time is unknown, along with PingSlotInfoReq if the periodicity
+    /// changed. Returns an error if the device is not joined or the region has no beacon.
+    #[cfg(feature = "class-b")]
+    pub fn enable_class_b(&mut self, periodicity: u8) -> Result<(), Error<R::PhyError>> {
+        Ok(self.mac.enable_class_b(periodicity, self.timer.now_ms())?)
+    }
+
+    /// Disables Class B behavior. The network is informed with the next uplink, which no longer
+    /// has the Class B bit set.
+    #[cfg(feature = "class-b")]
+    pub fn disable_class_b(&mut self) {
+        self.mac.disable_class_b();
+    }
+
     pub fn get_session(&mut self) -> Option<&Session> {
         self.mac.get_session()
     }
@@ -306,32 +361,138 @@ where
         self.mac.configuration.data_rate = datarate;
     }
 
+    /// Enables Adaptive Data Rate: the ADR bit is set in uplinks so that the network may control
+    /// data rate, TX power and channel mask of the device. If no downlink is received for
+    /// `ADR_ACK_LIMIT` uplinks, acknowledgement is requested with ADRACKReq and the device
+    /// gradually backs off to its default TX power, lowest data rate and default channels.
+    pub fn enable_adr(&mut self) {
+        self.mac.configuration.adr = true;
+    }
+
+    /// Disables Adaptive Data Rate.
+    pub fn disable_adr(&mut self) {
+        self.mac.configuration.adr = false;
+    }
+
+    /// Set the retry policy for confirmed uplinks. Unacknowledged confirmed frames are
+    /// retransmitted after a random delay of 1 to 3 seconds (`ACK_TIMEOUT`) until an
+    /// acknowledgment is received or `max_attempts` transmissions have been made. By default,
+    /// confirmed frames are sent only once.
+    pub fn set_confirmed_retry_policy(&mut self, policy: ConfirmedRetryPolicy) {
+        self.mac.configuration.confirmed_retries = policy;
+    }
+
+    /// Send up to `max_uplinks` uplinks without application payload after a downlink with the
+    /// FPending bit set, so that the network can send the downlinks it has queued. These are
+    /// available with [`Device::take_downlink`] once `send` completes, which returns the response
+    /// to the application uplink. No more uplinks are sent once the downlink buffer is full or
+    /// the duty cycle blocks them; other errors of these uplinks are returned by `send`, the
+    /// downlinks received until then remain available. By default, no uplinks are sent for
+    /// FPending.
+    pub fn set_f_pending_uplinks(&mut self, max_uplinks: u8) {
+        self.f_pending_uplinks = max_uplinks;
+    }
+
+    /// Select how the DevNonce of OTAA join requests is generated. By default, DevNonce is a
+    /// counter as required by LoRaWAN 1.0.4, which needs to be persisted across reboots (see
+    /// [`Device::join`]).
+    pub fn set_dev_nonce_mode(&mut self, mode: DevNonceMode) {
+        self.mac.set_dev_nonce_mode(mode);
+    }
+
+    /// Restore the DevNonce counter, ie: the DevNonce to use for the next join request, typically
+    /// from non-volatile storage after a reboot. Values above `u16::MAX` mean that all DevNonces
+    /// have been used.
+    pub fn set_dev_nonce(&mut self, dev_nonce: u32) {
+        self.mac.set_dev_nonce(dev_nonce);
+    }
+
+    /// DevNonce to use for the next join request.
+    pub fn get_dev_nonce(&self) -> u32 {
+        self.mac.get_dev_nonce()
+    }
+
+    /// Returns the DevNonce counter if it has changed since it was last restored or taken.
+    ///
+    /// This only becomes available once the join request has been sent, so a reset during the
+    /// join procedure would lose it: persist `get_dev_nonce() + 1` before calling
+    /// [`Device::join`] instead.
+    pub fn take_dev_nonce_update(&mut self) -> Option<u32> {
+        self.mac.take_dev_nonce_update()
+    }
+
+    /// Set the function which provides the battery level to report when the network requests
+    /// the device status with DevStatusReq. By default, the battery level is reported as
+    /// [`BatteryLevel::Unknown`].
+    pub fn set_battery_level_callback(&mut self, battery_level: fn() -> BatteryLevel) {
+        self.mac.set_battery_level_callback(battery_level);
+    }
+
+    /// Request link quality from the network: LinkCheckReq is sent with the next uplink and the
+    /// answer of the network becomes available from [`Device::get_link_check`] once a downlink
+    /// carrying it is received. Returns an error if the device is not joined.
+    pub fn request_link_check(&mut self) -> Result<(), Error<R::PhyError>> {
+        Ok(self.mac.request_link_check()?)
+    }
+
+    /// Maximum length of the application payload of the next uplink, for the current data rate
+    /// and the pending MAC commands which fit FOpts. It is 0 if the device is not joined.
+    pub fn available_payload_length(&self) -> u8 {
+        self.mac.available_payload_length()
+    }
+
+    /// Link margin and gateway count from the answer to the last LinkCheckReq, or `None` if the
+    /// network hasn't answered yet.
+    pub fn get_link_check(&self) -> Option<LinkCheck> {
+        self.mac.get_link_check()
+    }
+
+    /// Request the network time: DeviceTimeReq is sent with the next uplink and, once the answer
+    /// is received, the synchronized time is available from [`Device::get_network_time`].
+    /// Returns an error if the device is not joined.
+    pub fn request_device_time(&mut self) -> Result<(), Error<R::PhyError>> {
+        Ok(self.mac.request_device_time()?)
+    }
+
+    /// Current network (GPS) time, based on the last DeviceTimeAns and the [`radio::Timer`]
+    /// clock, or `None` if the network time hasn't been received yet.
+    pub fn get_network_time(&self) -> Option<GpsTime> {
+        self.mac.get_gps_time(self.timer.now_ms())
+    }
+
+    /// Snapshot of the MAC state: the session and the configuration provided by the network,
+    /// such as the channel plan, data rate, TX power and RX window settings. Store it before
+    /// powering down and bring the device back with [`Device::restore`].
+    pub fn snapshot(&self) -> Snapshot {
+        self.mac.snapshot()
+    }
+
+    /// Restore the MAC state from a snapshot taken with [`Device::snapshot`], on a device
+    /// created for the same region. The device is left unchanged if restoring fails.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+        self.mac.restore(snapshot)
+    }
+
     /// Join the LoRaWAN network asynchronously. The returned future completes when
     /// the LoRaWAN network has been joined successfully, or an error has occurred.
     ///
     /// Repeatedly calling join using OTAA will result in a new LoRaWAN session to be created.
     ///
+    /// With [`DevNonceMode::Counter`], the join request uses [`Device::get_dev_nonce`]. Before
+    /// calling `join`, the application must persist `get_dev_nonce() + 1` and restore it with
+    /// [`Device::set_dev_nonce`] after a reboot: join servers reject reused DevNonces, so a reset
+    /// before the counter is persisted would otherwise prevent joining with these credentials.
+    ///
     /// Note that for a Class C enabled device, you must repeatedly send *confirmed* uplink until
     /// LoRaWAN Network Server (LNS) confirmation after joining.
     pub async fn join(&mut self, join_mode: &JoinMode) -> Result<JoinResponse, Error<R::PhyError>> {
         match join_mode {
             JoinMode::OTAA { deveui, appeui, appkey } => {
-                let (tx_config, _) = self.mac.join_otaa::<C, G, N>(
-                    &mut self.rng,
-                    NetworkCredentials::new(*appeui, *deveui, *appkey),
-                    &mut self.radio_buffer,
-                );
-
-                // Transmit the join payload
-                let ms = self
-                    .radio
-                    .tx(tx_config, self.radio_buffer.as_ref_for_read())
+                self.join_otaa(NetworkCredentials::new(*appeui, *deveui, *appkey)).await
+            }
+            JoinMode::OTAA1_1 { deveui, appeui, appkey, nwkkey } => {
+                self.join_otaa(NetworkCredentials::new_1_1(*appeui, *deveui, *appkey, *nwkkey))
                     .await
-                    .map_err(Error::Radio)?;
-
-                // Receive join response within RX window
-                self.timer.reset();
-                Ok(self.rx_downlink(&Frame::Join, ms).await?.into())
             }
             JoinMode::ABP { nwkskey, appskey, devaddr } => {
                 self.mac.join_abp(*nwkskey, *appskey, *devaddr);
@@ -340,6 +501,59 @@ where
         }
     }
 
+    async fn join_otaa(
+        &mut self,
+        credentials: NetworkCredentials,
+    ) -> Result<JoinResponse, Error<R::PhyError>> {
+        let (tx_config, _) = self.mac.join_otaa::<C, G, N>(
+            &mut self.rng,
+            credentials,
+            &mut self.radio_buffer,
+            self.timer.now_ms(),
+        )?;
+
+        // Transmit the join payload
+        let ms =
+            Self::transmit(&mut self.radio, tx_config, self.radio_buffer.as_ref_for_read()).await?;
+
+        // Receive join response within RX window
+        self.timer.reset();
+        Ok(self.rx_downlink(&Frame::Join, ms).await?.into())
+    }
+
+    /// Type of the rejoin-request which is due in a LoRaWAN 1.1 session, either periodically as
+    /// set up by the network with RejoinParamSetupReq or as requested with ForceRejoinReq.
+    /// Should be checked after every uplink, the rejoin-request is sent with [`Device::rejoin`].
+    pub fn rejoin_due(&mut self) -> Option<RejoinType> {
+        self.mac.rejoin_due(self.timer.now_ms())
+    }
+
+    /// Send a rejoin-request of the given type and receive the join-accept in the join-accept
+    /// windows. `join_mode` needs to be [`JoinMode::OTAA1_1`] with the credentials used for
+    /// joining. The current session is kept unless a join-accept is received.
+    pub async fn rejoin(
+        &mut self,
+        join_mode: &JoinMode,
+        rejoin_type: RejoinType,
+    ) -> Result<JoinResponse, Error<R::PhyError>> {
+        let JoinMode::OTAA1_1 { deveui, appeui, appkey, nwkkey } = join_mode else {
+            return Err(mac::Error::RejoinUnavailable.into());
+        };
+        let tx_config = self.mac.rejoin::<C, G, N>(
+            &mut self.rng,
+            NetworkCredentials::new_1_1(*appeui, *deveui, *appkey, *nwkkey),
+            rejoin_type,
+            &mut self.radio_buffer,
+            self.timer.now_ms(),
+        )?;
+
+        let ms =
+            Self::transmit(&mut self.radio, tx_config, self.radio_buffer.as_ref_for_read()).await?;
+
+        self.timer.reset();
+        Ok(self.rx_downlink(&Frame::Join, ms).await?.into())
+    }
+
     /// Send data on a given port with the expected confirmation. If downlink data is provided, the
     /// data is copied into the provided byte slice.
     ///
@@ -350,28 +564,122 @@ where
     /// In Class C mode, it is possible to get one or more downlinks and `Reponse::DownlinkReceived`
     /// maybe not even be indicated. It is recommended to call `take_downlink` after `send` until
     /// it returns `None`.
+    ///
+    /// Data longer than [`Device::available_payload_length`] is rejected with
+    /// `mac::Error::PayloadTooLarge`. MAC commands which don't fit FOpts are held back for the
+    /// following uplinks; sending empty data transmits them all on FPort 0.
     pub async fn send(
         &mut self,
         data: &[u8],
         fport: u8,
         confirmed: bool,
     ) -> Result<SendResponse, Error<R::PhyError>> {
+        let response = self.send_uplink(data, fport, confirmed).await?;
+        let mut f_pending =
+            matches!(response, SendResponse::DownlinkReceived { f_pending: true, .. });
+        for _ in 0..self.f_pending_uplinks {
+            // Downlinks received while the buffer is full would be lost
+            if !f_pending || self.downlink.is_full() {
+                break;
+            }
+            f_pending = match self.send_uplink(&[], 0, false).await {
+                Ok(SendResponse::DownlinkReceived { f_pending, .. }) => f_pending,
+                Ok(SendResponse::SessionExpired) => return Ok(SendResponse::SessionExpired),
+                Ok(_) => false,
+                // Draining the queue is given up if the duty cycle doesn't allow an uplink now
+                Err(Error::Mac(mac::Error::DutyCycle { .. })) => false,
+                Err(e) => return Err(e),
+            };
+        }
+        Ok(response)
+    }
+
+    async fn send_uplink(
+        &mut self,
+        data: &[u8],
+        fport: u8,
+        confirmed: bool,
+    ) -> Result<SendResponse, Error<R::PhyError>> {
+        let send_data = SendData { data, fport, confirmed };
         // Prepare transmission buffer
         let (tx_config, _fcnt_up) = self.mac.send::<C, G, N>(
             &mut self.rng,
             &mut self.radio_buffer,
-            &SendData { data, fport, confirmed },
+            &send_data,
+            self.timer.now_ms(),
         )?;
-        // Transmit our data packet
-        let ms = self
-            .radio
-            .tx(tx_config, self.radio_buffer.as_ref_for_read())
-            .await
-            .map_err(Error::Radio)?;
+        let mut transmitted = false;
+        let response = self.transmit_uplink(&send_data, tx_config, &mut transmitted).await;
+        if response.is_err() && transmitted {
+            // The frame has been on air, so its frame counter must not be reused
+            self.mac.abandon_uplink();
+        }
+        response
+    }
 
-        // Wait for received data within window
-        self.timer.reset();
-        Ok(self.rx_downlink(&Frame::Data, ms).await?.into())
+    /// Transmit the prepared uplink and receive the downlink, repeating the uplink as needed.
+    async fn transmit_uplink(
+        &mut self,
+        send_data: &SendData<'_>,
+        mut tx_config: radio::TxConfig,
+        transmitted: &mut bool,
+    ) -> Result<SendResponse, Error<R::PhyError>> {
+        loop {
+            // Transmit our data packet
+            let ms =
+                Self::transmit(&mut self.radio, tx_config, self.radio_buffer.as_ref_for_read())
+                    .await?;
+            *transmitted = true;
+            self.mac.tx_done(self.timer.now_ms());
+
+            // Wait for received data within window
+            self.timer.reset();
+            match self.rx_downlink(&Frame::Data, ms).await? {
+                // Repeat unconfirmed uplink as requested by NbTrans, unless a downlink was
+                // received in one of the windows, or retransmit unacknowledged confirmed uplink.
+                mac::Response::RetransmitRequest => {
+                    if send_data.confirmed {
+                        let delay = self.mac.get_ack_timeout(&mut self.rng);
+                        self.timer.delay_ms(delay.into()).await;
+                    }
+                    // Repetitions wait for duty-cycle limits instead of failing
+                    (tx_config, _) = loop {
+                        match self.mac.retransmit::<C, G, N>(
+                            &mut self.rng,
+                            &mut self.radio_buffer,
+                            send_data,
+                            self.timer.now_ms(),
+                        ) {
+                            Err(mac::Error::DutyCycle { retry_after_ms }) => {
+                                self.timer.delay_ms(retry_after_ms).await
+                            }
+                            result => break result?,
+                        }
+                    };
+                }
+                r => return Ok(SendResponse::from_mac(r, self.mac.get_tx_attempts())),
+            }
+        }
+    }
+
+    /// Send the pending MAC commands without application payload. They are carried in
+    /// FRMPayload on FPort 0, encrypted with the NwkSKey (NwkSEncKey in LoRaWAN 1.1 sessions).
+    ///
+    /// This answers the network without waiting for the next application uplink, eg: for sticky
+    /// answers such as RXParamSetupAns, which are repeated until a downlink is received, or for
+    /// answers to requests received in Class C. Nothing is sent if no MAC commands are pending,
+    /// which is reported as `mac::Error::NoMacCommands`. See
+    /// [`Device::has_pending_mac_commands`].
+    pub async fn send_mac_commands(&mut self) -> Result<SendResponse, Error<R::PhyError>> {
+        if self.mac.is_joined() && !self.has_pending_mac_commands() {
+            return Err(Error::Mac(mac::Error::NoMacCommands));
+        }
+        self.send(&[], 0, false).await
+    }
+
+    /// Whether MAC commands are pending for the next uplink.
+    pub fn has_pending_mac_commands(&self) -> bool {
+        self.mac.has_pending_mac_commands()
     }
 
     /// Take the downlink data from the device. This is typically called after a
@@ -455,6 +763,9 @@ where
         debug!("Configuring RXC window with config {}.", rx_config);
         self.radio.setup_rx(rx_config).await.map_err(Error::Radio)?;
         let mut response = None;
+        // The timer is busy with the window timeout, uplinks triggered by RXC frames are accounted
+        // at the opening of the window.
+        let now_ms = self.timer.now_ms();
         let timeout_fut = self.timer.at(duration.into());
         pin_mut!(timeout_fut);
         let mut maybe_timeout_fut = Some(timeout_fut);
@@ -469,17 +780,22 @@ where
             )
             .await
             {
-                RxcWindowResponse::Rx(sz, _, timeout_fut) => {
+                RxcWindowResponse::Rx(sz, rx_quality, timeout_fut) => {
                     debug!("RXC window received {} bytes.", sz);
                     self.radio_buffer.set_pos(sz);
-                    let mac_response = self
-                        .mac
-                        .handle_rxc::<C, N, D>(&mut self.radio_buffer, &mut self.downlink)?;
+                    let mac_response = self.mac.handle_rxc::<C, N, D>(
+                        &mut self.radio_buffer,
+                        &mut self.downlink,
+                        rx_quality,
+                        RxWindow::RxC,
+                        rx_config.rf.frequency,
+                    )?;
                     match Self::handle_mac_response(
                         &mut self.radio_buffer,
                         &mut self.mac,
                         &mut self.radio,
                         &mut self.rng,
+                        now_ms,
                         mac_response,
                         Some(rx_config),
                     )
@@ -531,7 +847,7 @@ where
         debug!("Configuring RX1 window with config {}.", rx_config);
         self.radio.setup_rx(rx_config).await.map_err(Error::Radio)?;
 
-        if let Some(response) = self.rx_listen().await? {
+        if let Some(response) = self.rx_listen(RxWindow::Rx1, rx_config.rf.frequency).await? {
             debug!("RX1 received {}", response);
             return Ok(response);
         }
@@ -548,7 +864,7 @@ where
         debug!("Configuring RX2 window with config {}.", rx_config);
         self.radio.setup_rx(rx_config).await.map_err(Error::Radio)?;
 
-        if let Some(response) = self.rx_listen().await? {
+        if let Some(response) = self.rx_listen(RxWindow::Rx2, rx_config.rf.frequency).await? {
             debug!("RX2 received {}", response);
             return Ok(response);
         }
@@ -556,6 +872,20 @@ where
         Ok(self.mac.rx2_complete())
     }
 
+    /// Transmit the frame, after listen-before-talk if the region requires it.
+    async fn transmit(
+        radio: &mut R,
+        tx_config: radio::TxConfig,
+        buf: &[u8],
+    ) -> Result<u32, Error<R::PhyError>> {
+        if let Some(lbt) = tx_config.lbt {
+            if !radio.listen_before_talk(tx_config.rf, lbt).await.map_err(Error::Radio)? {
+                return Err(mac::Error::ChannelBusy.into());
+            }
+        }
+        radio.tx(tx_config, buf).await.map_err(Error::Radio)
+    }
+
     /// Helper function to handle MAC responses and perform common actions
     #[allow(unused_variables)]
     async fn handle_mac_response(
@@ -563,6 +893,7 @@ where
         mac: &mut Mac,
         radio: &mut R,
         rng: &mut G,
+        now_ms: u64,
         response: mac::Response,
         rx_config: Option<RxConfig>,
     ) -> Result<Option<mac::Response>, Error<R::PhyError>> {
@@ -572,19 +903,16 @@ where
             #[cfg(feature = "certification")]
             mac::Response::UplinkPrepared => {
                 let (tx_config, _fcnt_up) =
-                    mac.certification_setup_send::<C, G, N>(rng, radio_buffer)?;
-                radio.tx(tx_config, radio_buffer.as_ref_for_read()).await.map_err(Error::Radio)?;
+                    mac.certification_setup_send::<C, G, N>(rng, radio_buffer, now_ms)?;
+                Self::transmit(radio, tx_config, radio_buffer.as_ref_for_read()).await?;
                 Ok(Some(mac.rx2_complete()))
             }
             #[cfg(feature = "multicast")]
             mac::Response::Multicast(mut response) => {
                 if response.is_transmit_request() {
                     let (tx_config, _fcnt_up) =
-                        mac.multicast_setup_send::<C, G, N>(rng, radio_buffer)?;
-                    radio
-                        .tx(tx_config, radio_buffer.as_ref_for_read())
-                        .await
-                        .map_err(Error::Radio)?;
+                        mac.multicast_setup_send::<C, G, N>(rng, radio_buffer, now_ms)?;
+                    Self::transmit(radio, tx_config, radio_buffer.as_ref_for_read()).await?;
                     if let Some(rx_config) = rx_config {
                         radio.setup_rx(rx_config).await.map_err(Error::Radio)?;
                     }
@@ -603,18 +931,28 @@ where
         }
     }
 
-    async fn rx_listen(&mut self) -> Result<Option<mac::Response>, Error<R::PhyError>> {
+    async fn rx_listen(
+        &mut self,
+        window: RxWindow,
+        frequency: u32,
+    ) -> Result<Option<mac::Response>, Error<R::PhyError>> {
         let response =
             match self.radio.rx_single(self.radio_buffer.as_mut()).await.map_err(Error::Radio)? {
-                RxStatus::Rx(s, _q) => {
+                RxStatus::Rx(s, q) => {
                     self.radio_buffer.set_pos(s);
-                    let mac_response =
-                        self.mac.handle_rx::<C, N, D>(&mut self.radio_buffer, &mut self.downlink);
+                    let mac_response = self.mac.handle_rx::<C, N, D>(
+                        &mut self.radio_buffer,
+                        &mut self.downlink,
+                        q,
+                        window,
+                        frequency,
+                    );
                     Self::handle_mac_response(
                         &mut self.radio_buffer,
                         &mut self.mac,
                         &mut self.radio,
                         &mut self.rng,
+                        self.timer.now_ms(),
                         mac_response,
                         None,
                     )
@@ -632,16 +970,22 @@ where
     pub async fn rxc_listen(&mut self) -> Result<ListenResponse, Error<R::PhyError>> {
         let rx_config = self.mac.get_rxc_config();
         loop {
-            let (sz, _rx_quality) =
+            let (sz, rx_quality) =
                 self.radio.rx_continuous(self.radio_buffer.as_mut()).await.map_err(Error::Radio)?;
             self.radio_buffer.set_pos(sz);
-            let mac_response =
-                self.mac.handle_rxc::<C, N, D>(&mut self.radio_buffer, &mut self.downlink)?;
+            let mac_response = self.mac.handle_rxc::<C, N, D>(
+                &mut self.radio_buffer,
+                &mut self.downlink,
+                rx_quality,
+                RxWindow::RxC,
+                rx_config.rf.frequency,
+            )?;
             if let Some(response) = Self::handle_mac_response(
                 &mut self.radio_buffer,
                 &mut self.mac,
                 &mut self.radio,
                 &mut self.rng,
+                self.timer.now_ms(),
                 mac_response,
                 Some(rx_config),
             )
@@ -653,6 +997,86 @@ where
     }
 }
 
+#[cfg(feature = "class-b")]
+impl<R, C, T, G, const N: usize, const D: usize> Device<R, C, T, G, N, D>
+where
+    R: radio::PhyRxTx + Timings,
+    T: radio::Timer,
+    C: CryptoFactory + Default,
+    G: RngCore,
+{
+    /// When Class B is enabled, the device opens the beacon and ping slot windows. The caller is
+    /// expected to await this between uplinks: it returns once a beacon or a ping slot downlink
+    /// has been received, or when the beacon is lost.
+    ///
+    /// Returns [`mac::Error::NoNetworkTime`] until the network time has been received with
+    /// DeviceTimeAns.
+    pub async fn class_b_listen(&mut self) -> Result<ListenResponse, Error<R::PhyError>> {
+        loop {
+            let window = self.mac.get_class_b_window::<C>(
+                self.timer.now_ms(),
+                self.radio.get_rx_window_lead_time_ms(),
+                self.radio.get_rx_window_buffer(),
+            )?;
+            self.radio.low_power().await.map_err(Error::Radio)?;
+            let delay = window.at_ms.saturating_sub(
+                self.timer.now_ms() + self.radio.get_rx_window_lead_time_ms() as u64,
+            );
+            self.timer.reset();
+            self.timer.at(delay).await;
+
+            self.radio_buffer.clear();
+            self.radio.setup_rx(window.rx_config).await.map_err(Error::Radio)?;
+            let status =
+                self.radio.rx_single(self.radio_buffer.as_mut()).await.map_err(Error::Radio)?;
+            let now_ms = self.timer.now_ms();
+            match (window.slot, status) {
+                (mac::class_b::Slot::Beacon, RxStatus::Rx(s, _)) => {
+                    self.radio_buffer.set_pos(s);
+                    if let Some(beacon) =
+                        self.mac.handle_beacon(&self.radio_buffer, &window.rx_config, now_ms)
+                    {
+                        debug!("Beacon received at GPS time {}", beacon.time);
+                        return Ok(ListenResponse::BeaconReceived(beacon));
+                    }
+                    if self.mac.beacon_missed(now_ms) {
+                        return Ok(ListenResponse::BeaconLost);
+                    }
+                }
+                (mac::class_b::Slot::Beacon, RxStatus::RxTimeout) => {
+                    if self.mac.beacon_missed(now_ms) {
+                        return Ok(ListenResponse::BeaconLost);
+                    }
+                }
+                (mac::class_b::Slot::Ping, RxStatus::Rx(s, q)) => {
+                    self.radio_buffer.set_pos(s);
+                    let mac_response = self.mac.handle_rxc::<C, N, D>(
+                        &mut self.radio_buffer,
+                        &mut self.downlink,
+                        q,
+                        RxWindow::PingSlot,
+                        window.rx_config.rf.frequency,
+                    )?;
+                    if let Some(response) = Self::handle_mac_response(
+                        &mut self.radio_buffer,
+                        &mut self.mac,
+                        &mut self.radio,
+                        &mut self.rng,
+                        now_ms,
+                        mac_response,
+                        None,
+                    )
+                    .await?
+                    {
+                        return Ok(response.into());
+                    }
+                }
+                (mac::class_b::Slot::Ping, RxStatus::RxTimeout) => (),
+            }
+        }
+    }
+}
+
 /// Allows to fine-tune the beginning and end of the receive windows for a specific board and runtime.
 pub trait Timings {
     /// How many milliseconds before the RX window should the SPI transaction start?
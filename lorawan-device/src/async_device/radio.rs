@@ -1,4 +1,4 @@
-pub use crate::radio::{RfConfig, RxConfig, RxMode, RxQuality, TxConfig};
+pub use crate::radio::{Lbt, RfConfig, RxConfig, RxMode, RxQuality, TxConfig};
 
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
 pub struct Error<E>(pub E);
@@ -25,6 +25,10 @@ pub trait Timer {
 
     /// Delay for millis milliseconds
     async fn delay_ms(&mut self, millis: u64);
+
+    /// Milliseconds elapsed since an arbitrary, fixed point in time. The clock must be monotonic
+    /// and is used for duty-cycle accounting and network time synchronization.
+    fn now_ms(&self) -> u64;
 }
 
 /// An asynchronous radio implementation that can transmit and receive data.
@@ -58,6 +62,17 @@ pub trait PhyRxTx: Sized {
     /// future should complete when RX data has been received or when the timeout has expired.
     async fn rx_single(&mut self, buf: &mut [u8]) -> Result<RxStatus, Self::PhyError>;
 
+    /// Sense the channel before transmitting in regions which require listen-before-talk, such
+    /// as KR920. Returns whether the channel is clear. Radios used in these regions need to
+    /// implement this, the default implementation always reports the channel as clear.
+    async fn listen_before_talk(
+        &mut self,
+        _config: RfConfig,
+        _lbt: Lbt,
+    ) -> Result<bool, Self::PhyError> {
+        Ok(true)
+    }
+
     /// Puts the radio into a low-power mode
     async fn low_power(&mut self) -> Result<(), Self::PhyError> {
         Ok(())
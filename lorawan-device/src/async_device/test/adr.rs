@@ -0,0 +1,141 @@
+use super::*;
+use crate::region::constants::{ADR_ACK_DELAY, ADR_ACK_LIMIT};
+use crate::region::DR;
+use lorawan::parser::{DataHeader, DataPayload, FCtrl, PhyPayload};
+
+fn adr_device(adr_ack_cnt: u32) -> (radio::RadioChannel, timer::TimerChannel, Device) {
+    let (radio, timer, mut device) =
+        util::session_with_region(crate::region::EU868::new_eu868().into());
+    let mut session = device.mac.get_session().unwrap().clone();
+    session.adr_ack_cnt = adr_ack_cnt;
+    device.mac.set_session(session);
+    device.enable_adr();
+    device.set_datarate(DR::_5);
+    (radio, timer, device)
+}
+
+async fn send_without_downlink(
+    radio: &radio::RadioChannel,
+    timer: &timer::TimerChannel,
+    mut device: Device,
+) -> Device {
+    let task = tokio::spawn(async move {
+        let response = device.send(&[1, 2, 3], 3, false).await;
+        (device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    let (device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::RxComplete)));
+    device
+}
+
+async fn last_uplink_fctrl(radio: &radio::RadioChannel) -> FCtrl {
+    let mut uplink = radio.get_last_uplink().await;
+    match uplink.get_payload() {
+        PhyPayload::Data(DataPayload::Encrypted(data)) => data.fhdr().fctrl(),
+        _ => panic!(),
+    }
+}
+
+fn empty_downlink(_uplink: Option<Uplink>, _config: RfConfig, buf: &mut [u8]) -> usize {
+    let mut phy = lorawan::creator::DataPayloadCreator::new(buf).unwrap();
+    phy.set_confirmed(false);
+    phy.set_dev_addr(&[0; 4]);
+    phy.set_uplink(false);
+    phy.set_fcnt(1);
+    phy.set_fctrl(&FCtrl::new(0x0, false));
+    phy.build(&[], [], &get_key().into(), &get_key().into(), &DefaultFactory).unwrap().len()
+}
+
+#[tokio::test]
+async fn adr_bit_not_set_when_disabled() {
+    let (radio, timer, mut device) = adr_device(ADR_ACK_LIMIT);
+    device.disable_adr();
+    let _device = send_without_downlink(&radio, &timer, device).await;
+    let fctrl = last_uplink_fctrl(&radio).await;
+    assert!(!fctrl.adr());
+    assert!(!fctrl.adr_ack_req());
+}
+
+#[tokio::test]
+async fn adr_ack_req_after_limit() {
+    let (radio, timer, device) = adr_device(ADR_ACK_LIMIT - 2);
+
+    let device = send_without_downlink(&radio, &timer, device).await;
+    let fctrl = last_uplink_fctrl(&radio).await;
+    assert!(fctrl.adr());
+    assert!(!fctrl.adr_ack_req());
+
+    let mut device = send_without_downlink(&radio, &timer, device).await;
+    let fctrl = last_uplink_fctrl(&radio).await;
+    assert!(fctrl.adr());
+    assert!(fctrl.adr_ack_req());
+    assert_eq!(device.get_session().unwrap().adr_ack_cnt, ADR_ACK_LIMIT);
+
+    // Any Class A downlink resets ADR_ACK_CNT
+    let task = tokio::spawn(async move {
+        let response = device.send(&[1, 2, 3], 3, false).await;
+        (device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(empty_downlink).await;
+    let (mut device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::DownlinkReceived { fcnt_down: 1, .. })));
+    assert!(last_uplink_fctrl(&radio).await.adr_ack_req());
+    assert_eq!(device.get_session().unwrap().adr_ack_cnt, 0);
+}
+
+#[tokio::test]
+async fn adr_backoff() {
+    let (radio, timer, mut device) = adr_device(ADR_ACK_LIMIT + ADR_ACK_DELAY - 1);
+    device.mac.configuration.tx_power = Some(2);
+    let mut channel_mask = device.mac.region.channel_mask_get();
+    channel_mask.set_channel(0, false);
+    channel_mask.set_channel(1, false);
+    device.mac.region.channel_mask_set(channel_mask);
+
+    // Default TX power is restored first...
+    let mut device = send_without_downlink(&radio, &timer, device).await;
+    assert_eq!(device.mac.configuration.tx_power, None);
+    assert_eq!(device.get_datarate(), DR::_5);
+
+    // ...and then data rate is decreased every ADR_ACK_DELAY uplinks
+    for _ in 0..ADR_ACK_DELAY {
+        device = send_without_downlink(&radio, &timer, device).await;
+    }
+    assert_eq!(device.get_datarate(), DR::_4);
+    assert!(last_uplink_fctrl(&radio).await.adr_ack_req());
+
+    for _ in 0..4 * ADR_ACK_DELAY {
+        device = send_without_downlink(&radio, &timer, device).await;
+    }
+    assert_eq!(device.get_datarate(), DR::_0);
+    // No more ADRACKReq when settings are already at their defaults
+    let device = send_without_downlink(&radio, &timer, device).await;
+    assert!(!last_uplink_fctrl(&radio).await.adr_ack_req());
+
+    // Default channels are re-enabled at the lowest data rate
+    let channel_mask = device.mac.region.channel_mask_get();
+    assert!(channel_mask.is_enabled(0).unwrap());
+    assert!(channel_mask.is_enabled(1).unwrap());
+}
+
+#[tokio::test]
+async fn adr_ack_cnt_uplink_not_sent() {
+    let (radio, timer, device) = adr_device(0);
+    let mut device = send_without_downlink(&radio, &timer, device).await;
+    assert_eq!(device.get_session().unwrap().adr_ack_cnt, 1);
+
+    // Uplinks which are not sent don't count
+    let mut channel_mask = device.mac.region.channel_mask_get();
+    for channel in 0..3 {
+        channel_mask.set_channel(channel, false);
+    }
+    device.mac.region.channel_mask_set(channel_mask);
+    let response = device.send(&[1, 2, 3], 3, false).await;
+    assert!(matches!(response, Err(Error::Mac(crate::mac::Error::NoChannelForDataRate))));
+    assert_eq!(device.get_session().unwrap().adr_ack_cnt, 1);
+}
@@ -74,7 +74,7 @@ async fn txframectrlreq_no_change() {
 
     let (mut device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(_)) => {}
+        Ok(SendResponse::DownlinkReceived { .. }) => {}
         _ => panic!(),
     }
     // Check that session is configured to override and send only confirmed packets
@@ -97,7 +97,7 @@ async fn txframectrlreq_no_change() {
 
     let (device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(_)) => {}
+        Ok(SendResponse::DownlinkReceived { .. }) => {}
         _ => panic!(),
     }
     // Check that override_confirm has not changed!
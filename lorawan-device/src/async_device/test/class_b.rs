@@ -0,0 +1,157 @@
+use super::util;
+use crate::async_device::{ListenResponse, SendResponse};
+use crate::mac::class_b::crc16;
+use crate::radio::{RfConfig, RxMode};
+use crate::test_util::{get_key, Uplink};
+use lorawan::creator::DataPayloadCreator;
+use lorawan::default_crypto::DefaultFactory;
+use lorawan::parser::{DataHeader, DataPayload, FCtrl, PhyPayload};
+
+/// GPS time of the beacon following the DeviceTimeAns below
+const BEACON_TIME: u32 = 1_400_000_128;
+
+fn time_and_ping_slot_info_ans(uplink: Option<Uplink>, _config: RfConfig, buf: &mut [u8]) -> usize {
+    match uplink.unwrap().get_payload() {
+        PhyPayload::Data(DataPayload::Encrypted(data)) => {
+            // PingSlotInfoReq with periodicity 5 and DeviceTimeReq
+            assert_eq!(data.fhdr().data(), [0x10, 0x05, 0x0d]);
+            assert!(!data.fhdr().fctrl().class_b());
+        }
+        _ => panic!(),
+    }
+    let mut phy = DataPayloadCreator::new(buf).unwrap();
+    phy.set_f_port(0);
+    phy.set_dev_addr(&[0; 4]);
+    phy.set_uplink(false);
+    phy.set_fcnt(1);
+    phy.set_fctrl(&FCtrl::new(0, false));
+    // DeviceTimeAns: 1_400_000_000 s and PingSlotInfoAns
+    let cmds = hex::decode("0d004e72530010").unwrap();
+    phy.build(&[], cmds, &get_key().into(), &get_key().into(), &DefaultFactory).unwrap().len()
+}
+
+fn beacon(_uplink: Option<Uplink>, config: RfConfig, buf: &mut [u8]) -> usize {
+    // US915 beacons hop over 8 channels
+    assert_eq!(config.frequency, 923_300_000 + (BEACON_TIME / 128 % 8) * 600_000);
+    let data = &mut buf[..23];
+    data.fill(0);
+    data[5..9].copy_from_slice(&BEACON_TIME.to_le_bytes());
+    let crc = crc16(&data[..9]);
+    data[9..11].copy_from_slice(&crc.to_le_bytes());
+    let crc = crc16(&data[11..21]);
+    data[21..23].copy_from_slice(&crc.to_le_bytes());
+    23
+}
+
+fn ping_slot_downlink(_uplink: Option<Uplink>, _config: RfConfig, buf: &mut [u8]) -> usize {
+    let mut phy = DataPayloadCreator::new(buf).unwrap();
+    phy.set_f_port(3);
+    phy.set_dev_addr(&[0; 4]);
+    phy.set_uplink(false);
+    phy.set_fcnt(2);
+    let finished =
+        phy.build(&[1, 2, 3], [], &get_key().into(), &get_key().into(), &DefaultFactory).unwrap();
+    finished.len()
+}
+
+#[tokio::test]
+async fn test_class_b_beacon_and_ping_slot() {
+    let (radio, timer, mut async_device) = util::setup_with_session();
+    timer.stop_clock(10_000);
+    async_device.enable_class_b(5).unwrap();
+    // The beacon can't be acquired without the network time
+    assert!(matches!(
+        async_device.class_b_listen().await,
+        Err(crate::async_device::Error::Mac(crate::mac::Error::NoNetworkTime))
+    ));
+
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(time_and_ping_slot_info_ans).await;
+    let (mut async_device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::DownlinkReceived { .. })));
+
+    // Beacon window
+    let task = tokio::spawn(async move {
+        let response = async_device.class_b_listen().await;
+        (async_device, response)
+    });
+    timer.fire_most_recent().await;
+    timer.advance_clock(128_000);
+    radio.handle_rxtx(beacon).await;
+    let (mut async_device, response) = task.await.unwrap();
+    assert!(matches!(radio.get_rxconfig().await.unwrap().mode, RxMode::Beacon { len: 23, .. }));
+    match response {
+        Ok(ListenResponse::BeaconReceived(beacon)) => assert_eq!(beacon.time, BEACON_TIME),
+        _ => panic!(),
+    }
+
+    // Ping slot window
+    let task = tokio::spawn(async move {
+        let response = async_device.class_b_listen().await;
+        (async_device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(ping_slot_downlink).await;
+    let (mut async_device, response) = task.await.unwrap();
+    assert!(matches!(radio.get_rxconfig().await.unwrap().mode, RxMode::Single { .. }));
+    assert!(matches!(response, Ok(ListenResponse::DownlinkReceived { fcnt_down: 2, .. })));
+    assert_eq!(async_device.take_downlink().unwrap().data, [1, 2, 3]);
+
+    // Uplinks signal Class B operation once the beacon is tracked
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    let (_, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::RxComplete)));
+    match radio.get_last_uplink().await.get_payload() {
+        PhyPayload::Data(DataPayload::Encrypted(data)) => assert!(data.fhdr().fctrl().class_b()),
+        _ => panic!(),
+    }
+}
+
+#[tokio::test]
+async fn test_class_b_beacon_lost() {
+    let (radio, timer, mut async_device) = util::setup_with_session();
+    timer.stop_clock(10_000);
+    async_device.enable_class_b(7).unwrap();
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+    fn device_time_ans(_uplink: Option<Uplink>, _config: RfConfig, buf: &mut [u8]) -> usize {
+        let mut phy = DataPayloadCreator::new(buf).unwrap();
+        phy.set_f_port(0);
+        phy.set_dev_addr(&[0; 4]);
+        phy.set_uplink(false);
+        phy.set_fcnt(1);
+        phy.set_fctrl(&FCtrl::new(0, false));
+        let cmds = hex::decode("0d004e725300").unwrap();
+        phy.build(&[], cmds, &get_key().into(), &get_key().into(), &DefaultFactory).unwrap().len()
+    }
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(device_time_ans).await;
+    let (mut async_device, _) = task.await.unwrap();
+
+    // The search is given up after three beacon windows without a beacon
+    let task = tokio::spawn(async move {
+        let response = async_device.class_b_listen().await;
+        (async_device, response)
+    });
+    for _ in 0..3 {
+        timer.fire_most_recent().await;
+        timer.advance_clock(128_000);
+        radio.handle_timeout().await;
+    }
+    let (mut async_device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(ListenResponse::BeaconLost)));
+    assert!(async_device.class_b_listen().await.is_err());
+}
@@ -2,6 +2,7 @@ use super::util;
 use crate::async_device::{ListenResponse, SendResponse};
 use crate::radio::RfConfig;
 use crate::test_util::{get_key, Uplink};
+use crate::RxWindow;
 use lorawan::creator::DataPayloadCreator;
 use lorawan::default_crypto::DefaultFactory;
 
@@ -38,13 +39,15 @@ async fn test_class_c_data_before_rx1() {
     radio.handle_rxtx(util::handle_data_uplink_with_link_adr_req::<1, 2>).await;
     let (mut device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(_)) => (),
+        Ok(SendResponse::DownlinkReceived { .. }) => (),
         _ => {
             panic!()
         }
     }
-    let _ = device.take_downlink().unwrap();
-    let _ = device.take_downlink().unwrap();
+    assert_eq!(device.take_downlink().unwrap().metadata.window, RxWindow::Rx1);
+    let metadata = device.take_downlink().unwrap().metadata;
+    assert_eq!(metadata.window, RxWindow::RxC);
+    assert_eq!(metadata.fcnt_down, 1);
 }
 
 #[tokio::test]
@@ -70,7 +73,7 @@ async fn test_class_c_data_before_rx2() {
     radio.handle_rxtx(util::handle_data_uplink_with_link_adr_req::<1, 2>).await;
     let (mut device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(_)) => (),
+        Ok(SendResponse::DownlinkReceived { .. }) => (),
         _ => {
             panic!()
         }
@@ -91,7 +94,7 @@ async fn test_class_c_async_down() {
     radio.handle_rxtx(class_c_downlink::<1>).await;
     let (mut device, response) = task.await.unwrap();
     match response {
-        Ok(ListenResponse::DownlinkReceived(_)) => (),
+        Ok(ListenResponse::DownlinkReceived { .. }) => (),
         _ => {
             panic!()
         }
@@ -57,7 +57,7 @@ async fn linkadrreq_dynamic() {
 
     let (mut device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(_)) => {}
+        Ok(SendResponse::DownlinkReceived { .. }) => {}
         _ => panic!(),
     }
 
@@ -86,7 +86,7 @@ async fn linkadrreq_dynamic() {
 
     let (device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(_)) => {}
+        Ok(SendResponse::DownlinkReceived { .. }) => {}
         _ => panic!(),
     }
 
@@ -96,6 +96,31 @@ async fn linkadrreq_dynamic() {
     assert_eq!(data, [3, 6]);
 }
 
+#[tokio::test]
+#[cfg(feature = "region-eu868")]
+async fn linkadrreq_nb_trans() {
+    let (radio, timer, mut device) =
+        util::session_with_region(crate::region::EU868::new_eu868().into());
+    let task = tokio::spawn(async move {
+        let response = device.send(&[1, 2, 3], 3, false).await;
+        (device, response)
+    });
+
+    fn nb_trans(_uplink: Option<Uplink>, _config: RfConfig, buf: &mut [u8]) -> usize {
+        // LinkADRReq - DR5, default channels, NbTrans = 3
+        build_frm_payload(buf, "0350070003", 1)
+    }
+
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(nb_trans).await;
+
+    let (device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::DownlinkReceived { .. })));
+    assert_eq!(device.mac.configuration.nb_trans, 3);
+    assert_eq!(device.mac.configuration.data_rate, crate::region::DR::_5);
+    assert_eq!(device.mac.get_session().unwrap().uplink.mac_commands(), [3, 7]);
+}
+
 #[tokio::test]
 #[cfg(feature = "region-us915")]
 async fn linkadrreq_fixed_125khz_extra_mask() {
@@ -121,7 +146,7 @@ async fn linkadrreq_fixed_125khz_extra_mask() {
 
     let (device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(_)) => {}
+        Ok(SendResponse::DownlinkReceived { .. }) => {}
         _ => panic!(),
     }
 
@@ -161,7 +186,7 @@ async fn linkaddreq_fixed_channel_mask_validation() {
 
     let (mut device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(_)) => {}
+        Ok(SendResponse::DownlinkReceived { .. }) => {}
         _ => panic!(),
     }
 
@@ -192,7 +217,7 @@ async fn linkaddreq_fixed_channel_mask_validation() {
 
     let (device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(_)) => {}
+        Ok(SendResponse::DownlinkReceived { .. }) => {}
         _ => panic!(),
     }
 
@@ -228,7 +253,7 @@ async fn linkaddreq_fixed_invalid_125khz() {
 
     let (mut device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(_)) => {}
+        Ok(SendResponse::DownlinkReceived { .. }) => {}
         _ => panic!(),
     }
 
@@ -260,7 +285,7 @@ async fn linkaddreq_fixed_invalid_125khz() {
 
     let (mut device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(_)) => {}
+        Ok(SendResponse::DownlinkReceived { .. }) => {}
         _ => panic!(),
     }
 
@@ -292,7 +317,7 @@ async fn linkaddreq_fixed_invalid_125khz() {
 
     let (mut device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(_)) => {}
+        Ok(SendResponse::DownlinkReceived { .. }) => {}
         _ => panic!(),
     }
 
@@ -325,7 +350,7 @@ async fn linkaddreq_fixed_invalid_125khz() {
 
     let (device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(_)) => {}
+        Ok(SendResponse::DownlinkReceived { .. }) => {}
         _ => panic!(),
     }
 
@@ -367,7 +392,7 @@ async fn linkadrreq_dynamic_invalid() {
 
     let (device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(_)) => {}
+        Ok(SendResponse::DownlinkReceived { .. }) => {}
         _ => panic!(),
     }
 
@@ -418,7 +443,7 @@ async fn newchannelreq_eu868_readonly() {
 
     let (device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(_)) => {}
+        Ok(SendResponse::DownlinkReceived { .. }) => {}
         _ => panic!(),
     }
 
@@ -452,7 +477,7 @@ async fn newchannelreq_eu868_invalid_dr() {
 
     let (device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(_)) => {}
+        Ok(SendResponse::DownlinkReceived { .. }) => {}
         _ => panic!(),
     }
 
@@ -486,7 +511,7 @@ async fn newchannelreq_fixed_region_ignore() {
 
     let (device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(_)) => {}
+        Ok(SendResponse::DownlinkReceived { .. }) => {}
         _ => panic!(),
     }
 
@@ -499,9 +524,282 @@ async fn newchannelreq_fixed_region_ignore() {
     }
 }
 
+#[tokio::test]
+#[cfg(feature = "region-eu868")]
+async fn dlchannelreq_eu868() {
+    let (radio, timer, mut async_device) =
+        util::session_with_region(crate::region::EU868::new_eu868().into());
+
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+
+    fn dlchannelreq(_uplink: Option<Uplink>, _config: RfConfig, buf: &mut [u8]) -> usize {
+        // DlChannelReq - RX1 frequency 867100000 for join channels 0..=2
+        // DlChannelReq - RX1 frequency 867100000 for undefined channel 5
+        // DlChannelReq - invalid RX1 frequency 915000000 for channel 0
+        build_frm_payload(buf, "0a00184f840a01184f840a02184f840a05184f840a00309e8b", 1)
+    }
+
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(dlchannelreq).await;
+
+    let (mut device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::DownlinkReceived { .. })));
+
+    let session = device.mac.get_session().unwrap();
+    let data = session.uplink.mac_commands();
+    assert_eq!(parse_uplink_mac_commands(data).count(), 5);
+    assert_eq!(data, [10, 3, 10, 3, 10, 3, 10, 1, 10, 2]);
+
+    let task = tokio::spawn(async move {
+        let response = device.send(&[1, 2, 3], 3, false).await;
+        (device, response)
+    });
+
+    // RX1 uses the downlink frequency of the channel
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    let rx_conf = radio.get_rxconfig().await.unwrap();
+    assert_eq!(rx_conf.rf.frequency, 867100000);
+    // RX2 is not affected
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    let rx_conf = radio.get_rxconfig().await.unwrap();
+    assert_eq!(rx_conf.rf.frequency, 869525000);
+
+    let (device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::RxComplete)));
+
+    // DlChannelAns is retained until a Class A downlink is received
+    let mut uplink = radio.get_last_uplink().await;
+    match uplink.get_payload() {
+        PhyPayload::Data(DataPayload::Encrypted(data)) => {
+            assert_eq!(data.fhdr().data(), [10, 3, 10, 3, 10, 3, 10, 1, 10, 2]);
+        }
+        _ => panic!(),
+    }
+    let session = device.mac.get_session().unwrap();
+    assert_eq!(session.uplink.mac_commands(), [10, 3, 10, 3, 10, 3, 10, 1, 10, 2]);
+}
+
+#[tokio::test]
+async fn dutycyclereq() {
+    let (radio, timer, mut async_device) = util::setup_with_session();
+    timer.stop_clock(0);
+
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+
+    fn dutycyclereq(_uplink: Option<Uplink>, _config: RfConfig, buf: &mut [u8]) -> usize {
+        // DutyCycleReq: MaxDCycle=4, ie: 1/16
+        build_frm_payload(buf, "0404", 1)
+    }
+
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(dutycyclereq).await;
+
+    let (mut device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::DownlinkReceived { .. })));
+    assert_eq!(device.mac.get_session().unwrap().uplink.mac_commands(), [4]);
+
+    let task = tokio::spawn(async move {
+        let response = device.send(&[1, 2, 3], 3, false).await;
+        (device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    let (mut device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::RxComplete)));
+
+    // The aggregated duty cycle applies to the uplink after the request
+    let airtime_ms =
+        radio.get_last_uplink().await.get_tx_config().rf.bb.time_on_air_us(Some(8), true, 17);
+    match device.send(&[1, 2, 3], 3, false).await {
+        Err(crate::async_device::Error::Mac(crate::mac::Error::DutyCycle { retry_after_ms })) => {
+            assert_eq!(retry_after_ms, airtime_ms.div_ceil(1000) as u64 * 16)
+        }
+        _ => panic!(),
+    }
+}
+
+#[tokio::test]
+async fn devstatusreq() {
+    let (radio, timer, mut async_device) = util::setup_with_session();
+    async_device.set_battery_level_callback(|| crate::mac::BatteryLevel::Level(200));
+
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+
+    fn devstatusreq(_uplink: Option<Uplink>, _config: RfConfig, buf: &mut [u8]) -> usize {
+        build_frm_payload(buf, "06", 1)
+    }
+
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(devstatusreq).await;
+
+    let (device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::DownlinkReceived { .. })));
+    // Battery level 200, margin -7 dB (SNR of the test radio) as a 6-bit signed integer
+    assert_eq!(device.mac.get_session().unwrap().uplink.mac_commands(), [6, 200, 0x39]);
+}
+
+#[tokio::test]
+async fn linkcheckreq() {
+    let (radio, timer, mut async_device) = util::setup_with_session();
+    async_device.request_link_check().unwrap();
+    // Requesting again doesn't duplicate the command
+    async_device.request_link_check().unwrap();
+    assert!(async_device.get_link_check().is_none());
+
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+
+    fn linkcheckans(uplink: Option<Uplink>, _config: RfConfig, buf: &mut [u8]) -> usize {
+        match uplink.unwrap().get_payload() {
+            PhyPayload::Data(DataPayload::Encrypted(data)) => {
+                assert_eq!(data.fhdr().data(), [2]);
+            }
+            _ => panic!(),
+        }
+        // LinkCheckAns: margin 20 dB, 3 gateways
+        build_frm_payload(buf, "021403", 1)
+    }
+
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(linkcheckans).await;
+
+    let (device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::DownlinkReceived { .. })));
+    let link_check = device.get_link_check().unwrap();
+    assert_eq!(link_check.margin, 20);
+    assert_eq!(link_check.gateway_count, 3);
+    // Nothing to answer
+    assert!(device.mac.get_session().unwrap().uplink.mac_commands().is_empty());
+}
+
+#[tokio::test]
+async fn devicetimereq() {
+    let (radio, timer, mut async_device) = util::setup_with_session();
+    timer.stop_clock(10_000);
+    async_device.request_device_time().unwrap();
+    assert!(async_device.get_network_time().is_none());
+
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+
+    fn devicetimeans(uplink: Option<Uplink>, _config: RfConfig, buf: &mut [u8]) -> usize {
+        match uplink.unwrap().get_payload() {
+            PhyPayload::Data(DataPayload::Encrypted(data)) => {
+                assert_eq!(data.fhdr().data(), [0x0d]);
+            }
+            _ => panic!(),
+        }
+        // DeviceTimeAns: 1_400_000_000 s + 128/256 s at the end of the uplink
+        build_frm_payload(buf, "0d004e725380", 1)
+    }
+
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(devicetimeans).await;
+
+    let (device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::DownlinkReceived { .. })));
+
+    timer.advance_clock(2_750);
+    let time = device.get_network_time().unwrap();
+    assert_eq!(time.seconds, 1_400_000_003);
+    assert_eq!(time.nanoseconds, 250_000_000);
+}
+
+#[tokio::test]
+#[cfg(feature = "region-as923-1")]
+async fn txparamsetup_as923() {
+    use lora_modulation::SpreadingFactor;
+
+    let (radio, timer, mut async_device) = util::session_with_region(
+        crate::region::Configuration::new(crate::region::Region::AS923_1),
+    );
+
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+
+    fn txparamsetup(_uplink: Option<Uplink>, _config: RfConfig, buf: &mut [u8]) -> usize {
+        // TXParamSetupReq: uplink and downlink dwell time, MaxEIRP=12 dBm
+        build_frm_payload(buf, "0932", 1)
+    }
+
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(txparamsetup).await;
+
+    let (mut device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::DownlinkReceived { .. })));
+
+    let session = device.mac.get_session().unwrap();
+    assert_eq!(session.uplink.mac_commands(), [9]);
+    // DR0 and DR1 can't be used with uplink dwell time
+    assert_eq!(device.mac.configuration.data_rate, crate::region::DR::_2);
+
+    let task = tokio::spawn(async move {
+        let response = device.send(&[1, 2, 3], 4, false).await;
+        (device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    let (_device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::RxComplete)));
+
+    let uplink = radio.get_last_uplink().await;
+    let tx_config = uplink.get_tx_config();
+    assert_eq!(tx_config.pw, 12);
+    assert_eq!(tx_config.rf.bb.sf, SpreadingFactor::_10);
+}
+
+#[tokio::test]
+#[cfg(feature = "region-eu868")]
+async fn txparamsetup_eu868_ignored() {
+    let (radio, timer, mut async_device) =
+        util::session_with_region(crate::region::EU868::new_eu868().into());
+
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+
+    fn txparamsetup(_uplink: Option<Uplink>, _config: RfConfig, buf: &mut [u8]) -> usize {
+        build_frm_payload(buf, "0932", 1)
+    }
+
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(txparamsetup).await;
+
+    let (device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::DownlinkReceived { .. })));
+
+    let session = device.mac.get_session().unwrap();
+    assert!(session.uplink.mac_commands().is_empty());
+    assert_eq!(device.mac.configuration.data_rate, crate::region::DR::_0);
+}
+
 #[tokio::test]
 #[cfg(all(feature = "region-eu868", feature = "experimental"))]
 async fn rxparamsetup_eu868() {
+    use lora_modulation::{Bandwidth, SpreadingFactor};
+
     // RXParamSetupAns command SHALL be added in the FOpts field
     // (if FPort is either missing or >0) or in the FRMPayload field (if FPort=0)
     // of all uplinks until a Class A downlink is received by the end-device.
@@ -527,7 +825,7 @@ async fn rxparamsetup_eu868() {
 
     let (mut device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(_)) => {}
+        Ok(SendResponse::DownlinkReceived { .. }) => {}
         _ => panic!(),
     }
 
@@ -537,6 +835,7 @@ async fn rxparamsetup_eu868() {
     assert_eq!(data, [5, 7]);
 
     let complete = send_await_complete.clone();
+    device.set_datarate(crate::region::DR::_5);
     let task = tokio::spawn(async move {
         let response = device.send(&[1, 2, 3], 4, false).await;
         let mut complete = complete.lock().await;
@@ -547,20 +846,19 @@ async fn rxparamsetup_eu868() {
     // RX1
     timer.fire_most_recent().await;
     radio.handle_timeout().await;
-    // TODO: Check for RX1 data rate once RX1DROffset is implemented
-    // let rx_conf = radio.get_rxconfig().await.unwrap();
-    // assert_eq!(rx_conf.rf.bb.sf, SpreadingFactor::..);
-    // assert_eq!(rx_conf.rf.bb.bw, Bandwidth::..);
+    // DR5 with RX1DROffset=2 is DR3 (SF9BW125)
+    let rx_conf = radio.get_rxconfig().await.unwrap();
+    assert_eq!(rx_conf.rf.bb.sf, SpreadingFactor::_9);
+    assert_eq!(rx_conf.rf.bb.bw, Bandwidth::_125KHz);
     // RX2
     timer.fire_most_recent().await;
     radio.handle_timeout().await;
     let rx_conf = radio.get_rxconfig().await.unwrap();
 
     assert_eq!(rx_conf.rf.frequency, 868525000);
-    // TODO: Test that rx2 data rate override works
     // SF10BW125
-    // assert_eq!(rx_conf.rf.bb.sf, SpreadingFactor::_10);
-    // assert_eq!(rx_conf.rf.bb.bw, Bandwidth::_125KHz);
+    assert_eq!(rx_conf.rf.bb.sf, SpreadingFactor::_10);
+    assert_eq!(rx_conf.rf.bb.bw, Bandwidth::_125KHz);
 
     // RxComplete (no answer)
     assert!(*send_await_complete.lock().await);
@@ -632,7 +930,7 @@ async fn rxparamsetup_eu868() {
 
     let (device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(_)) => {}
+        Ok(SendResponse::DownlinkReceived { .. }) => {}
         _ => panic!(),
     }
 
@@ -694,7 +992,7 @@ async fn maccommands_in_frmpayload() {
 
     let (device, response) = task.await.unwrap();
     match response {
-        Ok(SendResponse::DownlinkReceived(5)) => {}
+        Ok(SendResponse::DownlinkReceived { fcnt_down: 5, .. }) => {}
         _ => panic!(),
     }
 
@@ -702,7 +1000,7 @@ async fn maccommands_in_frmpayload() {
         let data = session.uplink.mac_commands();
         assert_eq!(parse_uplink_mac_commands(data).count(), 4);
         // LinkADRReq sends freq = 869525000, but this is invalid in US915
-        assert_eq!(device.mac.configuration.rx2_frequency, None);
+        assert_eq!(session.rx_settings.rx2_frequency, None);
         // TODO: Implement RxParamSetup and RxTimingSetup...
         // assert_eq!(data, [5, 7]);
     } else {
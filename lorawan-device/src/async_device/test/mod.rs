@@ -20,8 +20,13 @@ pub(crate) use util::{setup, setup_with_session};
 #[cfg(feature = "certification")]
 mod certification;
 
+mod adr;
+
 mod maccommands;
 
+#[cfg(feature = "class-b")]
+mod class_b;
+
 #[cfg(feature = "class-c")]
 mod class_c;
 
@@ -51,6 +56,54 @@ async fn test_join_rx1() {
     }
 }
 
+#[tokio::test]
+async fn test_join_dev_nonce_counter() {
+    let (radio, timer, mut async_device) = setup();
+    async_device.set_dev_nonce(41);
+    assert!(async_device.take_dev_nonce_update().is_none());
+    let task = tokio::spawn(async move {
+        let response = async_device.join(&get_otaa_credentials()).await;
+        (async_device, response)
+    });
+
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(handle_join_request::<3>).await;
+
+    let (mut device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(JoinResponse::JoinSuccess)));
+    match radio.get_last_uplink().await.get_payload() {
+        lorawan::parser::PhyPayload::JoinRequest(join_request) => {
+            assert_eq!(join_request.dev_nonce().as_ref(), &41u16.to_le_bytes());
+        }
+        _ => panic!(),
+    }
+    assert_eq!(device.get_dev_nonce(), 42);
+    assert_eq!(device.take_dev_nonce_update(), Some(42));
+    assert!(device.take_dev_nonce_update().is_none());
+
+    // The last DevNonce can be used, but the counter must not wrap around
+    device.set_dev_nonce(u16::MAX as u32);
+    let task = tokio::spawn(async move {
+        let response = device.join(&get_otaa_credentials()).await;
+        (device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(handle_join_request::<3>).await;
+    let (mut device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(JoinResponse::JoinSuccess)));
+    match radio.get_last_uplink().await.get_payload() {
+        lorawan::parser::PhyPayload::JoinRequest(join_request) => {
+            assert_eq!(join_request.dev_nonce().as_ref(), &u16::MAX.to_le_bytes());
+        }
+        _ => panic!(),
+    }
+    assert_eq!(device.take_dev_nonce_update(), Some(0x1_0000));
+    assert!(matches!(
+        device.join(&get_otaa_credentials()).await,
+        Err(Error::Mac(mac::Error::DevNonceExhausted))
+    ));
+}
+
 #[tokio::test]
 async fn test_join_rx2() {
     let (radio, timer, mut async_device) = setup();
@@ -75,6 +128,60 @@ async fn test_join_rx2() {
     }
 }
 
+/// Answer the join request with a channel mask CFList enabling the channels of `MASK`
+#[cfg(feature = "region-as923-1")]
+fn handle_join_request_with_channel_mask<const MASK: u8>(
+    _uplink: Option<Uplink>,
+    _config: RfConfig,
+    rx_buffer: &mut [u8],
+) -> usize {
+    let mut phy = lorawan::creator::JoinAcceptCreator::new(&mut rx_buffer[..33]).unwrap();
+    phy.set_app_nonce(&[1; 3]);
+    phy.set_net_id(&[1; 3]);
+    phy.set_dev_addr(get_dev_addr());
+    let channel_mask = lorawan::types::ChannelMask::new_from_raw(&[MASK, 0, 0, 0, 0, 0, 0, 0, 0]);
+    phy.set_c_f_list_channel_mask(&channel_mask).unwrap();
+    phy.build(&get_key().into(), &DefaultFactory).unwrap().len()
+}
+
+#[tokio::test]
+#[cfg(feature = "region-as923-1")]
+async fn test_join_cflist_channel_mask() {
+    let region = || region::Configuration::new(region::Region::AS923_1);
+    // Only the second default channel is enabled, a mask enabling undefined channels is ignored
+    let cases: [(RxTxHandler, &[u32]); 2] = [
+        (handle_join_request_with_channel_mask::<0b10>, &[923_400_000]),
+        (handle_join_request_with_channel_mask::<0b110>, &[923_200_000, 923_400_000]),
+    ];
+    for (handler, frequencies) in cases {
+        let (radio, timer, mut device) = util::setup_with_region(region());
+        let task = tokio::spawn(async move {
+            let response = device.join(&get_otaa_credentials()).await;
+            (device, response)
+        });
+        timer.fire_most_recent().await;
+        radio.handle_rxtx(handler).await;
+        let (mut device, response) = task.await.unwrap();
+        assert!(matches!(response, Ok(JoinResponse::JoinSuccess)));
+
+        for _ in 0..5 {
+            let task = tokio::spawn(async move {
+                let response = device.send(&[1, 2, 3], 3, false).await;
+                (device, response)
+            });
+            timer.fire_most_recent().await;
+            radio.handle_timeout().await;
+            timer.fire_most_recent().await;
+            radio.handle_timeout().await;
+            let (returned, response) = task.await.unwrap();
+            assert!(matches!(response, Ok(SendResponse::RxComplete)));
+            let uplink = radio.get_last_uplink().await;
+            assert!(frequencies.contains(&uplink.get_tx_config().rf.frequency));
+            device = returned;
+        }
+    }
+}
+
 #[tokio::test]
 async fn test_no_join_accept() {
     let (radio, timer, mut async_device) = setup();
@@ -132,6 +239,304 @@ async fn test_unconfirmed_uplink_no_downlink() {
     assert!(*send_await_complete.lock().await);
 }
 
+#[tokio::test]
+async fn test_uplink_payload_length() {
+    use lorawan::maccommandcreator::DevStatusAnsCreator;
+    use lorawan::parser::{AsPhyPayloadBytes, DataHeader, DataPayload, PhyPayload};
+
+    let (radio, timer, mut async_device) = setup_with_session();
+    // US915 DR0 allows 11 bytes of application payload
+    assert_eq!(async_device.available_payload_length(), 11);
+    let response = async_device.send(&[0; 12], 3, false).await;
+    assert!(matches!(response, Err(Error::Mac(crate::mac::Error::PayloadTooLarge))));
+
+    // MAC commands which don't fit FOpts are held back
+    async_device.set_datarate(region::DR::_3);
+    let mut session = async_device.mac.get_session().unwrap().clone();
+    for _ in 0..6 {
+        session.uplink.add_mac_command(DevStatusAnsCreator::new());
+    }
+    async_device.mac.set_session(session);
+    assert_eq!(async_device.available_payload_length(), 242 - 15);
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1], 3, false).await;
+        (async_device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    let (mut async_device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::RxComplete)));
+    match radio.get_last_uplink().await.get_payload() {
+        PhyPayload::Data(DataPayload::Encrypted(data)) => {
+            assert_eq!(data.f_port(), Some(3));
+            // Five DevStatusAns in FOpts
+            assert_eq!(data.fhdr().data().len(), 15);
+        }
+        _ => panic!(),
+    }
+    assert_eq!(async_device.mac.get_session().unwrap().uplink.mac_commands().len(), 3);
+    assert_eq!(async_device.available_payload_length(), 242 - 3);
+
+    // Uplinks without application payload send all the MAC commands, on FPort 0 if needed
+    let mut session = async_device.mac.get_session().unwrap().clone();
+    for _ in 0..5 {
+        session.uplink.add_mac_command(DevStatusAnsCreator::new());
+    }
+    async_device.mac.set_session(session);
+
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[], 3, false).await;
+        (async_device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    let (async_device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::RxComplete)));
+    match radio.get_last_uplink().await.get_payload() {
+        PhyPayload::Data(DataPayload::Encrypted(data)) => {
+            assert_eq!(data.f_port(), Some(0));
+            assert!(data.fhdr().data().is_empty());
+            // MHDR, FHDR, FPort, six DevStatusAns and MIC
+            assert_eq!(data.as_bytes().len(), 1 + 7 + 1 + 18 + 4);
+        }
+        _ => panic!(),
+    }
+    assert_eq!(async_device.available_payload_length(), 242);
+}
+
+#[tokio::test]
+async fn test_mac_commands_kept_when_uplink_not_sent() {
+    use lorawan::maccommandcreator::DevStatusAnsCreator;
+
+    let (_, _, mut async_device) = setup_with_session();
+    // US915 DR0 only fits three DevStatusAns in an uplink without application payload
+    let mut session = async_device.mac.get_session().unwrap().clone();
+    for _ in 0..5 {
+        session.uplink.add_mac_command(DevStatusAnsCreator::new());
+    }
+    async_device.mac.set_session(session);
+    let mut channel_mask = async_device.mac.region.channel_mask_get();
+    for channel in 0..72 {
+        channel_mask.set_channel(channel, false);
+    }
+    async_device.mac.region.channel_mask_set(channel_mask);
+
+    let response = async_device.send(&[], 3, false).await;
+    assert!(matches!(response, Err(Error::Mac(crate::mac::Error::NoChannelForDataRate))));
+    assert_eq!(async_device.mac.get_session().unwrap().uplink.mac_commands().len(), 15);
+}
+
+#[tokio::test]
+async fn test_send_mac_commands() {
+    use lorawan::maccommandcreator::DevStatusAnsCreator;
+    use lorawan::parser::{DataHeader, DataPayload, FRMPayload, PhyPayload};
+
+    let (radio, timer, mut async_device) = setup_with_session();
+    assert!(!async_device.has_pending_mac_commands());
+    // Nothing is sent without pending MAC commands
+    let response = async_device.send_mac_commands().await;
+    assert!(matches!(response, Err(Error::Mac(crate::mac::Error::NoMacCommands))));
+    assert_eq!(async_device.mac.get_fcnt_up(), Some(0));
+    let mut session = async_device.mac.get_session().unwrap().clone();
+    session.uplink.add_mac_command(DevStatusAnsCreator::new());
+    async_device.mac.set_session(session);
+    assert!(async_device.has_pending_mac_commands());
+
+    let task = tokio::spawn(async move {
+        let response = async_device.send_mac_commands().await;
+        (async_device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    let (async_device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::RxComplete)));
+    assert!(!async_device.has_pending_mac_commands());
+    match radio.get_last_uplink().await.get_payload() {
+        PhyPayload::Data(DataPayload::Encrypted(data)) => {
+            assert_eq!(data.f_port(), Some(0));
+            assert!(data.fhdr().data().is_empty());
+            let data = data.decrypt(Some(&get_key().into()), None, 0).unwrap();
+            match data.frm_payload() {
+                FRMPayload::MACCommands(cmds) => assert_eq!(cmds.data(), [0x06, 0, 0]),
+                _ => panic!(),
+            }
+        }
+        _ => panic!(),
+    }
+}
+
+#[tokio::test]
+async fn test_unconfirmed_uplink_nb_trans() {
+    let (radio, timer, mut async_device) = setup_with_session();
+    async_device.mac.configuration.nb_trans = 3;
+
+    let async_device = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+    // Each transmission opens both RX windows and repeats the same frame counter
+    for _ in 0..3 {
+        timer.fire_most_recent().await;
+        radio.handle_timeout().await;
+        timer.fire_most_recent().await;
+        radio.handle_timeout().await;
+        let mut uplink = radio.get_last_uplink().await;
+        match uplink.get_payload() {
+            lorawan::parser::PhyPayload::Data(lorawan::parser::DataPayload::Encrypted(data)) => {
+                use lorawan::parser::DataHeader;
+                assert_eq!(data.fhdr().fcnt(), 0);
+            }
+            _ => panic!(),
+        }
+    }
+
+    let (async_device, response) = async_device.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::RxComplete)));
+    assert_eq!(async_device.mac.get_fcnt_up(), Some(1));
+}
+
+#[tokio::test]
+#[cfg(feature = "region-eu868")]
+async fn test_uplink_duty_cycle_eu868() {
+    use lora_modulation::{Bandwidth, BaseBandModulationParams, CodingRate, SpreadingFactor};
+
+    let (radio, timer, mut async_device) =
+        util::session_with_region(crate::region::EU868::new_eu868().into());
+    timer.stop_clock(0);
+
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    let (mut async_device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::RxComplete)));
+
+    // All default channels are in the same 1% sub-band; 16 bytes at DR0
+    let airtime_ms =
+        BaseBandModulationParams::new(SpreadingFactor::_12, Bandwidth::_125KHz, CodingRate::_4_5)
+            .time_on_air_us(Some(8), true, 16)
+            .div_ceil(1000) as u64;
+    match async_device.send(&[1, 2, 3], 3, false).await {
+        Err(Error::Mac(mac::Error::DutyCycle { retry_after_ms })) => {
+            assert_eq!(retry_after_ms, airtime_ms * 100)
+        }
+        _ => panic!(),
+    }
+    // Blocked uplinks don't use up a frame counter
+    assert_eq!(async_device.mac.get_fcnt_up(), Some(1));
+
+    timer.advance_clock(airtime_ms * 100);
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    let (async_device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::RxComplete)));
+    assert_eq!(async_device.mac.get_fcnt_up(), Some(2));
+}
+
+#[tokio::test]
+#[cfg(feature = "region-kr920")]
+async fn test_uplink_listen_before_talk_kr920() {
+    let (radio, timer, mut async_device) =
+        util::session_with_region(region::Configuration::new(region::Region::KR920));
+    radio.set_channel_busy(true);
+    assert!(matches!(
+        async_device.send(&[1, 2, 3], 3, false).await,
+        Err(Error::Mac(mac::Error::ChannelBusy))
+    ));
+
+    radio.set_channel_busy(false);
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    let (_, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::RxComplete)));
+    assert_eq!(radio.get_last_uplink().await.get_tx_config().pw, 14);
+}
+
+#[tokio::test]
+#[cfg(feature = "region-kr920")]
+async fn test_unconfirmed_uplink_nb_trans_failed_repetition() {
+    use lorawan::parser::{DataHeader, DataPayload, PhyPayload};
+
+    let (radio, timer, mut async_device) =
+        util::session_with_region(region::Configuration::new(region::Region::KR920));
+    async_device.mac.configuration.nb_trans = 3;
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+    for repetition in 0..2 {
+        timer.fire_most_recent().await;
+        radio.handle_timeout().await;
+        timer.fire_most_recent().await;
+        // The second repetition fails listen-before-talk
+        radio.set_channel_busy(repetition == 1);
+        radio.handle_timeout().await;
+    }
+    let (mut async_device, response) = task.await.unwrap();
+    assert!(matches!(response, Err(Error::Mac(mac::Error::ChannelBusy))));
+    // The frame has been on air, so the next uplink uses a new frame counter
+    assert_eq!(async_device.mac.get_fcnt_up(), Some(1));
+
+    radio.set_channel_busy(false);
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[4, 5, 6], 3, false).await;
+        (async_device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(data_downlink::<1, 0>).await;
+    let (_, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::DownlinkReceived { .. })));
+    match radio.get_last_uplink().await.get_payload() {
+        PhyPayload::Data(DataPayload::Encrypted(data)) => assert_eq!(data.fhdr().fcnt(), 1),
+        _ => panic!(),
+    }
+}
+
+#[tokio::test]
+async fn test_unconfirmed_uplink_nb_trans_stops_on_downlink() {
+    let (radio, timer, mut async_device) = setup_with_session();
+    async_device.mac.configuration.nb_trans = 3;
+
+    let async_device = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+    // First transmission is not answered...
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    // ...but the repetition is, which ends the transmissions
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(util::handle_class_c_uplink_after_join).await;
+
+    let (async_device, response) = async_device.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::DownlinkReceived { fcnt_down: 0, .. })));
+    assert_eq!(async_device.mac.get_fcnt_up(), Some(1));
+}
+
 #[tokio::test]
 async fn test_confirmed_uplink_no_ack() {
     let (radio, timer, mut async_device) = setup_with_session();
@@ -158,12 +563,181 @@ async fn test_confirmed_uplink_no_ack() {
     radio.handle_timeout().await;
 
     match async_device.await.unwrap() {
-        Ok(SendResponse::NoAck) => (),
+        Ok(SendResponse::NoAck { .. }) => (),
         _ => panic!(),
     }
     assert!(*send_await_complete.lock().await);
 }
 
+#[tokio::test]
+async fn test_confirmed_uplink_retries_no_ack() {
+    let (radio, timer, mut async_device) = setup_with_session();
+    async_device.set_datarate(region::DR::_3);
+    async_device.set_confirmed_retry_policy(ConfirmedRetryPolicy {
+        max_attempts: 4,
+        datarate_step_down: true,
+    });
+
+    let async_device = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, true).await;
+        (async_device, response)
+    });
+    for attempt in 0..4 {
+        if attempt > 0 {
+            // ACK_TIMEOUT before retransmission
+            timer.fire_most_recent().await;
+        }
+        timer.fire_most_recent().await;
+        radio.handle_timeout().await;
+        timer.fire_most_recent().await;
+        radio.handle_timeout().await;
+        let mut uplink = radio.get_last_uplink().await;
+        match uplink.get_payload() {
+            lorawan::parser::PhyPayload::Data(lorawan::parser::DataPayload::Encrypted(data)) => {
+                use lorawan::parser::DataHeader;
+                assert!(data.is_confirmed());
+                assert_eq!(data.fhdr().fcnt(), 0);
+            }
+            _ => panic!(),
+        }
+    }
+
+    let (mut async_device, response) = async_device.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::NoAck { attempts: 4 })));
+    assert_eq!(async_device.mac.get_fcnt_up(), Some(1));
+    // Data rate was decreased before the third transmission only
+    assert_eq!(async_device.get_datarate(), region::DR::_2);
+}
+
+#[tokio::test]
+async fn test_confirmed_uplink_retries_until_ack() {
+    let (radio, timer, mut async_device) = setup_with_session();
+    async_device.set_confirmed_retry_policy(ConfirmedRetryPolicy {
+        max_attempts: 8,
+        datarate_step_down: false,
+    });
+
+    let async_device = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, true).await;
+        (async_device, response)
+    });
+    // First transmission is not acknowledged...
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    // ...but the retransmission is
+    timer.fire_most_recent().await;
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(handle_data_uplink_with_link_adr_req::<0, 0>).await;
+
+    let (async_device, response) = async_device.await.unwrap();
+    assert!(matches!(
+        response,
+        Ok(SendResponse::DownlinkReceived { fcnt_down: 0, attempts: 2, .. })
+    ));
+    assert_eq!(async_device.mac.get_fcnt_up(), Some(1));
+}
+
+fn data_downlink<const FCNT: u32, const FCTRL: u8>(
+    _: Option<Uplink>,
+    _: RfConfig,
+    rx_buffer: &mut [u8],
+) -> usize {
+    let mut phy = lorawan::creator::DataPayloadCreator::new(rx_buffer).unwrap();
+    phy.set_f_port(1);
+    phy.set_dev_addr(&[0; 4]);
+    phy.set_uplink(false);
+    phy.set_fcnt(FCNT);
+    phy.set_fctrl(&lorawan::parser::FCtrl::new(FCTRL, false));
+    let finished =
+        phy.build(&[1], [], &get_key().into(), &get_key().into(), &DefaultFactory).unwrap();
+    finished.len()
+}
+
+#[tokio::test]
+async fn test_confirmed_uplink_downlink_without_ack() {
+    let (radio, timer, mut async_device) = setup_with_session();
+    let async_device = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, true).await;
+        (async_device, response)
+    });
+    // A downlink without the ACK bit doesn't acknowledge the uplink
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(data_downlink::<1, 0>).await;
+
+    let (mut async_device, response) = async_device.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::NoAck { attempts: 1 })));
+    assert_eq!(async_device.mac.get_fcnt_up(), Some(1));
+    assert_eq!(async_device.take_downlink().unwrap().data, [1]);
+}
+
+#[tokio::test]
+async fn test_f_pending_uplinks() {
+    let (radio, timer, mut async_device) = setup_with_session();
+    async_device.set_f_pending_uplinks(1);
+    let async_device = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+    // Downlink with FPending set...
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(data_downlink::<1, 0x10>).await;
+    // ...is followed by an empty uplink to let the network send the next one
+    tokio::time::sleep(tokio::time::Duration::from_millis(15)).await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+
+    let (async_device, response) = async_device.await.unwrap();
+    assert!(matches!(
+        response,
+        Ok(SendResponse::DownlinkReceived { fcnt_down: 1, ack: false, f_pending: true, .. })
+    ));
+    assert_eq!(async_device.mac.get_fcnt_up(), Some(2));
+    let mut uplink = radio.get_last_uplink().await;
+    match uplink.get_payload() {
+        lorawan::parser::PhyPayload::Data(lorawan::parser::DataPayload::Encrypted(data)) => {
+            use lorawan::parser::DataHeader;
+            assert_eq!(data.fhdr().fcnt(), 1);
+            assert!(data.f_port().is_none());
+        }
+        _ => panic!(),
+    }
+}
+
+#[tokio::test]
+async fn test_f_pending_uplinks_downlink_buffer_full() {
+    let (radio, timer, mut async_device) = setup_with_session();
+    async_device.set_f_pending_uplinks(5);
+    let async_device = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(data_downlink::<1, 0x10>).await;
+    // The uplinks for FPending receive the next downlinks...
+    tokio::time::sleep(tokio::time::Duration::from_millis(15)).await;
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(data_downlink::<2, 0x10>).await;
+    tokio::time::sleep(tokio::time::Duration::from_millis(15)).await;
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(data_downlink::<3, 0x10>).await;
+    tokio::time::sleep(tokio::time::Duration::from_millis(15)).await;
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(data_downlink::<4, 0x10>).await;
+
+    // ...until the buffer of four downlinks is full
+    let (mut async_device, response) = async_device.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::DownlinkReceived { fcnt_down: 1, .. })));
+    assert_eq!(async_device.mac.get_fcnt_up(), Some(4));
+    for fcnt_down in (1..=4).rev() {
+        assert_eq!(async_device.take_downlink().unwrap().metadata.fcnt_down, fcnt_down);
+    }
+    assert!(async_device.take_downlink().is_none());
+}
+
 #[tokio::test]
 async fn test_confirmed_uplink_with_ack_rx1() {
     let (radio, timer, mut async_device) = setup_with_session();
@@ -185,7 +759,7 @@ async fn test_confirmed_uplink_with_ack_rx1() {
     // Send a downlink with confirmation
     radio.handle_rxtx(handle_data_uplink_with_link_adr_req::<0, 0>).await;
     match async_device.await.unwrap() {
-        Ok(SendResponse::DownlinkReceived(_)) => (),
+        Ok(SendResponse::DownlinkReceived { .. }) => (),
         _ => {
             panic!()
         }
@@ -219,7 +793,7 @@ async fn test_confirmed_uplink_with_ack_rx2() {
     radio.handle_rxtx(handle_data_uplink_with_link_adr_req::<0, 0>).await;
 
     match async_device.await.unwrap() {
-        Ok(SendResponse::DownlinkReceived(_)) => (),
+        Ok(SendResponse::DownlinkReceived { .. }) => (),
         _ => {
             panic!()
         }
@@ -253,7 +827,7 @@ async fn test_link_adr_ans() {
     // Send a downlink with confirmation
     radio.handle_rxtx(handle_data_uplink_with_link_adr_ans).await;
     match async_device.await.unwrap() {
-        Ok(SendResponse::DownlinkReceived(_)) => (),
+        Ok(SendResponse::DownlinkReceived { .. }) => (),
         _ => {
             panic!()
         }
@@ -299,7 +873,7 @@ async fn invalid_maccommands_in_frmpayload() {
 
     let (mut device, task) = task.await.unwrap();
     match task {
-        Ok(SendResponse::DownlinkReceived(16)) => {
+        Ok(SendResponse::DownlinkReceived { fcnt_down: 16, .. }) => {
             // Nothing in downlink as expected
             assert!(device.take_downlink().is_none());
         }
@@ -308,3 +882,366 @@ async fn invalid_maccommands_in_frmpayload() {
         }
     }
 }
+
+#[tokio::test]
+async fn test_fcnt_down_rollover_and_replay() {
+    fn downlink(rx_buffer: &mut [u8], fcnt: u32) -> usize {
+        let mut phy = lorawan::creator::DataPayloadCreator::new(rx_buffer).unwrap();
+        phy.set_confirmed(false);
+        phy.set_f_port(1);
+        phy.set_dev_addr(&[0; 4]);
+        phy.set_uplink(false);
+        phy.set_fcnt(fcnt);
+        let finished =
+            phy.build(&[1], [], &get_key().into(), &get_key().into(), &DefaultFactory).unwrap();
+        finished.len()
+    }
+    fn downlink_after_rollover(_: Option<Uplink>, _: RfConfig, rx_buffer: &mut [u8]) -> usize {
+        // FHDR only carries the 16 LSBs: 0x0001
+        downlink(rx_buffer, 0x1_0001)
+    }
+
+    let (radio, timer, mut async_device) = util::setup_with_session();
+    let mut session = async_device.mac.get_session().unwrap().clone();
+    session.fcnt_down = 0xFFFE;
+    async_device.mac.set_session(session);
+
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(downlink_after_rollover).await;
+
+    let (mut device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::DownlinkReceived { fcnt_down: 0x1_0001, .. })));
+    assert_eq!(device.take_downlink().unwrap().data, [1]);
+    assert_eq!(device.mac.get_session().unwrap().fcnt_down, 0x1_0001);
+
+    // A replayed frame in RX2 is ignored
+    let task = tokio::spawn(async move {
+        let response = device.send(&[1, 2, 3], 3, false).await;
+        (device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(downlink_after_rollover).await;
+
+    let (mut device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::RxComplete)));
+    assert!(device.take_downlink().is_none());
+}
+
+#[tokio::test]
+async fn test_downlink_metadata() {
+    let (radio, timer, mut async_device) = setup_with_session();
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(data_downlink::<1, 0>).await;
+    let (mut async_device, _) = task.await.unwrap();
+    let metadata = async_device.take_downlink().unwrap().metadata;
+    assert_eq!((metadata.rssi, metadata.snr), (-80, -7));
+    assert_eq!(metadata.window, RxWindow::Rx1);
+    assert_eq!(metadata.frequency, radio.get_rxconfig().await.unwrap().rf.frequency);
+    // RX1 data rate of US915 DR0 uplinks
+    assert_eq!(metadata.datarate, region::DR::_10);
+    assert_eq!(metadata.fcnt_down, 1);
+
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(data_downlink::<2, 0>).await;
+    let (mut async_device, _) = task.await.unwrap();
+    let metadata = async_device.take_downlink().unwrap().metadata;
+    assert_eq!(metadata.window, RxWindow::Rx2);
+    assert_eq!(metadata.frequency, 923_300_000);
+    assert_eq!(metadata.datarate, region::DR::_8);
+    assert_eq!(metadata.fcnt_down, 2);
+}
+
+#[tokio::test]
+async fn test_lorawan_1_1_session() {
+    use crate::{AppEui, AppKey, DevEui};
+    use lorawan::keys::{FNwkSIntKey, NwkKey};
+    use lorawan::maccommandcreator::RekeyConfCreator;
+    use lorawan::maccommands::{MacCommandIterator, UplinkMacCommand};
+    use lorawan::parser::{DataHeader, DataPayload, PhyPayload};
+    use lorawan::types::JoinReqType;
+
+    static SESSION: std::sync::Mutex<Option<Session>> = std::sync::Mutex::new(None);
+
+    fn nwk_key() -> NwkKey {
+        NwkKey::from([1; 16])
+    }
+
+    fn join_accept(uplink: Option<Uplink>, _: RfConfig, rx_buffer: &mut [u8]) -> usize {
+        let mut uplink = uplink.unwrap();
+        let PhyPayload::JoinRequest(join_request) = uplink.get_payload() else {
+            panic!("Did not parse join request from uplink");
+        };
+        assert!(join_request.validate_mic(nwk_key().inner()));
+        let js_int_key = nwk_key().derive_js_int_key(&DefaultFactory, &DevEui::from([0; 8]));
+        let mut phy = lorawan::creator::JoinAcceptCreator::new(rx_buffer).unwrap();
+        phy.set_app_nonce(&[1; 3]);
+        phy.set_net_id(&[1; 3]);
+        phy.set_dev_addr(get_dev_addr());
+        // OptNeg is set by LoRaWAN 1.1 network servers
+        phy.set_dl_settings(0x80);
+        let finished = phy
+            .build_1_1(
+                nwk_key().inner(),
+                &js_int_key,
+                JoinReqType::JoinRequest,
+                &AppEui::from([0; 8]),
+                &join_request.dev_nonce(),
+                &DefaultFactory,
+            )
+            .unwrap();
+        finished.len()
+    }
+
+    fn rekey_conf(uplink: Option<Uplink>, _: RfConfig, rx_buffer: &mut [u8]) -> usize {
+        let session = SESSION.lock().unwrap().clone().unwrap();
+        let session_1_1 = session.lorawan_1_1.unwrap();
+        let mut uplink = uplink.unwrap();
+        let PhyPayload::Data(DataPayload::Encrypted(data)) = uplink.get_payload() else {
+            panic!("Did not decode PhyPayload::Data!");
+        };
+        let fcnt = data.fhdr().fcnt() as u32;
+        let decrypted = data
+            .decrypt_1_1(Some(session_1_1.nwksenckey.inner()), Some(session.appskey.inner()), fcnt)
+            .unwrap();
+        // RekeyInd is sent in the encrypted FOpts
+        let fhdr = decrypted.fhdr();
+        let mut cmds = MacCommandIterator::<UplinkMacCommand<'_>>::new(fhdr.data());
+        assert!(matches!(
+            cmds.next(),
+            Some(UplinkMacCommand::RekeyInd(payload)) if payload.dev_lorawan_version() == 1
+        ));
+
+        let mut cmd = RekeyConfCreator::new();
+        cmd.set_serv_lorawan_version(1);
+        let mut phy = lorawan::creator::DataPayloadCreator::new(rx_buffer).unwrap();
+        phy.set_uplink(false);
+        phy.set_dev_addr(get_dev_addr());
+        phy.set_fcnt(0);
+        let finished = phy
+            .build_1_1(
+                &[],
+                cmd.build(),
+                &FNwkSIntKey::from(session.nwkskey.inner().0),
+                &session_1_1.snwksintkey,
+                &session_1_1.nwksenckey,
+                &session.appskey,
+                &DefaultFactory,
+            )
+            .unwrap();
+        finished.len()
+    }
+
+    let (radio, timer, mut async_device) = util::setup();
+    let join_mode = JoinMode::OTAA1_1 {
+        deveui: DevEui::from([0; 8]),
+        appeui: AppEui::from([0; 8]),
+        appkey: AppKey::from(get_key()),
+        nwkkey: nwk_key(),
+    };
+    let task = tokio::spawn(async move {
+        let response = async_device.join(&join_mode).await;
+        (async_device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(join_accept).await;
+
+    let (mut device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(JoinResponse::JoinSuccess)));
+    let session = device.get_session().unwrap().clone();
+    assert!(session.lorawan_1_1.is_some());
+    *SESSION.lock().unwrap() = Some(session.clone());
+
+    let task = tokio::spawn(async move {
+        let response = device.send(&[1, 2, 3], 3, false).await;
+        (device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(rekey_conf).await;
+
+    let (mut device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::DownlinkReceived { fcnt_down: 0, .. })));
+    let session_1_1 = device.get_session().unwrap().lorawan_1_1.clone().unwrap();
+    assert!(session_1_1.rekey_confirmed);
+
+    // The uplink MIC covers the data rate and channel of the transmission
+    let tx_dr = device.get_datarate() as u8;
+    let tx_ch = device.mac.region.get_last_tx_channel();
+    match radio.get_last_uplink().await.get_payload() {
+        PhyPayload::Data(DataPayload::Encrypted(data)) => {
+            let (f_key, s_key) = (session.nwkskey.inner(), session_1_1.snwksintkey.inner());
+            assert!(data.validate_uplink_mic_1_1(f_key, s_key, 0, 0, tx_dr, tx_ch));
+            assert!(!data.validate_uplink_mic_1_1(f_key, s_key, 0, 0, tx_dr, tx_ch + 1));
+        }
+        _ => panic!(),
+    }
+}
+
+#[tokio::test]
+async fn test_force_rejoin() {
+    use crate::mac::{Rejoin, Session1_1};
+    use crate::{AppEui, AppKey, AppSKey, DevEui};
+    use lorawan::keys::{FNwkSIntKey, NwkKey, NwkSEncKey, SNwkSIntKey};
+    use lorawan::maccommandcreator::ForceRejoinReqCreator;
+    use lorawan::parser::{DevNonce, PhyPayload};
+    use lorawan::types::JoinReqType;
+
+    fn nwk_key() -> NwkKey {
+        NwkKey::from([1; 16])
+    }
+
+    fn force_rejoin_req(_: Option<Uplink>, _: RfConfig, rx_buffer: &mut [u8]) -> usize {
+        let mut cmd = ForceRejoinReqCreator::new();
+        cmd.set_rejoin_type(RejoinType::Type2).set_data_rate(1);
+        let mut phy = lorawan::creator::DataPayloadCreator::new(rx_buffer).unwrap();
+        phy.set_uplink(false);
+        phy.set_dev_addr(get_dev_addr());
+        phy.set_fcnt(0);
+        let finished = phy
+            .build_1_1(
+                &[],
+                cmd.build(),
+                &FNwkSIntKey::from(get_key()),
+                &SNwkSIntKey::from([3; 16]),
+                &NwkSEncKey::from([4; 16]),
+                &AppSKey::from(get_key()),
+                &DefaultFactory,
+            )
+            .unwrap();
+        finished.len()
+    }
+
+    fn rejoin_accept(uplink: Option<Uplink>, _: RfConfig, rx_buffer: &mut [u8]) -> usize {
+        let mut uplink = uplink.unwrap();
+        // The rejoin-request is sent at the data rate requested by the network
+        assert_eq!(uplink.get_tx_config().rf.bb.sf, lora_modulation::SpreadingFactor::_9);
+        let PhyPayload::RejoinRequest(rejoin_request) = uplink.get_payload() else {
+            panic!("Did not parse rejoin-request from uplink");
+        };
+        assert_eq!(rejoin_request.rejoin_type(), RejoinType::Type2);
+        assert_eq!(rejoin_request.net_id().unwrap().as_ref(), &[1, 2, 3]);
+        assert_eq!(rejoin_request.rj_count(), 0);
+        assert!(rejoin_request.validate_mic(SNwkSIntKey::from([3; 16]).inner()));
+
+        let dev_eui = DevEui::from([0; 8]);
+        let js_enc_key = nwk_key().derive_js_enc_key(&DefaultFactory, &dev_eui);
+        let js_int_key = nwk_key().derive_js_int_key(&DefaultFactory, &dev_eui);
+        let mut phy = lorawan::creator::JoinAcceptCreator::new(rx_buffer).unwrap();
+        phy.set_app_nonce(&[1; 3]);
+        phy.set_net_id(&[1, 2, 3]);
+        phy.set_dev_addr(&[4; 4]);
+        phy.set_dl_settings(0x80);
+        let finished = phy
+            .build_1_1(
+                js_enc_key.inner(),
+                &js_int_key,
+                JoinReqType::RejoinRequest2,
+                &AppEui::from([0; 8]),
+                &DevNonce::from(0u16.to_le_bytes()),
+                &DefaultFactory,
+            )
+            .unwrap();
+        finished.len()
+    }
+
+    let (radio, timer, mut device) = util::setup_with_session();
+    let mut session = device.get_session().unwrap().clone();
+    session.lorawan_1_1 = Some(Session1_1 {
+        snwksintkey: [3; 16].into(),
+        nwksenckey: [4; 16].into(),
+        afcnt_down: 0,
+        conf_fcnt_down: 0,
+        rekey_confirmed: true,
+        rejoin: Rejoin::new([1, 2, 3]),
+    });
+    device.mac.set_session(session);
+    let join_mode = JoinMode::OTAA1_1 {
+        deveui: DevEui::from([0; 8]),
+        appeui: AppEui::from([0; 8]),
+        appkey: AppKey::from(get_key()),
+        nwkkey: nwk_key(),
+    };
+    // A LoRaWAN 1.0 join mode has no NwkKey for rejoin-requests
+    assert!(matches!(
+        device.rejoin(&get_otaa_credentials(), RejoinType::Type0).await,
+        Err(Error::Mac(mac::Error::RejoinUnavailable))
+    ));
+
+    let task = tokio::spawn(async move {
+        let response = device.send(&[1, 2, 3], 3, false).await;
+        (device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(force_rejoin_req).await;
+    let (mut device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::DownlinkReceived { fcnt_down: 0, .. })));
+    assert_eq!(device.rejoin_due(), Some(RejoinType::Type2));
+
+    let task = tokio::spawn(async move {
+        let response = device.rejoin(&join_mode, RejoinType::Type2).await;
+        (device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(rejoin_accept).await;
+    let (mut device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(JoinResponse::JoinSuccess)));
+    let session = device.get_session().unwrap();
+    assert_eq!(session.devaddr, lorawan::parser::DevAddr::from([4; 4]));
+    assert_eq!(session.fcnt_up, 0);
+    // The forced rejoin-request had no retries left
+    assert_eq!(device.rejoin_due(), None);
+}
+
+#[tokio::test]
+async fn test_snapshot_restore() {
+    use lorawan::parser::{DataHeader, DataPayload, PhyPayload};
+
+    let (_, _, mut async_device) = util::setup_with_session();
+    let mut session = async_device.mac.get_session().unwrap().clone();
+    session.fcnt_up = 10;
+    session.fcnt_down = 5;
+    session.rx_settings.rx1_dr_offset = 2;
+    async_device.mac.set_session(session);
+    async_device.set_datarate(region::DR::_3);
+    let bytes: heapless::Vec<u8, { mac::MAX_SNAPSHOT_LEN }> =
+        heapless::Vec::from_slice(async_device.snapshot().as_bytes()).unwrap();
+
+    let (radio, timer, mut async_device) = util::setup();
+    async_device.restore(&Snapshot::from_bytes(&bytes).unwrap()).unwrap();
+    assert_eq!(async_device.get_datarate(), region::DR::_3);
+    let session = async_device.get_session().unwrap();
+    assert_eq!(session.fcnt_down, 5);
+    assert_eq!(session.rx_settings.rx1_dr_offset, 2);
+
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+
+    let (device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::RxComplete)));
+    match radio.get_last_uplink().await.get_payload() {
+        PhyPayload::Data(DataPayload::Encrypted(data)) => assert_eq!(data.fhdr().fcnt(), 10),
+        _ => panic!(),
+    }
+    assert_eq!(device.mac.get_session().unwrap().fcnt_up, 11);
+}
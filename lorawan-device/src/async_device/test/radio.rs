@@ -1,5 +1,6 @@
 use super::*;
-use crate::async_device::radio::{PhyRxTx, RxConfig, RxStatus};
+use crate::async_device::radio::{Lbt, PhyRxTx, RxConfig, RxStatus};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::{
     sync::{mpsc, Mutex},
@@ -10,13 +11,15 @@ impl TestRadio {
         let (tx, rx) = mpsc::channel(2);
         let last_rxconfig = Arc::new(Mutex::new(None));
         let last_uplink = Arc::new(Mutex::new(None));
+        let channel_busy = Arc::new(AtomicBool::new(false));
         (
             RadioChannel {
                 tx,
                 last_uplink: last_uplink.clone(),
                 last_rxconfig: last_rxconfig.clone(),
+                channel_busy: channel_busy.clone(),
             },
-            Self { rx, last_rxconfig, last_uplink, current_config: None },
+            Self { rx, last_rxconfig, last_uplink, current_config: None, channel_busy },
         )
     }
 }
@@ -32,6 +35,7 @@ pub struct TestRadio {
     last_rxconfig: Arc<Mutex<Option<RxConfig>>>,
     last_uplink: Arc<Mutex<Option<Uplink>>>,
     rx: mpsc::Receiver<Msg>,
+    channel_busy: Arc<AtomicBool>,
 }
 
 impl PhyRxTx for TestRadio {
@@ -49,6 +53,14 @@ impl PhyRxTx for TestRadio {
         Ok(length as u32)
     }
 
+    async fn listen_before_talk(
+        &mut self,
+        _config: RfConfig,
+        _lbt: Lbt,
+    ) -> Result<bool, Self::PhyError> {
+        Ok(!self.channel_busy.load(Ordering::SeqCst))
+    }
+
     async fn setup_rx(&mut self, config: RxConfig) -> Result<(), Self::PhyError> {
         self.current_config = Some(config);
         // Make current rx configuration available for test harness
@@ -69,7 +81,7 @@ impl PhyRxTx for TestRadio {
                 time::sleep(time::Duration::from_millis(5)).await;
                 if let Some(config) = &self.current_config {
                     let length = handler(last_uplink.clone(), config.rf, rx_buf);
-                    Ok((length, RxQuality::new(-80, 0)))
+                    Ok((length, RxQuality::new(-80, -7)))
                 } else {
                     panic!("Trying to rx before settings config!")
                 }
@@ -86,7 +98,7 @@ impl PhyRxTx for TestRadio {
                 time::sleep(time::Duration::from_millis(5)).await;
                 if let Some(config) = &self.current_config {
                     let length = handler(last_uplink.clone(), config.rf, rx_buf);
-                    Ok(RxStatus::Rx(length, RxQuality::new(-80, 0)))
+                    Ok(RxStatus::Rx(length, RxQuality::new(-80, -7)))
                 } else {
                     panic!("Trying to rx before settings config!")
                 }
@@ -108,6 +120,7 @@ pub struct RadioChannel {
     last_rxconfig: Arc<Mutex<Option<RxConfig>>>,
     last_uplink: Arc<Mutex<Option<Uplink>>>,
     tx: mpsc::Sender<Msg>,
+    channel_busy: Arc<AtomicBool>,
 }
 
 impl RadioChannel {
@@ -120,6 +133,12 @@ impl RadioChannel {
         self.tx.send(Msg::Timeout).await.unwrap();
     }
 
+    /// Let listen-before-talk find the channel busy.
+    #[allow(unused)]
+    pub fn set_channel_busy(&self, busy: bool) {
+        self.channel_busy.store(busy, Ordering::SeqCst);
+    }
+
     pub async fn get_rxconfig(&self) -> Option<RxConfig> {
         let rxconf = self.last_rxconfig.lock().await;
         *rxconf
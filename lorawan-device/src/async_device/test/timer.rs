@@ -1,4 +1,5 @@
 use crate::async_device::radio::Timer;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::{collections::HashMap, sync::Arc};
 use tokio::sync::{mpsc, Mutex};
 
@@ -6,16 +7,27 @@ impl TestTimer {
     pub fn new() -> (TimerChannel, Self) {
         let tx = Arc::new(Mutex::new(HashMap::new()));
         let armed_count = Arc::new(Mutex::new(0));
+        let clock = Arc::new(Clock { now_ms: AtomicU64::new(0), step_ms: AtomicU64::new(HOUR_MS) });
         (
-            TimerChannel { tx: tx.clone(), armed_count: armed_count.clone() },
-            Self { tx, armed_count },
+            TimerChannel { tx: tx.clone(), armed_count: armed_count.clone(), clock: clock.clone() },
+            Self { tx, armed_count, clock },
         )
     }
 }
 
+const HOUR_MS: u64 = 3_600_000;
+
+/// Clock of the test timer. By default an hour passes between reads, so that duty-cycle limits
+/// don't get in the way of tests which aren't about them.
+struct Clock {
+    now_ms: AtomicU64,
+    step_ms: AtomicU64,
+}
+
 pub struct TestTimer {
     armed_count: Arc<Mutex<usize>>,
     tx: Arc<Mutex<HashMap<usize, mpsc::Sender<()>>>>,
+    clock: Arc<Clock>,
 }
 
 impl TestTimer {
@@ -40,12 +52,18 @@ impl Timer for TestTimer {
     async fn delay_ms(&mut self, _millis: u64) {
         self.create_channel_and_await().await;
     }
+
+    fn now_ms(&self) -> u64 {
+        let step = self.clock.step_ms.load(Ordering::SeqCst);
+        self.clock.now_ms.fetch_add(step, Ordering::SeqCst) + step
+    }
 }
 
 /// A channel for the test fixture to trigger fires and to check calls.
 pub struct TimerChannel {
     armed_count: Arc<Mutex<usize>>,
     tx: Arc<Mutex<HashMap<usize, mpsc::Sender<()>>>>,
+    clock: Arc<Clock>,
 }
 
 impl TimerChannel {
@@ -67,6 +85,18 @@ impl TimerChannel {
         }
     }
 
+    /// Stop the clock at the given time, it only moves with `advance_clock` afterwards.
+    #[allow(unused)]
+    pub fn stop_clock(&self, now_ms: u64) {
+        self.clock.step_ms.store(0, Ordering::SeqCst);
+        self.clock.now_ms.store(now_ms, Ordering::SeqCst);
+    }
+
+    #[allow(unused)]
+    pub fn advance_clock(&self, ms: u64) {
+        self.clock.now_ms.fetch_add(ms, Ordering::SeqCst);
+    }
+
     pub async fn get_armed_count(&self) -> usize {
         *self.armed_count.lock().await
     }
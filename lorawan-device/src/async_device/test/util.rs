@@ -15,6 +15,10 @@ fn default_session() -> Session {
         devaddr: get_dev_addr(),
         fcnt_up: 0,
         fcnt_down: 0,
+        adr_ack_cnt: 0,
+        rx_settings: Default::default(),
+        link_check: None,
+        lorawan_1_1: None,
         confirmed: false,
         uplink: Default::default(),
         #[cfg(feature = "certification")]
@@ -53,6 +57,15 @@ fn setup_internal(session_data: Option<Session>) -> (RadioChannel, TimerChannel,
     (radio_channel, timer_channel, async_device)
 }
 
+/// Device without session, which still needs to join
+pub fn setup_with_region(region: region::Configuration) -> (RadioChannel, TimerChannel, Device) {
+    let (radio_channel, mock_radio) = TestRadio::new();
+    let (timer_channel, mock_timer) = TestTimer::new();
+    let async_device =
+        Device::new_with_session(region, mock_radio, mock_timer, rand::rngs::OsRng, None);
+    (radio_channel, timer_channel, async_device)
+}
+
 pub fn setup_with_session() -> (RadioChannel, TimerChannel, Device) {
     setup_internal(Some(default_session()))
 }
@@ -106,7 +119,7 @@ pub async fn setup_with_session_class_c() -> (RadioChannel, TimerChannel, Device
 
     use super::SendResponse;
     match response {
-        Ok(SendResponse::DownlinkReceived(0)) => (),
+        Ok(SendResponse::DownlinkReceived { fcnt_down: 0, .. }) => (),
         _ => {
             panic!()
         }
@@ -33,7 +33,7 @@ use core::marker::PhantomData;
 #[cfg_attr(docsrs, doc(cfg(feature = "default-crypto")))]
 pub use lorawan::default_crypto;
 pub use lorawan::{
-    keys::{AppEui, AppKey, AppSKey, CryptoFactory, DevEui, NwkSKey},
+    keys::{AppEui, AppKey, AppSKey, CryptoFactory, DevEui, NwkKey, NwkSKey},
     parser::DevAddr,
 };
 
@@ -48,12 +48,13 @@ pub use rng::Prng;
 pub struct Downlink {
     pub data: Vec<u8, 256>,
     pub fport: u8,
+    pub metadata: DownlinkMetadata,
 }
 
 #[cfg(feature = "defmt-03")]
 impl defmt::Format for Downlink {
     fn format(&self, f: defmt::Formatter<'_>) {
-        defmt::write!(f, "Downlink {{ fport: {}, data: ", self.fport,);
+        defmt::write!(f, "Downlink {{ fport: {}, metadata: {}, data: ", self.fport, self.metadata);
 
         for byte in self.data.iter() {
             defmt::write!(f, "{:02x}", byte);
@@ -62,6 +63,37 @@ impl defmt::Format for Downlink {
     }
 }
 
+/// Reception conditions of a downlink message.
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownlinkMetadata {
+    /// RSSI of the frame in dBm.
+    pub rssi: i16,
+    /// SNR of the frame in dB.
+    pub snr: i8,
+    /// Receive window in which the frame was received.
+    pub window: RxWindow,
+    pub frequency: u32,
+    pub datarate: region::DR,
+    /// Frame counter of the frame, in the session of the multicast group for multicast frames.
+    pub fcnt_down: mac::FcntDown,
+    /// Multicast group the frame was sent to, `None` for unicast frames.
+    #[cfg(feature = "multicast")]
+    pub multicast_group: Option<u8>,
+}
+
+/// Receive windows of an end-device.
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RxWindow {
+    Rx1,
+    Rx2,
+    /// Class C continuous reception, between and after the RX1 and RX2 windows.
+    RxC,
+    /// Class B ping slot.
+    PingSlot,
+}
+
 /// Allows to fine-tune the beginning and end of the receive windows for a specific board.
 pub trait Timings {
     /// The offset in milliseconds from the beginning of the receive windows. For example, settings this to 100
@@ -71,12 +103,33 @@ pub trait Timings {
     /// How long to leave the receive window open in milliseconds. For example, if offset was set to 100 and duration
     /// was set to 200, the window would be open 100 ms before and close 100 ms after the target time.
     fn get_rx_window_duration_ms(&self) -> u32;
+
+    /// Milliseconds elapsed since an arbitrary, fixed point in time. The clock must be monotonic
+    /// and is used for duty-cycle accounting and network time synchronization.
+    fn get_time_ms(&self) -> u64;
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Join the network using either OTAA or ABP.
 pub enum JoinMode {
-    OTAA { deveui: DevEui, appeui: AppEui, appkey: AppKey },
-    ABP { nwkskey: NwkSKey, appskey: AppSKey, devaddr: DevAddr<[u8; 4]> },
+    OTAA {
+        deveui: DevEui,
+        appeui: AppEui,
+        appkey: AppKey,
+    },
+    /// OTAA for LoRaWAN 1.1 end-devices, which have separate root keys for the network and the
+    /// application. The session falls back to LoRaWAN 1.0 if the network server does not
+    /// implement LoRaWAN 1.1.
+    OTAA1_1 {
+        deveui: DevEui,
+        appeui: AppEui,
+        appkey: AppKey,
+        nwkkey: NwkKey,
+    },
+    ABP {
+        nwkskey: NwkSKey,
+        appskey: AppSKey,
+        devaddr: DevAddr<[u8; 4]>,
+    },
 }
@@ -2,6 +2,7 @@ use crate::mac;
 use crate::radio::RadioBuffer;
 use lorawan::certification::parse_downlink_certification_messages;
 use lorawan::keys::CryptoFactory;
+use lorawan::parser::FCtrl;
 
 /// Certification protocol uses `fport = 224`
 pub(crate) const CERTIFICATION_PORT: u8 = 224;
@@ -93,6 +94,8 @@ impl Certification {
     pub(crate) fn setup_send<C: CryptoFactory + Default, const N: usize>(
         &mut self,
         mut state: &mut mac::State,
+        fctrl: FCtrl,
+        tx_channel: &mac::TxChannel,
         buf: &mut RadioBuffer<N>,
     ) -> mac::Result<mac::FcntUp> {
         let send_data = mac::SendData {
@@ -102,7 +105,7 @@ impl Certification {
         };
         match &mut state {
             mac::State::Joined(ref mut session) => {
-                Ok(session.prepare_buffer::<C, N>(&send_data, buf))
+                session.prepare_buffer::<C, N>(&send_data, fctrl, tx_channel, buf)
             }
             mac::State::Otaa(_) => Err(mac::Error::NotJoined),
             mac::State::Unjoined => Err(mac::Error::NotJoined),
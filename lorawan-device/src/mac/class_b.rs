@@ -0,0 +1,430 @@
+//! Class B operation (LoRaWAN 1.0.3+): beacon acquisition and tracking, and the schedule of the
+//! ping slots in which the network may send downlinks.
+use super::device_time::DeviceTime;
+use crate::radio::{RxConfig, RxMode};
+use crate::region::{self, BeaconChannel, DR};
+use lorawan::keys::{CryptoFactory, Encrypter, AES128};
+use lorawan::maccommands::{BeaconFreqReqPayload, PingSlotChannelReqPayload};
+
+/// Beacons are transmitted every 128 s, at GPS times which are a multiple of the period.
+const BEACON_PERIOD_MS: u64 = 128_000;
+/// Interval at the start of the beacon period in which no ping slots are opened
+const BEACON_RESERVED_MS: u64 = 2_120;
+const PING_SLOT_MS: u64 = 30;
+/// Number of ping slots in a beacon period
+const PING_SLOTS: u32 = 4096;
+/// Without beacons, ping slots are kept open based on the last beacon for up to 2 hours
+const BEACONLESS_MS: u64 = 2 * 60 * 60 * 1000;
+/// Number of beacon windows opened based on the network time before the search is given up
+const MAX_SEARCH_WINDOWS: u8 = 3;
+/// Additional time beacon windows are opened early during the search, to account for the
+/// accuracy of the network time
+const SEARCH_MARGIN_MS: u32 = 100;
+/// Windows are widened by 1 ms every 40 s since the last beacon, accounting for a clock
+/// drift of 25 ppm
+const DRIFT_MS_PER_MS: u64 = 40_000;
+/// Without PingSlotInfoReq, the network opens one ping slot per beacon period
+const DEFAULT_PERIODICITY: u8 = 7;
+/// Preamble length of beacons, in symbols
+const BEACON_PREAMBLE: u8 = 10;
+
+/// Class B beacon, broadcast by the gateways at the start of every beacon period.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Beacon {
+    /// GPS time of the beacon in seconds
+    pub time: u32,
+    /// Gateway specific part: InfoDesc followed by 6 bytes of information, such as the gateway
+    /// coordinates. `None` if it fails its CRC check.
+    pub gw_specific: Option<[u8; 7]>,
+}
+
+impl Beacon {
+    /// Parse a beacon with the layout of the region. Beacons with an invalid CRC of the time
+    /// field are rejected.
+    pub(crate) fn parse(data: &[u8], channel: &BeaconChannel) -> Option<Self> {
+        if data.len() != channel.len() {
+            return None;
+        }
+        let (rfu, _) = channel.rfu;
+        let (common, gw_specific) = data.split_at(rfu + 6);
+        if !crc_valid(common) {
+            return None;
+        }
+        let time = u32::from_le_bytes(common[rfu..rfu + 4].try_into().unwrap());
+        let gw_specific = crc_valid(gw_specific).then(|| gw_specific[..7].try_into().unwrap());
+        Some(Self { time, gw_specific })
+    }
+}
+
+/// Check the CRC-16 (CCITT polynomial, initial value 0) in the last two bytes of `data`, which
+/// is transmitted in little endian.
+fn crc_valid(data: &[u8]) -> bool {
+    let (data, crc) = data.split_at(data.len() - 2);
+    crc16(data) == u16::from_le_bytes([crc[0], crc[1]])
+}
+
+pub(crate) fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0, |crc, byte| {
+        (0..8).fold(crc ^ ((*byte as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// Offset of the first ping slot of the device in the beacon period starting at `beacon_time`,
+/// the ping slots repeating every `ping_period` slots.
+fn ping_offset<C: CryptoFactory + Default>(
+    beacon_time: u32,
+    dev_addr: u32,
+    ping_period: u32,
+) -> u32 {
+    let mut block = [0; 16];
+    block[..4].copy_from_slice(&beacon_time.to_le_bytes());
+    block[4..8].copy_from_slice(&dev_addr.to_le_bytes());
+    C::default().new_enc(&AES128([0; 16])).encrypt_block(&mut block);
+    u16::from_le_bytes([block[0], block[1]]) as u32 % ping_period
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Disabled,
+    /// Looking for the first beacon, at the times given by the network time
+    Searching {
+        windows: u8,
+    },
+    /// Tracking beacons, with the GPS time of the last one and the local time it started at
+    Locked {
+        beacon_time: u32,
+        local_ms: u64,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Slot {
+    Beacon,
+    Ping,
+}
+
+/// Next receive window of the Class B schedule.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Window {
+    pub slot: Slot,
+    /// Local time at which the window opens
+    pub at_ms: u64,
+    pub rx_config: RxConfig,
+}
+
+#[derive(Debug)]
+pub(crate) struct ClassB {
+    state: State,
+    /// Ping slots are opened every 2^periodicity seconds, as known by the network
+    periodicity: u8,
+    /// Periodicity sent with PingSlotInfoReq which awaits PingSlotInfoAns
+    pending_periodicity: Option<u8>,
+    /// Ping slot channel set with PingSlotChannelReq, the region default is used if unset
+    ping_slot_frequency: Option<u32>,
+    ping_slot_data_rate: Option<DR>,
+    /// Beacon frequency set with BeaconFreqReq, the region default is used if unset
+    beacon_frequency: Option<u32>,
+}
+
+impl Default for ClassB {
+    fn default() -> Self {
+        Self {
+            state: State::Disabled,
+            periodicity: DEFAULT_PERIODICITY,
+            pending_periodicity: None,
+            ping_slot_frequency: None,
+            ping_slot_data_rate: None,
+            beacon_frequency: None,
+        }
+    }
+}
+
+impl ClassB {
+    /// Start the beacon search, unless beacons are already tracked. Returns whether the
+    /// periodicity needs to be sent to the network with PingSlotInfoReq.
+    pub fn enable(&mut self, periodicity: u8) -> bool {
+        if self.state == State::Disabled {
+            self.state = State::Searching { windows: 0 };
+        }
+        let changed = periodicity != self.periodicity;
+        self.pending_periodicity = changed.then_some(periodicity);
+        changed
+    }
+
+    pub fn disable(&mut self) {
+        self.state = State::Disabled;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.state != State::Disabled
+    }
+
+    /// Whether beacons are tracked, uplinks signal Class B operation to the network then.
+    pub fn is_locked(&self) -> bool {
+        matches!(self.state, State::Locked { .. })
+    }
+
+    pub fn handle_ping_slot_info_ans(&mut self) {
+        if let Some(periodicity) = self.pending_periodicity.take() {
+            self.periodicity = periodicity;
+        }
+    }
+
+    /// Returns whether the frequency and data rate are acceptable, the ping slot channel is
+    /// only changed if both are.
+    pub fn handle_ping_slot_channel_req(
+        &mut self,
+        payload: &PingSlotChannelReqPayload<'_>,
+        region: &region::Configuration,
+    ) -> (bool, bool) {
+        let frequency = payload.frequency().value();
+        // Frequency 0 restores the default channel
+        let freq_ack = frequency == 0 || region.frequency_valid(frequency);
+        let data_rate = region.check_rx2_data_rate(payload.data_rate());
+        if let (true, Some(data_rate)) = (freq_ack, data_rate) {
+            self.ping_slot_frequency = (frequency != 0).then_some(frequency);
+            self.ping_slot_data_rate = Some(data_rate);
+        }
+        (freq_ack, data_rate.is_some())
+    }
+
+    /// Data rate of the ping slots, set with PingSlotChannelReq or the beacon data rate.
+    pub fn ping_slot_data_rate(&self, channel: &BeaconChannel) -> DR {
+        self.ping_slot_data_rate.unwrap_or(channel.data_rate)
+    }
+
+    /// Returns whether the beacon frequency is acceptable.
+    pub fn handle_beacon_freq_req(
+        &mut self,
+        payload: &BeaconFreqReqPayload<'_>,
+        region: &region::Configuration,
+    ) -> bool {
+        let frequency = payload.frequency().value();
+        let ack = frequency == 0 || region.frequency_valid(frequency);
+        if ack {
+            self.beacon_frequency = (frequency != 0).then_some(frequency);
+        }
+        ack
+    }
+
+    /// Current GPS time in milliseconds, based on the last beacon or, while searching, on the
+    /// network time.
+    fn gps_ms(&self, device_time: &DeviceTime, now_ms: u64) -> Option<u64> {
+        match self.state {
+            State::Disabled => None,
+            State::Searching { .. } => device_time.gps_ms(now_ms),
+            State::Locked { beacon_time, local_ms } => {
+                Some(beacon_time as u64 * 1000 + now_ms.saturating_sub(local_ms))
+            }
+        }
+    }
+
+    /// Widening of a window at GPS time `gps_ms` due to the clock drift since the last beacon.
+    fn widening_ms(&self, gps_ms: u64) -> u32 {
+        match self.state {
+            State::Locked { beacon_time, .. } => {
+                (gps_ms.saturating_sub(beacon_time as u64 * 1000) / DRIFT_MS_PER_MS) as u32
+            }
+            _ => SEARCH_MARGIN_MS,
+        }
+    }
+
+    /// Next beacon or ping slot window opening no earlier than `earliest_ms`. Returns `None`
+    /// if Class B is disabled or, while searching, the network time is unknown.
+    #[allow(clippy::too_many_arguments)]
+    pub fn next_window<C: CryptoFactory + Default>(
+        &self,
+        region: &region::Configuration,
+        channel: &BeaconChannel,
+        device_time: &DeviceTime,
+        dev_addr: u32,
+        now_ms: u64,
+        earliest_ms: u64,
+        buffer_ms: u32,
+    ) -> Option<Window> {
+        let gps_now = self.gps_ms(device_time, now_ms)?;
+        let gps_earliest = gps_now + earliest_ms.saturating_sub(now_ms);
+        let period_start = (gps_earliest + self.widening_ms(gps_earliest) as u64)
+            / BEACON_PERIOD_MS
+            * BEACON_PERIOD_MS;
+        // Local time at which the widened window at `gps_ms` opens
+        let local_ms = |gps_ms: u64| {
+            (now_ms + gps_ms - gps_now).saturating_sub(self.widening_ms(gps_ms) as u64)
+        };
+
+        if self.is_locked() {
+            let beacon_time = (period_start / 1000) as u32;
+            let ping_period = 1 << (5 + self.periodicity);
+            let offset = ping_offset::<C>(beacon_time, dev_addr, ping_period);
+            let slot = (offset..PING_SLOTS).step_by(ping_period as usize).find_map(|slot| {
+                let gps_ms = period_start + BEACON_RESERVED_MS + slot as u64 * PING_SLOT_MS;
+                (gps_ms - self.widening_ms(gps_ms) as u64 >= gps_earliest).then_some(gps_ms)
+            });
+            if let Some(gps_ms) = slot {
+                let frequency = self
+                    .ping_slot_frequency
+                    .unwrap_or_else(|| channel.frequency(dev_addr.wrapping_add(beacon_time / 128)));
+                let data_rate = self.ping_slot_data_rate(channel);
+                return Some(Window {
+                    slot: Slot::Ping,
+                    at_ms: local_ms(gps_ms),
+                    rx_config: RxConfig {
+                        rf: region.get_class_b_rf_config(frequency, data_rate),
+                        mode: RxMode::Single { ms: buffer_ms + 2 * self.widening_ms(gps_ms) },
+                    },
+                });
+            }
+        }
+
+        let gps_ms = period_start + BEACON_PERIOD_MS;
+        let beacon_time = (gps_ms / 1000) as u32;
+        let frequency =
+            self.beacon_frequency.unwrap_or_else(|| channel.frequency(beacon_time / 128));
+        let widening = self.widening_ms(gps_ms);
+        Some(Window {
+            slot: Slot::Beacon,
+            at_ms: local_ms(gps_ms),
+            rx_config: RxConfig {
+                rf: region.get_class_b_rf_config(frequency, channel.data_rate),
+                mode: RxMode::Beacon { ms: buffer_ms + 2 * widening, len: channel.len() as u8 },
+            },
+        })
+    }
+
+    /// Handle a frame received in a beacon window ending at `now_ms`. Beacons are tracked from
+    /// then on.
+    pub fn handle_beacon(
+        &mut self,
+        data: &[u8],
+        channel: &BeaconChannel,
+        rx_config: &RxConfig,
+        now_ms: u64,
+    ) -> Option<Beacon> {
+        let beacon = Beacon::parse(data, channel)?;
+        if self.is_enabled() {
+            let airtime_us =
+                rx_config.rf.bb.time_on_air_us(Some(BEACON_PREAMBLE), false, data.len() as u8);
+            self.state = State::Locked {
+                beacon_time: beacon.time,
+                local_ms: now_ms.saturating_sub(airtime_us.div_ceil(1000) as u64),
+            };
+        }
+        Some(beacon)
+    }
+
+    /// Handle a beacon window without a beacon. Returns whether the beacon is lost, in which
+    /// case the device falls back to Class A.
+    pub fn beacon_missed(&mut self, now_ms: u64) -> bool {
+        let lost = match &mut self.state {
+            State::Disabled => false,
+            State::Searching { windows } => {
+                *windows += 1;
+                *windows >= MAX_SEARCH_WINDOWS
+            }
+            State::Locked { local_ms, .. } => now_ms.saturating_sub(*local_ms) >= BEACONLESS_MS,
+        };
+        if lost {
+            self.state = State::Disabled;
+        }
+        lost
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::region::Region;
+    use lorawan::default_crypto::DefaultFactory;
+
+    fn beacon(time: u32) -> [u8; 17] {
+        let mut data = [0; 17];
+        data[2..6].copy_from_slice(&time.to_le_bytes());
+        let crc = crc16(&data[..6]);
+        data[6..8].copy_from_slice(&crc.to_le_bytes());
+        data[8..15].copy_from_slice(&[0, 1, 2, 3, 4, 5, 6]);
+        let crc = crc16(&data[8..15]);
+        data[15..17].copy_from_slice(&crc.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn crc() {
+        // CRC-16/XMODEM check value
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+    }
+
+    #[test]
+    fn parse_beacon() {
+        let region = region::Configuration::new(Region::EU868);
+        let channel = region.beacon_channel().unwrap();
+        let mut data = beacon(1_400_000_000);
+        assert_eq!(
+            Beacon::parse(&data, &channel),
+            Some(Beacon { time: 1_400_000_000, gw_specific: Some([0, 1, 2, 3, 4, 5, 6]) })
+        );
+        // The gateway specific part is only provided with a valid CRC
+        data[16] ^= 1;
+        assert_eq!(Beacon::parse(&data, &channel).unwrap().gw_specific, None);
+        data[3] ^= 1;
+        assert_eq!(Beacon::parse(&data, &channel), None);
+        assert_eq!(Beacon::parse(&data[..16], &channel), None);
+    }
+
+    #[test]
+    fn ping_slots() {
+        let region = region::Configuration::new(Region::EU868);
+        let channel = region.beacon_channel().unwrap();
+        let device_time = DeviceTime::default();
+        let mut class_b = ClassB::default();
+        class_b.enable(DEFAULT_PERIODICITY);
+        // Searching needs the network time
+        let next = |class_b: &ClassB, now_ms| {
+            class_b.next_window::<DefaultFactory>(
+                &region,
+                &channel,
+                &device_time,
+                0x0102_0304,
+                now_ms,
+                now_ms,
+                10,
+            )
+        };
+        assert!(next(&class_b, 0).is_none());
+
+        let data = beacon(1_280_000_000);
+        let rx_config = RxConfig {
+            rf: region.get_class_b_rf_config(869_525_000, DR::_3),
+            mode: RxMode::Beacon { ms: 10, len: 17 },
+        };
+        assert!(class_b.handle_beacon(&data, &channel, &rx_config, 1_000).is_some());
+        assert!(class_b.is_locked());
+
+        // One ping slot per beacon period, at the offset given by the beacon time and DevAddr
+        let offset = ping_offset::<DefaultFactory>(1_280_000_000, 0x0102_0304, 4096);
+        let window = next(&class_b, 1_000).unwrap();
+        assert_eq!(window.slot, Slot::Ping);
+        let State::Locked { local_ms, .. } = class_b.state else { unreachable!() };
+        let slot_ms = 2_120 + offset as u64 * 30;
+        assert_eq!(window.at_ms, local_ms + slot_ms - slot_ms / 40_000);
+        assert_eq!(window.rx_config.rf.frequency, 869_525_000);
+
+        // The next beacon follows the ping slot
+        let window = next(&class_b, window.at_ms + 1).unwrap();
+        assert_eq!(window.slot, Slot::Beacon);
+        // Widened by 3 ms for the clock drift over a beacon period
+        assert_eq!(window.at_ms, local_ms + 128_000 - 3);
+        assert_eq!(window.rx_config.mode, RxMode::Beacon { ms: 10 + 2 * 3, len: 17 });
+
+        // Beacon-less operation for up to 2 hours
+        assert!(!class_b.beacon_missed(local_ms + 128_000));
+        assert!(class_b.beacon_missed(local_ms + 7_200_000));
+        assert!(!class_b.is_enabled());
+    }
+}
@@ -0,0 +1,47 @@
+//! Network time synchronization with DeviceTimeReq/DeviceTimeAns (LoRaWAN 1.0.3+).
+use lorawan::maccommands::DeviceTimeAnsPayload;
+
+/// Time since the GPS epoch (1980-01-06T00:00:00Z), which does not account for leap seconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct GpsTime {
+    pub seconds: u32,
+    /// Fractional part of the second, with millisecond resolution
+    pub nanoseconds: u32,
+}
+
+impl GpsTime {
+    fn from_ms(ms: u64) -> Self {
+        Self { seconds: (ms / 1000) as u32, nanoseconds: (ms % 1000) as u32 * 1_000_000 }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct DeviceTime {
+    /// Local time at the end of the last uplink transmission
+    tx_end_ms: u64,
+    /// GPS time and the corresponding local time, in milliseconds, of the last synchronization
+    sync: Option<(u64, u64)>,
+}
+
+impl DeviceTime {
+    pub fn tx_done(&mut self, now_ms: u64) {
+        self.tx_end_ms = now_ms;
+    }
+
+    /// DeviceTimeAns provides the GPS time at the end of the uplink transmission.
+    pub fn handle_answer(&mut self, payload: &DeviceTimeAnsPayload<'_>) {
+        let gps_ms = payload.seconds() as u64 * 1000 + (payload.nano_seconds() / 1_000_000) as u64;
+        self.sync = Some((gps_ms, self.tx_end_ms));
+    }
+
+    /// Current GPS time according to the last synchronization, if any.
+    pub fn gps_time(&self, now_ms: u64) -> Option<GpsTime> {
+        self.gps_ms(now_ms).map(GpsTime::from_ms)
+    }
+
+    /// Current GPS time in milliseconds according to the last synchronization, if any.
+    pub fn gps_ms(&self, now_ms: u64) -> Option<u64> {
+        self.sync.map(|(gps_ms, local_ms)| gps_ms + now_ms.saturating_sub(local_ms))
+    }
+}
@@ -3,30 +3,45 @@
 //! decrypting from send and receive buffers.
 
 use crate::{
-    radio::{self, RadioBuffer, RfConfig, RxConfig, RxMode},
-    region, AppSKey, Downlink, NwkSKey,
+    radio::{self, RadioBuffer, RfConfig, RxConfig, RxMode, RxQuality},
+    region, AppSKey, Downlink, DownlinkMetadata, NwkSKey, RxWindow,
 };
 use heapless::Vec;
-use lorawan::parser::DevAddr;
-use lorawan::{self, keys::CryptoFactory};
+use lorawan::parser::{DevAddr, FCtrl};
+use lorawan::types::RejoinType;
+use lorawan::{self, creator::RejoinRequestCreator, keys::CryptoFactory};
 
 pub type FcntDown = u32;
 pub type FcntUp = u32;
 
 mod session;
 use rand_core::RngCore;
-pub use session::{Session, SessionKeys};
+use session::TxChannel;
+pub use session::{BatteryLevel, LinkCheck, RxSettings, Session, Session1_1, SessionKeys};
 
 mod otaa;
-pub use otaa::NetworkCredentials;
+pub use otaa::{DevNonceMode, NetworkCredentials};
 
 use crate::async_device;
 use crate::nb_device;
 
 pub(crate) mod uplink;
 
+mod device_time;
+pub use device_time::GpsTime;
+
+mod rejoin;
+pub use rejoin::{ForcedRejoin, Rejoin, RejoinParams};
+
+pub(crate) mod snapshot;
+pub use snapshot::{Snapshot, SnapshotError, MAX_SNAPSHOT_LEN, SNAPSHOT_VERSION};
+
 #[cfg(feature = "certification")]
 pub(crate) mod certification;
+#[cfg(feature = "class-b")]
+pub(crate) mod class_b;
+#[cfg(feature = "class-b")]
+pub use class_b::Beacon;
 #[cfg(feature = "multicast")]
 pub(crate) mod multicast;
 
@@ -42,6 +57,31 @@ pub(crate) enum Window {
     _2,
 }
 
+/// Receive window and reception conditions of a frame, which make up the metadata of its
+/// downlink.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct RxInfo {
+    pub quality: RxQuality,
+    pub window: RxWindow,
+    pub frequency: u32,
+    pub datarate: region::DR,
+}
+
+impl RxInfo {
+    pub(crate) fn downlink_metadata(&self, fcnt_down: FcntDown) -> DownlinkMetadata {
+        DownlinkMetadata {
+            rssi: self.quality.rssi(),
+            snr: self.quality.snr(),
+            window: self.window,
+            frequency: self.frequency,
+            datarate: self.datarate,
+            fcnt_down,
+            #[cfg(feature = "multicast")]
+            multicast_group: None,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
 /// LoRaWAN Session and Network Configurations
@@ -52,10 +92,11 @@ pub struct Configuration {
     join_accept_delay2: u32,
 
     pub(crate) tx_power: Option<u8>,
-    // Overriden with RxParamSetupReq
-    // TODO: rx1_data_rate_offset
-    // TODO: rx2_data_rate
-    pub(crate) rx2_frequency: Option<u32>,
+    // Whether Adaptive Data Rate is requested from the network
+    pub(crate) adr: bool,
+    // Number of transmissions for unconfirmed uplinks, set with LinkADRReq
+    pub(crate) nb_trans: u8,
+    pub(crate) confirmed_retries: ConfirmedRetryPolicy,
 }
 
 pub(crate) struct Mac {
@@ -63,6 +104,19 @@ pub(crate) struct Mac {
     pub region: region::Configuration,
     board_eirp: BoardEirp,
     state: State,
+    // Number of transmissions of the current uplink frame so far and the maximum allowed,
+    // either according to NbTrans or the retry policy for confirmed frames
+    tx_attempts: u8,
+    max_tx_attempts: u8,
+    device_time: device_time::DeviceTime,
+    dev_nonce: otaa::DevNonceGenerator,
+    // Counter of type 1 rejoin-requests (RJcount1), which is kept across sessions
+    rj_count1: u16,
+    // Rejoin-request awaiting a join-accept
+    rejoin_request: Option<rejoin::RejoinRequest>,
+    battery_level: fn() -> BatteryLevel,
+    #[cfg(feature = "class-b")]
+    class_b: class_b::ClassB,
     #[cfg(feature = "certification")]
     certification: certification::Certification,
     #[cfg(feature = "multicast")]
@@ -85,10 +139,52 @@ pub(crate) enum State {
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
 pub enum Error {
     NotJoined,
+    /// All DevNonce values have been used for join requests, the device can't join with the
+    /// current credentials anymore.
+    DevNonceExhausted,
+    /// Duty-cycle limits do not allow transmitting now, retry after the given time.
+    DutyCycle {
+        retry_after_ms: u64,
+    },
+    /// Listen-before-talk found the channel busy, the frame has not been transmitted.
+    ChannelBusy,
+    /// Rejoin-requests need a LoRaWAN 1.1 session and the NwkKey, and are not possible anymore
+    /// once the rejoin-request counter is used up.
+    RejoinUnavailable,
+    /// None of the enabled channels supports the data rate in use.
+    NoChannelForDataRate,
+    /// The application payload and the pending MAC commands exceed the maximum payload length of
+    /// the data rate. See `available_payload_length()` for the room left in the next uplink.
+    PayloadTooLarge,
+    /// `send_mac_commands()` was called without pending MAC commands.
+    NoMacCommands,
+    /// Class B has not been enabled or is not supported by the region.
+    #[cfg(feature = "class-b")]
+    ClassBUnavailable,
+    /// The beacon search needs the network time, requested with DeviceTimeReq.
+    #[cfg(feature = "class-b")]
+    NoNetworkTime,
     #[cfg(feature = "multicast")]
     Multicast(multicast::Error),
 }
 
+/// Retransmission policy for confirmed uplinks which are not acknowledged by the network.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct ConfirmedRetryPolicy {
+    /// Maximum number of transmissions of a confirmed frame, including the first one.
+    pub max_attempts: u8,
+    /// Step the data rate down after every two unacknowledged transmissions, as described in
+    /// RP002-1.0.x.
+    pub datarate_step_down: bool,
+}
+
+impl Default for ConfirmedRetryPolicy {
+    fn default() -> Self {
+        Self { max_attempts: 1, datarate_step_down: false }
+    }
+}
+
 pub struct SendData<'a> {
     pub data: &'a [u8],
     pub fport: u8,
@@ -104,13 +200,24 @@ impl Mac {
             board_eirp: BoardEirp { max_power, antenna_gain },
             region,
             state: State::Unjoined,
+            tx_attempts: 0,
+            max_tx_attempts: 0,
+            device_time: device_time::DeviceTime::default(),
+            dev_nonce: otaa::DevNonceGenerator::default(),
+            rj_count1: 0,
+            rejoin_request: None,
+            battery_level: || BatteryLevel::Unknown,
+            #[cfg(feature = "class-b")]
+            class_b: class_b::ClassB::default(),
             configuration: Configuration {
                 data_rate,
                 rx1_delay: region::constants::RECEIVE_DELAY1,
                 join_accept_delay1: region::constants::JOIN_ACCEPT_DELAY1,
                 join_accept_delay2: region::constants::JOIN_ACCEPT_DELAY2,
-                rx2_frequency: None,
                 tx_power: None,
+                adr: false,
+                nb_trans: 1,
+                confirmed_retries: ConfirmedRetryPolicy::default(),
             },
             #[cfg(feature = "certification")]
             certification: certification::Certification::new(),
@@ -126,14 +233,38 @@ impl Mac {
         rng: &mut RNG,
         credentials: NetworkCredentials,
         buf: &mut RadioBuffer<N>,
-    ) -> (radio::TxConfig, u16) {
+        now_ms: u64,
+    ) -> Result<(radio::TxConfig, u16)> {
+        self.check_duty_cycle(&Frame::Join, now_ms)?;
+        let tx_config = self.select_tx_config(rng, &Frame::Join, now_ms)?;
         let mut otaa = otaa::Otaa::new(credentials);
-        let dev_nonce = otaa.prepare_buffer::<C, RNG, N>(rng, buf);
+        let dev_nonce = otaa
+            .prepare_buffer::<C, RNG, N>(rng, &mut self.dev_nonce, buf)
+            .ok_or(Error::DevNonceExhausted)?;
         self.state = State::Otaa(otaa);
-        let mut tx_config =
-            self.region.create_tx_config(rng, self.configuration.data_rate, &Frame::Join);
-        tx_config.adjust_power(self.board_eirp.max_power, self.board_eirp.antenna_gain);
-        (tx_config, dev_nonce)
+        self.region.register_transmission(
+            &Frame::Join,
+            &tx_config,
+            buf.as_ref_for_read().len(),
+            now_ms,
+        );
+        Ok((tx_config, dev_nonce))
+    }
+
+    pub(crate) fn set_dev_nonce_mode(&mut self, mode: DevNonceMode) {
+        self.dev_nonce.set_mode(mode);
+    }
+
+    pub(crate) fn set_dev_nonce(&mut self, next: u32) {
+        self.dev_nonce.set_next(next);
+    }
+
+    pub(crate) fn get_dev_nonce(&self) -> u32 {
+        self.dev_nonce.get_next()
+    }
+
+    pub(crate) fn take_dev_nonce_update(&mut self) -> Option<u32> {
+        self.dev_nonce.take_update()
     }
 
     /// Join via ABP. This does not transmit a join request frame, but instead sets the session.
@@ -158,36 +289,210 @@ impl Mac {
         rng: &mut RNG,
         buf: &mut RadioBuffer<N>,
         send_data: &SendData<'_>,
+        now_ms: u64,
     ) -> Result<(radio::TxConfig, FcntUp)> {
-        let fcnt = match &mut self.state {
-            State::Joined(ref mut session) => Ok(session.prepare_buffer::<C, N>(send_data, buf)),
+        if !self.is_joined() {
+            return Err(Error::NotJoined);
+        }
+        self.check_duty_cycle(&Frame::Data, now_ms)?;
+        self.check_payload_length(send_data)?;
+        let fctrl = self.uplink_fctrl(true);
+        // The channel is selected first, as LoRaWAN 1.1 covers it with the MIC
+        let tx_config = self.select_tx_config(rng, &Frame::Data, now_ms)?;
+        let tx_channel = self.tx_channel();
+        let max_mac_payload_length = self.max_mac_payload_length();
+        let (fcnt, confirmed) = match &mut self.state {
+            State::Joined(ref mut session) => {
+                // Uplinks without application payload carry the pending MAC commands, those
+                // which don't fit are dropped once the uplink can be sent
+                if send_data.data.is_empty() {
+                    session.truncate_mac_commands(max_mac_payload_length);
+                }
+                let fcnt = session.prepare_buffer::<C, N>(send_data, fctrl, &tx_channel, buf)?;
+                // Only frames which could be prepared count as uplinks for ADR
+                session.adr_backoff(&mut self.region, &mut self.configuration);
+                if let Some(session) = &mut session.lorawan_1_1 {
+                    session.rejoin.uplink_sent();
+                }
+                Ok((fcnt, session.confirmed))
+            }
+            State::Otaa(_) => Err(Error::NotJoined),
+            State::Unjoined => Err(Error::NotJoined),
+        }?;
+        self.tx_attempts = 1;
+        self.max_tx_attempts = if confirmed {
+            self.configuration.confirmed_retries.max_attempts
+        } else {
+            self.configuration.nb_trans
+        };
+        self.region.register_transmission(
+            &Frame::Data,
+            &tx_config,
+            buf.as_ref_for_read().len(),
+            now_ms,
+        );
+        Ok((tx_config, fcnt))
+    }
+
+    /// Prepare the radio buffer for repeating the previous uplink with the same frame counter,
+    /// either due to NbTrans or due to a missing acknowledgment of a confirmed uplink. The same
+    /// `send_data` as in the original transmission must be provided.
+    pub(crate) fn retransmit<C: CryptoFactory + Default, RNG: RngCore, const N: usize>(
+        &mut self,
+        rng: &mut RNG,
+        buf: &mut RadioBuffer<N>,
+        send_data: &SendData<'_>,
+        now_ms: u64,
+    ) -> Result<(radio::TxConfig, FcntUp)> {
+        if !self.is_joined() {
+            return Err(Error::NotJoined);
+        }
+        self.check_duty_cycle(&Frame::Data, now_ms)?;
+        let fctrl = self.uplink_fctrl(false);
+        let confirmed = match &self.state {
+            State::Joined(session) => Ok(session.confirmed),
             State::Otaa(_) => Err(Error::NotJoined),
             State::Unjoined => Err(Error::NotJoined),
         }?;
-        let mut tx_config =
-            self.region.create_tx_config(rng, self.configuration.data_rate, &Frame::Data);
+        self.tx_attempts += 1;
+        // Step data rate down after every two unacknowledged transmissions
+        if confirmed
+            && self.configuration.confirmed_retries.datarate_step_down
+            && self.tx_attempts % 2 == 1
+        {
+            if let Some(dr) = self.region.get_lower_datarate(self.configuration.data_rate) {
+                self.configuration.data_rate = dr;
+            }
+        }
+        let mut tx_config = self
+            .region
+            .create_retransmit_tx_config(rng, self.configuration.data_rate, now_ms)
+            .ok_or_else(|| {
+                no_tx_channel(&self.region, &Frame::Data, self.configuration.data_rate, now_ms)
+            })?;
         tx_config.adjust_power(
             self.configuration.tx_power.unwrap_or(self.board_eirp.max_power),
             self.board_eirp.antenna_gain,
         );
+        let tx_channel = self.tx_channel();
+        let fcnt = match &mut self.state {
+            State::Joined(ref mut session) => {
+                session.prepare_buffer::<C, N>(send_data, fctrl, &tx_channel, buf)
+            }
+            State::Otaa(_) => Err(Error::NotJoined),
+            State::Unjoined => Err(Error::NotJoined),
+        }?;
+        self.region.register_transmission(
+            &Frame::Data,
+            &tx_config,
+            buf.as_ref_for_read().len(),
+            now_ms,
+        );
         Ok((tx_config, fcnt))
     }
 
+    /// Prepare the radio buffer with a rejoin-request of the given type and provide the radio
+    /// configuration for the transmission. The join-accept is received in the join-accept
+    /// windows, the current session stays in use until then.
+    pub(crate) fn rejoin<C: CryptoFactory + Default, RNG: RngCore, const N: usize>(
+        &mut self,
+        rng: &mut RNG,
+        credentials: NetworkCredentials,
+        rejoin_type: RejoinType,
+        buf: &mut RadioBuffer<N>,
+        now_ms: u64,
+    ) -> Result<radio::TxConfig> {
+        self.check_duty_cycle(&Frame::Data, now_ms)?;
+        let State::Joined(session) = &mut self.state else {
+            return Err(Error::NotJoined);
+        };
+        let (Some(session), Some(nwkkey)) = (&mut session.lorawan_1_1, credentials.nwkkey()) else {
+            return Err(Error::RejoinUnavailable);
+        };
+        let rj_count = match rejoin_type {
+            RejoinType::Type1 => self.rj_count1,
+            _ => session.rejoin.rj_count0,
+        };
+        if rj_count == u16::MAX {
+            return Err(Error::RejoinUnavailable);
+        }
+
+        let crypto_factory = C::default();
+        buf.clear();
+        let mut phy = RejoinRequestCreator::new(buf.as_mut(), rejoin_type).unwrap();
+        // The setters only fail for fields of other rejoin types
+        let _ = match rejoin_type {
+            RejoinType::Type1 => phy.set_join_eui(*credentials.appeui()),
+            _ => phy.set_net_id(&session.rejoin.net_id),
+        };
+        phy.set_dev_eui(*credentials.deveui()).set_rj_count(rj_count);
+        let len = match rejoin_type {
+            RejoinType::Type1 => phy
+                .build(
+                    nwkkey.derive_js_int_key(&crypto_factory, credentials.deveui()).inner(),
+                    &crypto_factory,
+                )
+                .len(),
+            _ => phy.build(session.snwksintkey.inner(), &crypto_factory).len(),
+        };
+        buf.set_pos(len);
+
+        // Rejoin-requests use the channels of the session
+        let data_rate =
+            session.rejoin.data_rate(rejoin_type).unwrap_or(self.configuration.data_rate);
+        let mut tx_config = self
+            .region
+            .create_tx_config(rng, data_rate, &Frame::Data, now_ms)
+            .ok_or_else(|| no_tx_channel(&self.region, &Frame::Data, data_rate, now_ms))?;
+        tx_config.adjust_power(
+            self.configuration.tx_power.unwrap_or(self.board_eirp.max_power),
+            self.board_eirp.antenna_gain,
+        );
+        self.region.register_transmission(&Frame::Data, &tx_config, len, now_ms);
+
+        // Forced rejoin-requests are repeated after a random delay of up to 32 seconds
+        session.rejoin.transmitted(rejoin_type, now_ms, rng.next_u32() % 32_000);
+        if rejoin_type == RejoinType::Type1 {
+            self.rj_count1 += 1;
+        }
+        self.rejoin_request = Some(rejoin::RejoinRequest { rejoin_type, rj_count, credentials });
+        self.tx_attempts = 1;
+        self.max_tx_attempts = 1;
+        Ok(tx_config)
+    }
+
+    /// Type of the rejoin-request which is due, either periodically as set up with
+    /// RejoinParamSetupReq or as requested by the network with ForceRejoinReq.
+    pub(crate) fn rejoin_due(&mut self, now_ms: u64) -> Option<RejoinType> {
+        match &mut self.state {
+            State::Joined(Session { lorawan_1_1: Some(session), .. }) => session.rejoin.due(now_ms),
+            _ => None,
+        }
+    }
+
     #[cfg(feature = "multicast")]
     pub(crate) fn multicast_setup_send<C: CryptoFactory + Default, RNG: RngCore, const N: usize>(
         &mut self,
         rng: &mut RNG,
         buf: &mut RadioBuffer<N>,
+        now_ms: u64,
     ) -> Result<(radio::TxConfig, FcntUp)> {
-        self.multicast.setup_send::<C, N>(&mut self.state, buf).map(|fcnt_up| {
-            let mut tx_config =
-                self.region.create_tx_config(rng, self.configuration.data_rate, &Frame::Data);
-            tx_config.adjust_power(
-                self.configuration.tx_power.unwrap_or(self.board_eirp.max_power),
-                self.board_eirp.antenna_gain,
-            );
-            (tx_config, fcnt_up)
-        })
+        self.check_duty_cycle(&Frame::Data, now_ms)?;
+        self.tx_attempts = 1;
+        self.max_tx_attempts = 1;
+        let fctrl = self.uplink_fctrl(true);
+        let tx_config = self.select_tx_config(rng, &Frame::Data, now_ms)?;
+        let tx_channel = self.tx_channel();
+        let fcnt_up =
+            self.multicast.setup_send::<C, N>(&mut self.state, fctrl, &tx_channel, buf)?;
+        self.adr_backoff();
+        self.region.register_transmission(
+            &Frame::Data,
+            &tx_config,
+            buf.as_ref_for_read().len(),
+            now_ms,
+        );
+        Ok((tx_config, fcnt_up))
     }
 
     #[cfg(feature = "certification")]
@@ -199,13 +504,130 @@ impl Mac {
         &mut self,
         rng: &mut RNG,
         buf: &mut RadioBuffer<N>,
+        now_ms: u64,
     ) -> Result<(radio::TxConfig, FcntUp)> {
-        self.certification.setup_send::<C, N>(&mut self.state, buf).map(|fcnt_up| {
-            let mut tx_config =
-                self.region.create_tx_config(rng, self.configuration.data_rate, &Frame::Data);
-            tx_config.adjust_power(self.board_eirp.max_power, self.board_eirp.antenna_gain);
-            (tx_config, fcnt_up)
-        })
+        self.check_duty_cycle(&Frame::Data, now_ms)?;
+        self.tx_attempts = 1;
+        self.max_tx_attempts = 1;
+        let fctrl = self.uplink_fctrl(true);
+        let mut tx_config = self
+            .region
+            .create_tx_config(rng, self.configuration.data_rate, &Frame::Data, now_ms)
+            .ok_or_else(|| {
+                no_tx_channel(&self.region, &Frame::Data, self.configuration.data_rate, now_ms)
+            })?;
+        tx_config.adjust_power(self.board_eirp.max_power, self.board_eirp.antenna_gain);
+        let tx_channel = self.tx_channel();
+        let fcnt_up =
+            self.certification.setup_send::<C, N>(&mut self.state, fctrl, &tx_channel, buf)?;
+        self.adr_backoff();
+        self.region.register_transmission(
+            &Frame::Data,
+            &tx_config,
+            buf.as_ref_for_read().len(),
+            now_ms,
+        );
+        Ok((tx_config, fcnt_up))
+    }
+
+    /// Maximum length of the application payload of the next uplink at the current data rate, 0
+    /// if the device is not joined.
+    pub(crate) fn available_payload_length(&self) -> u8 {
+        match &self.state {
+            State::Joined(session) => {
+                session.available_payload_length(self.max_mac_payload_length())
+            }
+            State::Otaa(_) | State::Unjoined => 0,
+        }
+    }
+
+    fn max_mac_payload_length(&self) -> u8 {
+        self.region.max_payload_length(self.configuration.data_rate, false)
+    }
+
+    /// Returns an error if the application payload doesn't fit the next uplink.
+    fn check_payload_length(&self, send_data: &SendData<'_>) -> Result {
+        if send_data.data.len() > self.available_payload_length() as usize {
+            return Err(Error::PayloadTooLarge);
+        }
+        Ok(())
+    }
+
+    /// Returns an error with the time until the next transmission is possible if duty-cycle
+    /// limits do not allow transmitting the frame now.
+    fn check_duty_cycle(&self, frame: &Frame, now_ms: u64) -> Result {
+        match self.region.time_until_available(frame, self.configuration.data_rate, now_ms) {
+            0 => Ok(()),
+            retry_after_ms => Err(Error::DutyCycle { retry_after_ms }),
+        }
+    }
+
+    /// Select the channel and TX power for a new frame, without accounting its airtime.
+    fn select_tx_config<RNG: RngCore>(
+        &mut self,
+        rng: &mut RNG,
+        frame: &Frame,
+        now_ms: u64,
+    ) -> Result<radio::TxConfig> {
+        let mut tx_config = self
+            .region
+            .create_tx_config(rng, self.configuration.data_rate, frame, now_ms)
+            .ok_or_else(|| {
+                no_tx_channel(&self.region, frame, self.configuration.data_rate, now_ms)
+            })?;
+        let max_power = match frame {
+            Frame::Join => self.board_eirp.max_power,
+            Frame::Data => self.configuration.tx_power.unwrap_or(self.board_eirp.max_power),
+        };
+        tx_config.adjust_power(max_power, self.board_eirp.antenna_gain);
+        Ok(tx_config)
+    }
+
+    /// Data rate and channel of the TX configuration selected last.
+    fn tx_channel(&self) -> TxChannel {
+        TxChannel {
+            data_rate: self.configuration.data_rate,
+            index: self.region.get_last_tx_channel(),
+        }
+    }
+
+    /// Prepare FCtrl for a data uplink. New (ie: not repeated) frames are only accounted for ADR
+    /// backoff once they have been prepared, see `adr_backoff()`.
+    fn uplink_fctrl(&self, new_frame: bool) -> FCtrl {
+        let mut fctrl = FCtrl(0x0, true);
+        if let State::Joined(session) = &self.state {
+            let adr_ack_cnt = session.adr_ack_cnt.saturating_add(new_frame.into());
+            if session.adr_ack_req(adr_ack_cnt, &self.region, &self.configuration) {
+                fctrl.set_adr_ack_req();
+            }
+        }
+        if self.configuration.adr {
+            fctrl.set_adr();
+        }
+        #[cfg(feature = "class-b")]
+        if self.class_b.is_locked() {
+            fctrl.set_class_b();
+        }
+        fctrl
+    }
+
+    /// Account for a new uplink frame, which has been prepared, in ADR backoff.
+    #[cfg(any(feature = "multicast", feature = "certification"))]
+    fn adr_backoff(&mut self) {
+        if let State::Joined(session) = &mut self.state {
+            session.adr_backoff(&mut self.region, &mut self.configuration);
+        }
+    }
+
+    /// Number of transmissions of the current (or last) uplink frame.
+    pub(crate) fn get_tx_attempts(&self) -> u8 {
+        self.tx_attempts
+    }
+
+    /// Random delay before retransmitting an unacknowledged confirmed uplink.
+    pub(crate) fn get_ack_timeout<RNG: RngCore>(&self, rng: &mut RNG) -> u32 {
+        // ACK_TIMEOUT is a random delay between 1 and 3 seconds
+        region::constants::ACK_TIMEOUT - 1000 + rng.next_u32() % 2001
     }
 
     pub(crate) fn get_rx_delay(&self, frame: &Frame, window: &Window) -> u32 {
@@ -230,11 +652,21 @@ impl Mac {
         window: &Window,
     ) -> (RfConfig, u32) {
         (
-            self.region.get_rx_config(self.configuration.data_rate, frame, window),
+            self.region.get_rx_config(
+                self.configuration.data_rate,
+                &self.get_rx_settings(frame),
+                frame,
+                window,
+            ),
             self.get_rx_delay(frame, window),
         )
     }
 
+    /// Set the function providing the battery level reported in DevStatusAns.
+    pub(crate) fn set_battery_level_callback(&mut self, battery_level: fn() -> BatteryLevel) {
+        self.battery_level = battery_level;
+    }
+
     /// Handles a received RF frame. Returns None is unparseable, fails decryption, or fails MIC
     /// verification. Upon successful join, provides Response::JoinSuccess. Upon successful data
     /// rx, provides Response::DownlinkReceived. User must take the downlink from vec for
@@ -243,24 +675,65 @@ impl Mac {
         &mut self,
         buf: &mut RadioBuffer<N>,
         dl: &mut Vec<Downlink, D>,
+        rx_quality: RxQuality,
+        window: RxWindow,
+        frequency: u32,
     ) -> Response {
+        let dev_status =
+            session::DevStatus { battery_level: self.battery_level, snr: rx_quality.snr() };
+        let rx = self.get_rx_info(rx_quality, window, frequency);
+        if let Some(request) = &self.rejoin_request {
+            return match request.handle_rx::<C, N>(&mut self.region, &mut self.configuration, buf) {
+                Some(session) => {
+                    self.state = State::Joined(session);
+                    self.rejoin_request = None;
+                    #[cfg(feature = "class-b")]
+                    {
+                        self.class_b = class_b::ClassB::default();
+                    }
+                    Response::JoinSuccess
+                }
+                None => Response::NoUpdate,
+            };
+        }
         match &mut self.state {
-            State::Joined(ref mut session) => session.handle_rx::<C, N, D>(
+            State::Joined(ref mut session) => match session.handle_rx::<C, N, D>(
                 &mut self.region,
                 &mut self.configuration,
                 #[cfg(feature = "certification")]
                 &mut self.certification,
                 #[cfg(feature = "multicast")]
                 &mut self.multicast,
+                #[cfg(feature = "class-b")]
+                &mut self.class_b,
+                &mut self.device_time,
+                &dev_status,
+                &rx,
                 buf,
                 dl,
                 false,
-            ),
+            ) {
+                // The downlink does not acknowledge the confirmed uplink, which is retransmitted
+                // if the retry policy allows it. The downlink itself is still provided.
+                Response::DownlinkReceived { ack: false, .. } if session.confirmed => {
+                    if self.tx_attempts < self.max_tx_attempts {
+                        Response::RetransmitRequest
+                    } else {
+                        session.uplink_complete()
+                    }
+                }
+                response => response,
+            },
             State::Otaa(ref mut otaa) => {
                 if let Some(session) =
                     otaa.handle_rx::<C, N>(&mut self.region, &mut self.configuration, buf)
                 {
                     self.state = State::Joined(session);
+                    self.region.reset_join_backoff();
+                    #[cfg(feature = "class-b")]
+                    {
+                        self.class_b = class_b::ClassB::default();
+                    }
                     Response::JoinSuccess
                 } else {
                     Response::NoUpdate
@@ -270,15 +743,22 @@ impl Mac {
         }
     }
 
-    /// Handles a received RF frame during RXC window. Returns None if unparseable, fails decryption,
-    /// or fails MIC verification. Upon successful data rx, provides Response::DownlinkReceived.
-    /// User must later call `take_downlink()` on the device to get the application data.
-    #[cfg(feature = "class-c")]
+    /// Handles a received RF frame during RXC window or a ping slot. Returns None if unparseable,
+    /// fails decryption, or fails MIC verification. Upon successful data rx, provides
+    /// Response::DownlinkReceived. User must later call `take_downlink()` on the device to get the
+    /// application data.
+    #[cfg(any(feature = "class-b", feature = "class-c"))]
     pub(crate) fn handle_rxc<C: CryptoFactory + Default, const N: usize, const D: usize>(
         &mut self,
         buf: &mut RadioBuffer<N>,
         dl: &mut Vec<Downlink, D>,
+        rx_quality: RxQuality,
+        window: RxWindow,
+        frequency: u32,
     ) -> Result<Response> {
+        let dev_status =
+            session::DevStatus { battery_level: self.battery_level, snr: rx_quality.snr() };
+        let rx = self.get_rx_info(rx_quality, window, frequency);
         match &mut self.state {
             State::Joined(ref mut session) => Ok(session.handle_rx::<C, N, D>(
                 &mut self.region,
@@ -287,6 +767,11 @@ impl Mac {
                 &mut self.certification,
                 #[cfg(feature = "multicast")]
                 &mut self.multicast,
+                #[cfg(feature = "class-b")]
+                &mut self.class_b,
+                &mut self.device_time,
+                &dev_status,
+                &rx,
                 buf,
                 dl,
                 true,
@@ -296,14 +781,52 @@ impl Mac {
         }
     }
 
+    /// Reception of a frame in `window` at `frequency`, with the data rate of the window.
+    fn get_rx_info(&self, quality: RxQuality, window: RxWindow, frequency: u32) -> RxInfo {
+        let rx_settings = self.get_rx_settings(&Frame::Data);
+        let datarate = match window {
+            RxWindow::Rx1 => {
+                self.region.get_rx_dr(self.configuration.data_rate, &rx_settings, &Window::_1)
+            }
+            // RXC uses the RX2 data rate
+            RxWindow::Rx2 | RxWindow::RxC => {
+                self.region.get_rx_dr(self.configuration.data_rate, &rx_settings, &Window::_2)
+            }
+            #[cfg(feature = "class-b")]
+            RxWindow::PingSlot => self
+                .region
+                .beacon_channel()
+                .map_or(region::DR::_0, |channel| self.class_b.ping_slot_data_rate(&channel)),
+            #[cfg(not(feature = "class-b"))]
+            RxWindow::PingSlot => region::DR::_0,
+        };
+        RxInfo { quality, window, frequency, datarate }
+    }
+
     pub(crate) fn rx2_complete(&mut self) -> Response {
+        // The session is kept if the rejoin-request is not answered
+        if self.rejoin_request.take().is_some() {
+            return Response::NoJoinAccept;
+        }
         match &mut self.state {
+            // Uplink still needs to be repeated
+            State::Joined(_) if self.tx_attempts < self.max_tx_attempts => {
+                Response::RetransmitRequest
+            }
             State::Joined(session) => session.rx2_complete(),
             State::Otaa(otaa) => otaa.rx2_complete(),
             State::Unjoined => Response::NoUpdate,
         }
     }
 
+    /// Complete a data uplink which failed after having been transmitted, as if no downlink was
+    /// received, so that its frame counter is not reused.
+    pub(crate) fn abandon_uplink(&mut self) {
+        if let State::Joined(session) = &mut self.state {
+            let _ = session.rx2_complete();
+        }
+    }
+
     pub(crate) fn get_session_keys(&self) -> Option<SessionKeys> {
         match &self.state {
             State::Joined(session) => session.get_session_keys(),
@@ -320,6 +843,122 @@ impl Mac {
         }
     }
 
+    /// Add LinkCheckReq to the next uplink. Returns an error if the device is not joined.
+    pub(crate) fn request_link_check(&mut self) -> Result {
+        match &mut self.state {
+            State::Joined(session) => {
+                session.request_link_check();
+                Ok(())
+            }
+            State::Otaa(_) => Err(Error::NotJoined),
+            State::Unjoined => Err(Error::NotJoined),
+        }
+    }
+
+    pub(crate) fn has_pending_mac_commands(&self) -> bool {
+        self.get_session().is_some_and(|session| !session.uplink.mac_commands().is_empty())
+    }
+
+    pub(crate) fn get_link_check(&self) -> Option<LinkCheck> {
+        self.get_session().and_then(|session| session.link_check)
+    }
+
+    /// Add DeviceTimeReq to the next uplink. Returns an error if the device is not joined.
+    pub(crate) fn request_device_time(&mut self) -> Result {
+        match &mut self.state {
+            State::Joined(session) => {
+                session.request_device_time();
+                Ok(())
+            }
+            State::Otaa(_) => Err(Error::NotJoined),
+            State::Unjoined => Err(Error::NotJoined),
+        }
+    }
+
+    /// Record the end of an uplink transmission, which DeviceTimeAns refers to.
+    pub(crate) fn tx_done(&mut self, now_ms: u64) {
+        self.device_time.tx_done(now_ms);
+    }
+
+    /// Current GPS time, if the network time has been received with DeviceTimeAns.
+    pub(crate) fn get_gps_time(&self, now_ms: u64) -> Option<GpsTime> {
+        self.device_time.gps_time(now_ms)
+    }
+
+    /// Start the beacon search for Class B operation with ping slots every 2^`periodicity`
+    /// seconds. PingSlotInfoReq is sent with the next uplink if the network doesn't know the
+    /// periodicity yet, as is DeviceTimeReq if the network time is unknown.
+    #[cfg(feature = "class-b")]
+    pub(crate) fn enable_class_b(&mut self, periodicity: u8, now_ms: u64) -> Result {
+        let State::Joined(session) = &mut self.state else {
+            return Err(Error::NotJoined);
+        };
+        if self.region.beacon_channel().is_none() {
+            return Err(Error::ClassBUnavailable);
+        }
+        let periodicity = periodicity.min(7);
+        if self.class_b.enable(periodicity) {
+            session.request_ping_slot_info(periodicity);
+        }
+        if self.device_time.gps_ms(now_ms).is_none() {
+            session.request_device_time();
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "class-b")]
+    pub(crate) fn disable_class_b(&mut self) {
+        self.class_b.disable();
+    }
+
+    /// Next beacon or ping slot window of the Class B schedule, opening no earlier than
+    /// `lead_ms` from now.
+    #[cfg(feature = "class-b")]
+    pub(crate) fn get_class_b_window<C: CryptoFactory + Default>(
+        &self,
+        now_ms: u64,
+        lead_ms: u32,
+        buffer_ms: u32,
+    ) -> Result<class_b::Window> {
+        let State::Joined(session) = &self.state else {
+            return Err(Error::NotJoined);
+        };
+        let channel = self.region.beacon_channel().ok_or(Error::ClassBUnavailable)?;
+        if !self.class_b.is_enabled() {
+            return Err(Error::ClassBUnavailable);
+        }
+        self.class_b
+            .next_window::<C>(
+                &self.region,
+                &channel,
+                &self.device_time,
+                u32::from(session.devaddr),
+                now_ms,
+                now_ms + lead_ms as u64,
+                buffer_ms,
+            )
+            .ok_or(Error::NoNetworkTime)
+    }
+
+    /// Handle a frame received in a beacon window ending at `now_ms`.
+    #[cfg(feature = "class-b")]
+    pub(crate) fn handle_beacon<const N: usize>(
+        &mut self,
+        buf: &RadioBuffer<N>,
+        rx_config: &RxConfig,
+        now_ms: u64,
+    ) -> Option<Beacon> {
+        let channel = self.region.beacon_channel()?;
+        self.class_b.handle_beacon(buf.as_ref_for_read(), &channel, rx_config, now_ms)
+    }
+
+    /// Handle a beacon window without a beacon. Returns whether the beacon is lost and the
+    /// device is back to Class A.
+    #[cfg(feature = "class-b")]
+    pub(crate) fn beacon_missed(&mut self, now_ms: u64) -> bool {
+        self.class_b.beacon_missed(now_ms)
+    }
+
     pub(crate) fn is_joined(&self) -> bool {
         matches!(&self.state, State::Joined(_))
     }
@@ -333,34 +972,46 @@ impl Mac {
     }
 
     pub(crate) fn get_rx_config(&self, buffer_ms: u32, frame: &Frame, window: &Window) -> RxConfig {
-        let mut cfg = RxConfig {
-            rf: self.region.get_rx_config(self.configuration.data_rate, frame, window),
+        RxConfig {
+            rf: self.region.get_rx_config(
+                self.configuration.data_rate,
+                &self.get_rx_settings(frame),
+                frame,
+                window,
+            ),
             mode: RxMode::Single { ms: buffer_ms },
-        };
-
-        // Handle server-defined Rx parameters:
-        // * rx2 frequency
-        match frame {
-            Frame::Join => {}
-            Frame::Data => match window {
-                Window::_1 => {}
-                Window::_2 => {
-                    if let Some(f) = self.configuration.rx2_frequency {
-                        cfg.rf.frequency = f;
-                    }
-                }
-            },
         }
-        cfg
     }
 
     #[cfg(feature = "class-c")]
     pub(crate) fn get_rxc_config(&self) -> RxConfig {
         RxConfig {
-            rf: self.region.get_rxc_config(self.configuration.data_rate),
+            rf: self.region.get_rxc_config(&self.get_rx_settings(&Frame::Data)),
             mode: RxMode::Continuous,
         }
     }
+
+    /// Server-defined receive window settings apply to data frames of the current session only.
+    fn get_rx_settings(&self, frame: &Frame) -> RxSettings {
+        match (&self.state, frame) {
+            (State::Joined(session), Frame::Data) => session.rx_settings,
+            _ => RxSettings::default(),
+        }
+    }
+}
+
+/// Error for a frame at `datarate` for which no channel could be selected: either all the channels
+/// supporting the data rate are blocked by duty-cycle limits or there is none.
+fn no_tx_channel(
+    region: &region::Configuration,
+    frame: &Frame,
+    datarate: region::DR,
+    now_ms: u64,
+) -> Error {
+    match region.time_until_available(frame, datarate, now_ms) {
+        0 => Error::NoChannelForDataRate,
+        retry_after_ms => Error::DutyCycle { retry_after_ms },
+    }
 }
 
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
@@ -368,7 +1019,13 @@ impl Mac {
 pub(crate) enum Response {
     NoAck,
     SessionExpired,
-    DownlinkReceived(FcntDown),
+    /// A downlink was received, `ack` and `f_pending` are the ACK and FPending bits of its FCtrl.
+    DownlinkReceived {
+        fcnt: FcntDown,
+        ack: bool,
+        f_pending: bool,
+    },
+    RetransmitRequest,
     NoJoinAccept,
     JoinSuccess,
     NoUpdate,
@@ -394,12 +1051,14 @@ impl From<Response> for nb_device::Response {
     fn from(r: Response) -> Self {
         match r {
             Response::SessionExpired => nb_device::Response::SessionExpired,
-            Response::DownlinkReceived(fcnt) => nb_device::Response::DownlinkReceived(fcnt),
+            Response::DownlinkReceived { fcnt, .. } => nb_device::Response::DownlinkReceived(fcnt),
             Response::NoAck => nb_device::Response::NoAck,
             Response::NoJoinAccept => nb_device::Response::NoJoinAccept,
             Response::JoinSuccess => nb_device::Response::JoinSuccess,
             Response::NoUpdate => nb_device::Response::NoUpdate,
             Response::RxComplete => nb_device::Response::RxComplete,
+            // The state machine repeats the uplink, there is nothing to report yet
+            Response::RetransmitRequest => nb_device::Response::NoUpdate,
             #[cfg(feature = "certification")]
             Response::UplinkPrepared => unimplemented!(),
             #[cfg(feature = "certification")]
@@ -410,12 +1069,19 @@ impl From<Response> for nb_device::Response {
     }
 }
 
-impl From<Response> for async_device::SendResponse {
-    fn from(r: Response) -> async_device::SendResponse {
+impl async_device::SendResponse {
+    pub(crate) fn from_mac(r: Response, attempts: u8) -> async_device::SendResponse {
         match r {
             Response::SessionExpired => async_device::SendResponse::SessionExpired,
-            Response::DownlinkReceived(fcnt) => async_device::SendResponse::DownlinkReceived(fcnt),
-            Response::NoAck => async_device::SendResponse::NoAck,
+            Response::DownlinkReceived { fcnt, ack, f_pending } => {
+                async_device::SendResponse::DownlinkReceived {
+                    fcnt_down: fcnt,
+                    attempts,
+                    ack,
+                    f_pending,
+                }
+            }
+            Response::NoAck => async_device::SendResponse::NoAck { attempts },
             Response::RxComplete => async_device::SendResponse::RxComplete,
             #[cfg(feature = "multicast")]
             Response::Multicast(mc) => async_device::SendResponse::Multicast(mc.into()),
@@ -438,8 +1104,8 @@ impl From<Response> for async_device::ListenResponse {
     fn from(r: Response) -> async_device::ListenResponse {
         match r {
             Response::SessionExpired => async_device::ListenResponse::SessionExpired,
-            Response::DownlinkReceived(fcnt) => {
-                async_device::ListenResponse::DownlinkReceived(fcnt)
+            Response::DownlinkReceived { fcnt, ack, f_pending } => {
+                async_device::ListenResponse::DownlinkReceived { fcnt_down: fcnt, ack, f_pending }
             }
             #[cfg(feature = "multicast")]
             Response::Multicast(mc) => async_device::ListenResponse::Multicast(mc.into()),
@@ -448,9 +1114,43 @@ impl From<Response> for async_device::ListenResponse {
     }
 }
 
+/// Reconstruct the 32-bit frame counter of a downlink from the 16 LSBs transmitted in FHDR, given
+/// the frame counter expected for the next downlink. Returns `None` for frames which were already
+/// received (ie: replays) or if more than MAX_FCNT_GAP frames were lost.
+pub(crate) fn fcnt_down_from_lsb(fcnt_lsb: u16, next_fcnt: u32) -> Option<u32> {
+    let mut fcnt = (next_fcnt & 0xFFFF_0000) | fcnt_lsb as u32;
+    if fcnt < next_fcnt {
+        // The 16 LSBs have rolled over
+        fcnt = fcnt.checked_add(0x1_0000)?;
+    }
+    (fcnt - next_fcnt < region::constants::MAX_FCNT_GAP).then_some(fcnt)
+}
+
 fn del_to_delay_ms(del: u8) -> u32 {
     match del {
         2..=15 => del as u32 * 1000,
         _ => region::constants::RECEIVE_DELAY1,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use region::constants::MAX_FCNT_GAP;
+
+    #[test]
+    fn fcnt_down_reconstruction() {
+        assert_eq!(fcnt_down_from_lsb(0, 0), Some(0));
+        assert_eq!(fcnt_down_from_lsb(5, 3), Some(5));
+        // Already received
+        assert_eq!(fcnt_down_from_lsb(2, 3), None);
+        // Rollover of the 16 LSBs
+        assert_eq!(fcnt_down_from_lsb(0x0002, 0x1_FFF0), Some(0x2_0002));
+        assert_eq!(fcnt_down_from_lsb(0xFFF0, 0x1_FFF0), Some(0x1_FFF0));
+        // Too many lost frames
+        assert_eq!(fcnt_down_from_lsb(MAX_FCNT_GAP as u16, 0), None);
+        assert_eq!(fcnt_down_from_lsb(MAX_FCNT_GAP as u16 - 1, 0), Some(MAX_FCNT_GAP - 1));
+        // No frame counter left
+        assert_eq!(fcnt_down_from_lsb(0x0001, 0xFFFF_FFF0), None);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::mac::FcntDown;
 use crate::radio::RadioBuffer;
-use crate::Downlink;
 use crate::{async_device, mac};
+use crate::{Downlink, DownlinkMetadata};
 use core::fmt::Debug;
 use core::ops::RangeInclusive;
 use lorawan::keys::{CryptoFactory, McKEKey};
@@ -12,7 +12,7 @@ use lorawan::multicast::{
 };
 use lorawan::parser::FRMPayload;
 pub use lorawan::parser::McAddr;
-use lorawan::parser::{DataHeader, EncryptedDataPayload};
+use lorawan::parser::{DataHeader, EncryptedDataPayload, FCtrl};
 
 #[derive(Debug)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
@@ -65,27 +65,29 @@ impl Multicast {
         &mut self,
         dl: &mut heapless::Vec<Downlink, D>,
         encrypted_data: EncryptedDataPayload<&mut [u8], C>,
+        rx_info: &mac::RxInfo,
     ) -> Response {
         let mc_addr = encrypted_data.fhdr().mc_addr();
         if let Some((group_id, session)) = self.matching_session(mc_addr) {
-            let fcnt = encrypted_data.fhdr().fcnt() as u32;
-            if encrypted_data.validate_mic(session.mc_net_s_key().inner(), fcnt)
-                && (fcnt > session.fcnt_down || fcnt == 0)
+            // `fcnt_down` is the next expected frame counter, starting at minMcFCount
+            let fcnt = mac::fcnt_down_from_lsb(encrypted_data.fhdr().fcnt(), session.fcnt_down);
+            if let Some(fcnt) = fcnt
+                .filter(|fcnt| encrypted_data.validate_mic(session.mc_net_s_key().inner(), *fcnt))
             {
                 return {
-                    session.fcnt_down = fcnt;
                     // We can safely unwrap here because we already validated the MIC
                     let decrypted = encrypted_data
                         .decrypt(
                             Some(session.mc_net_s_key().inner()),
                             Some(session.mc_app_s_key().inner()),
-                            session.fcnt_down,
+                            fcnt,
                         )
                         .unwrap();
-                    if session.fcnt_down == session.max_fcnt_down() {
-                        // if the FCnt is used up, the session has expired
+                    if fcnt >= session.max_fcnt_down() {
+                        // Frames are only accepted up to maxMcFCount, the session has expired
                         Response::SessionExpired { group_id }
                     } else {
+                        session.fcnt_down = fcnt + 1;
                         if let (Some(fport), FRMPayload::Data(data)) =
                             (decrypted.f_port(), decrypted.frm_payload())
                         {
@@ -93,7 +95,11 @@ impl Multicast {
                             // A data FRM payload will never exceed 256 bytes.
                             let data = heapless::Vec::from_slice(data).unwrap();
                             // TODO: propagate error when heapless vec is full?
-                            let _ = dl.push(Downlink { data, fport });
+                            let metadata = DownlinkMetadata {
+                                multicast_group: Some(group_id),
+                                ..rx_info.downlink_metadata(fcnt)
+                            };
+                            let _ = dl.push(Downlink { data, fport, metadata });
                         }
                         Response::DownlinkReceived { group_id, fcnt }
                     }
@@ -200,6 +206,8 @@ impl Multicast {
     pub(crate) fn setup_send<C: CryptoFactory + Default, const N: usize>(
         &mut self,
         mut state: &mut mac::State,
+        fctrl: FCtrl,
+        tx_channel: &mac::TxChannel,
         buf: &mut RadioBuffer<N>,
     ) -> mac::Result<mac::FcntUp> {
         let send_data = mac::SendData {
@@ -209,7 +217,11 @@ impl Multicast {
         };
         match &mut state {
             mac::State::Joined(ref mut session) => {
-                let response = session.prepare_buffer::<C, N>(&send_data, buf);
+                let response =
+                    session.prepare_buffer::<C, N>(&send_data, fctrl, tx_channel, buf)?;
+                // This frame is never repeated, so its answers can be dropped right away
+                session.uplink.clear_mac_commands(true);
+                session.uplink.clear_downlink_confirmation();
                 self.pending_uplinks.clear();
                 Ok(response)
             }
@@ -1,8 +1,9 @@
 use super::{del_to_delay_ms, session::Session, Response};
 use crate::radio::RadioBuffer;
 use crate::region::Configuration;
-use crate::{AppEui, AppKey, DevEui};
+use crate::{AppEui, AppKey, DevEui, NwkKey};
 use lorawan::keys::CryptoFactory;
+use lorawan::types::JoinReqType;
 use lorawan::{
     creator::JoinRequestCreator,
     parser::{parse_with_factory as lorawan_parse, *},
@@ -11,6 +12,67 @@ use rand_core::RngCore;
 
 pub(crate) type DevNonce = lorawan::parser::DevNonce<[u8; 2]>;
 
+/// How the DevNonce of join requests is generated.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum DevNonceMode {
+    /// Counter incremented with every join request, as required by LoRaWAN 1.0.4. Join servers
+    /// reject DevNonce values which have already been used, so the counter must be persisted and
+    /// restored after reboots.
+    #[default]
+    Counter,
+    /// Random DevNonce, for legacy LoRaWAN 1.0.2 networks.
+    Random,
+}
+
+/// Provides the DevNonce for join requests and keeps track of whether the counter needs to be
+/// persisted.
+#[derive(Default)]
+pub(crate) struct DevNonceGenerator {
+    mode: DevNonceMode,
+    /// DevNonce of the next join request, above `u16::MAX` once all values have been used
+    next: u32,
+    updated: bool,
+}
+
+impl DevNonceGenerator {
+    pub fn set_mode(&mut self, mode: DevNonceMode) {
+        self.mode = mode;
+    }
+
+    pub fn get_mode(&self) -> DevNonceMode {
+        self.mode
+    }
+
+    /// Restore the counter, ie: the DevNonce of the next join request.
+    pub fn set_next(&mut self, next: u32) {
+        self.next = next;
+        self.updated = false;
+    }
+
+    pub fn get_next(&self) -> u32 {
+        self.next
+    }
+
+    /// Returns the counter if it has changed since it was last restored or taken.
+    pub fn take_update(&mut self) -> Option<u32> {
+        core::mem::take(&mut self.updated).then_some(self.next)
+    }
+
+    /// DevNonce for a new join request, or `None` if the counter is used up.
+    fn generate<G: RngCore>(&mut self, rng: &mut G) -> Option<u16> {
+        match self.mode {
+            DevNonceMode::Counter => {
+                let dev_nonce = u16::try_from(self.next).ok()?;
+                self.next += 1;
+                self.updated = true;
+                Some(dev_nonce)
+            }
+            DevNonceMode::Random => Some(rng.next_u32() as u16),
+        }
+    }
+}
+
 pub(crate) struct Otaa {
     dev_nonce: DevNonce,
     network_credentials: NetworkCredentials,
@@ -21,6 +83,8 @@ pub struct NetworkCredentials {
     deveui: DevEui,
     appeui: AppEui,
     appkey: AppKey,
+    /// NwkKey of LoRaWAN 1.1 end-devices
+    nwkkey: Option<NwkKey>,
 }
 
 impl Otaa {
@@ -29,22 +93,28 @@ impl Otaa {
     }
 
     /// Prepare a join request to be sent. This populates the radio buffer with the request to be
-    /// sent, and returns the radio config to use for transmitting.
+    /// sent, and returns the DevNonce of the request, or `None` if DevNonce values are used up.
     pub(crate) fn prepare_buffer<C: CryptoFactory + Default, G: RngCore, const N: usize>(
         &mut self,
         rng: &mut G,
+        dev_nonce: &mut DevNonceGenerator,
         buf: &mut RadioBuffer<N>,
-    ) -> u16 {
-        self.dev_nonce = DevNonce::from(rng.next_u32() as u16);
+    ) -> Option<u16> {
+        let nonce = dev_nonce.generate(rng)?;
+        // DevNonce is transmitted little-endian
+        self.dev_nonce = DevNonce::from(nonce.to_le_bytes());
         buf.clear();
         let mut phy = JoinRequestCreator::new(buf.as_mut()).unwrap();
         phy.set_app_eui(self.network_credentials.appeui)
             .set_dev_eui(self.network_credentials.deveui)
             .set_dev_nonce(self.dev_nonce);
         let crypto_factory = C::default();
-        let len = phy.build(&self.network_credentials.appkey, &crypto_factory).len();
+        let len = match &self.network_credentials.nwkkey {
+            Some(nwkkey) => phy.build_with_nwk_key(nwkkey, &crypto_factory).len(),
+            None => phy.build(&self.network_credentials.appkey, &crypto_factory).len(),
+        };
         buf.set_pos(len);
-        u16::from(self.dev_nonce)
+        Some(nonce)
     }
 
     pub(crate) fn handle_rx<C: CryptoFactory + Default, const N: usize>(
@@ -56,15 +126,25 @@ impl Otaa {
         if let Ok(PhyPayload::JoinAccept(JoinAcceptPayload::Encrypted(encrypted))) =
             lorawan_parse(rx.as_mut_for_read(), C::default())
         {
-            let decrypt = encrypted.decrypt(&self.network_credentials.appkey);
+            let creds = &self.network_credentials;
+            let decrypt = match &creds.nwkkey {
+                Some(nwkkey) => encrypted.decrypt_with_nwk_key(nwkkey),
+                None => encrypted.decrypt(&creds.appkey),
+            };
             region.process_join_accept(&decrypt);
             configuration.rx1_delay = del_to_delay_ms(decrypt.rx_delay());
-            if decrypt.validate_mic(&self.network_credentials.appkey) {
-                return Some(Session::derive_new(
-                    &decrypt,
-                    self.dev_nonce,
-                    &self.network_credentials,
-                ));
+            let mic_valid = match &creds.nwkkey {
+                Some(nwkkey) => decrypt.validate_mic_1_1(
+                    nwkkey,
+                    &nwkkey.derive_js_int_key(&C::default(), &creds.deveui),
+                    JoinReqType::JoinRequest,
+                    &creds.appeui,
+                    &self.dev_nonce,
+                ),
+                None => decrypt.validate_mic(&creds.appkey),
+            };
+            if mic_valid {
+                return Some(new_session(region, &decrypt, self.dev_nonce, creds));
             }
         }
         None
@@ -75,9 +155,32 @@ impl Otaa {
     }
 }
 
+/// Derives the session established by a valid join-accept, answering a join-request or a
+/// rejoin-request.
+pub(crate) fn new_session<T: AsRef<[u8]>, F: CryptoFactory>(
+    region: &Configuration,
+    decrypt: &DecryptedJoinAcceptPayload<T, F>,
+    dev_nonce: DevNonce,
+    credentials: &NetworkCredentials,
+) -> Session {
+    let mut session = Session::derive_new(decrypt, dev_nonce, credentials);
+    // Invalid DLSettings values are ignored and region defaults are used instead
+    let dl = decrypt.dl_settings();
+    if region.rx1_dr_offset_valid(dl.rx1_dr_offset()) {
+        session.rx_settings.rx1_dr_offset = dl.rx1_dr_offset();
+    }
+    session.rx_settings.rx2_data_rate = region.check_rx2_data_rate(dl.rx2_data_rate());
+    session
+}
+
 impl NetworkCredentials {
     pub fn new(appeui: AppEui, deveui: DevEui, appkey: AppKey) -> Self {
-        Self { deveui, appeui, appkey }
+        Self { deveui, appeui, appkey, nwkkey: None }
+    }
+
+    /// Credentials of a LoRaWAN 1.1 end-device, where `appeui` is the JoinEUI.
+    pub fn new_1_1(appeui: AppEui, deveui: DevEui, appkey: AppKey, nwkkey: NwkKey) -> Self {
+        Self { deveui, appeui, appkey, nwkkey: Some(nwkkey) }
     }
     pub fn appeui(&self) -> &AppEui {
         &self.appeui
@@ -90,4 +193,8 @@ impl NetworkCredentials {
     pub fn appkey(&self) -> &AppKey {
         &self.appkey
     }
+
+    pub fn nwkkey(&self) -> Option<&NwkKey> {
+        self.nwkkey.as_ref()
+    }
 }
@@ -0,0 +1,217 @@
+//! Rejoin-requests of LoRaWAN 1.1 end-devices: periodic type 0 rejoin-requests set up with
+//! RejoinParamSetupReq and rejoin-requests requested by the network with ForceRejoinReq.
+use super::{del_to_delay_ms, otaa, otaa::NetworkCredentials, Session};
+use crate::radio::RadioBuffer;
+use crate::region::{self, DR};
+use lorawan::keys::CryptoFactory;
+use lorawan::maccommands::{ForceRejoinReqPayload, RejoinParamSetupReqPayload};
+use lorawan::parser::{parse_with_factory as lorawan_parse, *};
+use lorawan::types::{JoinReqType, RejoinType};
+
+/// Rejoin state of a LoRaWAN 1.1 session.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rejoin {
+    /// NetID of the network, provided by the join-accept
+    pub net_id: [u8; 3],
+    /// Counter of type 0 and type 2 rejoin-requests (RJcount0)
+    pub rj_count0: u16,
+    /// Limits for periodic type 0 rejoin-requests set with RejoinParamSetupReq
+    pub params: Option<RejoinParams>,
+    /// Number of uplinks since the last type 0 rejoin-request
+    pub uplink_count: u32,
+    /// Rejoin-requests requested by the network with ForceRejoinReq
+    pub forced: Option<ForcedRejoin>,
+    /// Local time of the last type 0 rejoin-request, or of the first check for a due
+    /// rejoin-request
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) last_ms: Option<u64>,
+}
+
+/// Periodic type 0 rejoin-requests: at least every 2^(MaxCountN + 4) uplinks and every
+/// 2^(MaxTimeN + 10) seconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RejoinParams {
+    pub max_time_n: u8,
+    pub max_count_n: u8,
+}
+
+impl RejoinParams {
+    fn max_count(&self) -> u32 {
+        1 << (self.max_count_n + 4)
+    }
+
+    fn max_time_ms(&self) -> u64 {
+        1000 << (self.max_time_n + 10)
+    }
+}
+
+/// Rejoin-requests requested by the network with ForceRejoinReq.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ForcedRejoin {
+    pub rejoin_type: RejoinType,
+    pub data_rate: DR,
+    /// Number of transmissions left
+    pub transmissions: u8,
+    /// Retransmissions are delayed by 32 s * 2^period plus a random delay of up to 32 s
+    pub period: u8,
+    /// Local time of the next transmission
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) next_ms: u64,
+}
+
+impl Rejoin {
+    pub fn new(net_id: [u8; 3]) -> Self {
+        Self { net_id, ..Default::default() }
+    }
+
+    /// ForceRejoinReq asks for a rejoin-request right away, repeated up to MaxRetries times.
+    /// Requests with an RFU rejoin type are ignored.
+    pub(crate) fn handle_force_rejoin_req(
+        &mut self,
+        payload: &ForceRejoinReqPayload<'_>,
+        data_rate: DR,
+    ) {
+        if let Ok(rejoin_type) = payload.rejoin_type() {
+            self.forced = Some(ForcedRejoin {
+                rejoin_type,
+                data_rate,
+                transmissions: payload.max_retries() + 1,
+                period: payload.period(),
+                next_ms: 0,
+            });
+        }
+    }
+
+    pub(crate) fn handle_param_setup_req(&mut self, payload: &RejoinParamSetupReqPayload<'_>) {
+        self.params = Some(RejoinParams {
+            max_time_n: payload.max_time_n(),
+            max_count_n: payload.max_count_n(),
+        });
+    }
+
+    pub(crate) fn uplink_sent(&mut self) {
+        self.uplink_count = self.uplink_count.saturating_add(1);
+    }
+
+    /// Type of the rejoin-request which is due, if any.
+    pub(crate) fn due(&mut self, now_ms: u64) -> Option<RejoinType> {
+        if let Some(forced) = self.forced.filter(|forced| now_ms >= forced.next_ms) {
+            return Some(forced.rejoin_type);
+        }
+        let params = self.params?;
+        let last_ms = *self.last_ms.get_or_insert(now_ms);
+        (self.uplink_count >= params.max_count()
+            || now_ms.saturating_sub(last_ms) >= params.max_time_ms())
+        .then_some(RejoinType::Type0)
+    }
+
+    /// Data rate requested by the network for rejoin-requests of the given type.
+    pub(crate) fn data_rate(&self, rejoin_type: RejoinType) -> Option<DR> {
+        self.forced.filter(|forced| forced.rejoin_type == rejoin_type).map(|f| f.data_rate)
+    }
+
+    /// Accounts for a transmitted rejoin-request and schedules the next forced one, `jitter_ms`
+    /// being a random delay of up to 32 s.
+    pub(crate) fn transmitted(&mut self, rejoin_type: RejoinType, now_ms: u64, jitter_ms: u32) {
+        if rejoin_type != RejoinType::Type1 {
+            self.rj_count0 = self.rj_count0.saturating_add(1);
+        }
+        if rejoin_type == RejoinType::Type0 {
+            self.uplink_count = 0;
+            self.last_ms = Some(now_ms);
+        }
+        if let Some(forced) = self.forced.as_mut().filter(|f| f.rejoin_type == rejoin_type) {
+            forced.transmissions -= 1;
+            forced.next_ms = now_ms + (32_000 << forced.period) + jitter_ms as u64;
+            if forced.transmissions == 0 {
+                self.forced = None;
+            }
+        }
+    }
+}
+
+/// A rejoin-request which has been sent and awaits a join-accept.
+pub(crate) struct RejoinRequest {
+    pub rejoin_type: RejoinType,
+    pub rj_count: u16,
+    pub credentials: NetworkCredentials,
+}
+
+impl RejoinRequest {
+    /// Handles the join-accept answering the rejoin-request, which is encrypted with JSEncKey
+    /// and uses RJcount instead of DevNonce. Returns the new session if the join-accept is valid.
+    pub(crate) fn handle_rx<C: CryptoFactory + Default, const N: usize>(
+        &self,
+        region: &mut region::Configuration,
+        configuration: &mut super::Configuration,
+        rx: &mut RadioBuffer<N>,
+    ) -> Option<Session> {
+        let creds = &self.credentials;
+        let nwkkey = creds.nwkkey()?;
+        if let Ok(PhyPayload::JoinAccept(JoinAcceptPayload::Encrypted(encrypted))) =
+            lorawan_parse(rx.as_mut_for_read(), C::default())
+        {
+            let factory = C::default();
+            let decrypt = encrypted
+                .decrypt_with_js_enc_key(&nwkkey.derive_js_enc_key(&factory, creds.deveui()));
+            let rj_count = otaa::DevNonce::from(self.rj_count.to_le_bytes());
+            if decrypt.validate_mic_1_1(
+                nwkkey,
+                &nwkkey.derive_js_int_key(&factory, creds.deveui()),
+                JoinReqType::from(self.rejoin_type),
+                creds.appeui(),
+                &rj_count,
+            ) {
+                region.process_join_accept(&decrypt);
+                configuration.rx1_delay = del_to_delay_ms(decrypt.rx_delay());
+                return Some(otaa::new_session(region, &decrypt, rj_count, creds));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn periodic_rejoin() {
+        let mut rejoin = Rejoin::new([1, 2, 3]);
+        assert_eq!(rejoin.due(0), None);
+        rejoin.params = Some(RejoinParams { max_time_n: 0, max_count_n: 0 });
+        // The time limit starts with the first check
+        assert_eq!(rejoin.due(5_000), None);
+        for _ in 0..16 {
+            rejoin.uplink_sent();
+        }
+        assert_eq!(rejoin.due(6_000), Some(RejoinType::Type0));
+        rejoin.transmitted(RejoinType::Type0, 6_000, 0);
+        assert_eq!(rejoin.rj_count0, 1);
+        assert_eq!(rejoin.due(6_000 + 1_023_999), None);
+        assert_eq!(rejoin.due(6_000 + 1_024_000), Some(RejoinType::Type0));
+    }
+
+    #[test]
+    fn forced_rejoin() {
+        let mut rejoin = Rejoin::new([1, 2, 3]);
+        // Period 1, MaxRetries 1, RejoinType 2, DR 3
+        let payload = ForceRejoinReqPayload::new(&[0x23, 0x09]).unwrap();
+        rejoin.handle_force_rejoin_req(&payload, DR::_3);
+        assert_eq!(rejoin.due(0), Some(RejoinType::Type2));
+        assert_eq!(rejoin.data_rate(RejoinType::Type2), Some(DR::_3));
+        assert_eq!(rejoin.data_rate(RejoinType::Type0), None);
+        rejoin.transmitted(RejoinType::Type2, 1_000, 500);
+        assert_eq!(rejoin.due(65_499), None);
+        assert_eq!(rejoin.due(65_500), Some(RejoinType::Type2));
+        rejoin.transmitted(RejoinType::Type2, 65_500, 0);
+        assert_eq!(rejoin.forced, None);
+        assert_eq!(rejoin.rj_count0, 2);
+    }
+}
@@ -1,16 +1,28 @@
 use super::{
     otaa::{DevNonce, NetworkCredentials},
-    uplink, FcntUp, Response, SendData,
+    rejoin::Rejoin,
+    uplink, Error, FcntUp, Response, RxInfo, SendData,
 };
 use crate::radio::RadioBuffer;
-use crate::{region, AppSKey, Downlink, NwkSKey};
+use crate::region::constants::{ADR_ACK_DELAY, ADR_ACK_LIMIT};
+use crate::{region, region::DR, AppSKey, Downlink, NwkSKey};
 use heapless::Vec;
-use lorawan::keys::CryptoFactory;
+use lorawan::keys::{CryptoFactory, FNwkSIntKey, NwkSEncKey, SNwkSIntKey};
+#[cfg(feature = "class-b")]
+use lorawan::maccommandcreator::{BeaconFreqAnsCreator, PingSlotChannelAnsCreator};
 use lorawan::maccommandcreator::{
-    DevStatusAnsCreator, LinkADRAnsCreator, NewChannelAnsCreator, RXParamSetupAnsCreator,
-    RXTimingSetupAnsCreator,
+    DevStatusAnsCreator, DeviceTimeReqCreator, DlChannelAnsCreator, DutyCycleAnsCreator,
+    LinkADRAnsCreator, LinkCheckReqCreator, NewChannelAnsCreator, RXParamSetupAnsCreator,
+    RXTimingSetupAnsCreator, RejoinParamSetupAnsCreator, RekeyIndCreator, TXParamSetupAnsCreator,
+};
+use lorawan::maccommands::{
+    parse_uplink_mac_commands, DownlinkMacCommand, MacCommandIterator, SerializableMacCommand,
+    UplinkMacCommand,
+};
+use lorawan::packet_length::phy::mac::{
+    fhdr::{FHDR_MIN_LEN, FOPTS_MAX_LEN},
+    FPORT_LEN,
 };
-use lorawan::maccommands::{DownlinkMacCommand, MacCommandIterator};
 use lorawan::{
     creator::DataPayloadCreator,
     parser::{parse_with_factory as lorawan_parse, *},
@@ -25,12 +37,26 @@ use super::DeviceEvent;
 pub struct Session {
     pub uplink: uplink::Uplink,
     pub confirmed: bool,
+    /// NwkSKey, or FNwkSIntKey of LoRaWAN 1.1 sessions
     pub nwkskey: NwkSKey,
     pub appskey: AppSKey,
     pub devaddr: DevAddr<[u8; 4]>,
     pub fcnt_up: u32,
+    /// Frame counter of the last downlink received, 0 if none has been received yet. For
+    /// LoRaWAN 1.1 sessions, this is the network frame counter (NFCntDown).
     pub fcnt_down: u32,
-    // TODO: ADR handling
+    /// Number of uplinks sent since the last Class A downlink (ADR_ACK_CNT)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub adr_ack_cnt: u32,
+    /// Receive window settings provided by the network
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rx_settings: RxSettings,
+    /// Answer to the last LinkCheckReq, if any
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub link_check: Option<LinkCheck>,
+    /// Additional state of sessions with a LoRaWAN 1.1 network server
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub lorawan_1_1: Option<Session1_1>,
     #[cfg(feature = "certification")]
     /// Whether to force ADR bit for subsequent frames
     pub override_adr: bool,
@@ -42,6 +68,83 @@ pub struct Session {
     pub rx_app_cnt: u16,
 }
 
+/// Receive window settings set by the network with the join-accept DLSettings field or with
+/// RXParamSetupReq. `None` stands for the region default.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RxSettings {
+    pub rx1_dr_offset: u8,
+    pub rx2_data_rate: Option<DR>,
+    pub rx2_frequency: Option<u32>,
+}
+
+/// Session keys and counters which only exist in LoRaWAN 1.1 sessions.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Session1_1 {
+    pub snwksintkey: SNwkSIntKey,
+    pub nwksenckey: NwkSEncKey,
+    /// Frame counter expected for the next downlink with application data (AFCntDown)
+    pub afcnt_down: u32,
+    /// 16 LSBs of the frame counter of the last confirmed downlink, acknowledged by the next
+    /// uplink
+    pub conf_fcnt_down: u16,
+    /// Whether the network has answered RekeyInd with RekeyConf
+    pub rekey_confirmed: bool,
+    /// Rejoin-request state
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rejoin: Rejoin,
+}
+
+/// Data rate and channel index of an uplink, which are covered by the LoRaWAN 1.1 uplink MIC.
+pub(crate) struct TxChannel {
+    pub data_rate: DR,
+    pub index: u8,
+}
+
+/// Link quality reported by the network in LinkCheckAns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LinkCheck {
+    /// Link margin in dB above the demodulation floor of the LinkCheckReq uplink, as received
+    /// by the best gateway.
+    pub margin: u8,
+    /// Number of gateways that received the LinkCheckReq uplink.
+    pub gateway_count: u8,
+}
+
+/// Battery level reported to the network in DevStatusAns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum BatteryLevel {
+    /// The device is connected to an external power source.
+    ExternalPower,
+    /// Battery level from 1 (minimum) to 254 (maximum).
+    Level(u8),
+    /// The device is not able to measure the battery level.
+    Unknown,
+}
+
+impl From<BatteryLevel> for u8 {
+    fn from(level: BatteryLevel) -> u8 {
+        match level {
+            BatteryLevel::ExternalPower => 0,
+            BatteryLevel::Level(level) => level.clamp(1, 254),
+            BatteryLevel::Unknown => 255,
+        }
+    }
+}
+
+/// Device status for answering DevStatusReq.
+pub(crate) struct DevStatus {
+    pub battery_level: fn() -> BatteryLevel,
+    /// SNR of the received downlink
+    pub snr: i8,
+}
+
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
 pub struct SessionKeys {
@@ -62,17 +165,38 @@ impl Session {
         devnonce: DevNonce,
         credentials: &NetworkCredentials,
     ) -> Self {
-        Self::new(
-            decrypt.derive_nwkskey(&devnonce, credentials.appkey()),
-            decrypt.derive_appskey(&devnonce, credentials.appkey()),
-            DevAddr::new([
-                decrypt.dev_addr().as_ref()[0],
-                decrypt.dev_addr().as_ref()[1],
-                decrypt.dev_addr().as_ref()[2],
-                decrypt.dev_addr().as_ref()[3],
-            ])
-            .unwrap(),
-        )
+        let devaddr = DevAddr::new([
+            decrypt.dev_addr().as_ref()[0],
+            decrypt.dev_addr().as_ref()[1],
+            decrypt.dev_addr().as_ref()[2],
+            decrypt.dev_addr().as_ref()[3],
+        ])
+        .unwrap();
+        let Some(nwkkey) = credentials.nwkkey() else {
+            return Self::new(
+                decrypt.derive_nwkskey(&devnonce, credentials.appkey()),
+                decrypt.derive_appskey(&devnonce, credentials.appkey()),
+                devaddr,
+            );
+        };
+
+        let joineui = credentials.appeui();
+        let fnwksintkey = decrypt.derive_fnwksintkey(nwkkey, joineui, &devnonce);
+        let appskey = decrypt.derive_appskey_1_1(credentials.appkey(), nwkkey, joineui, &devnonce);
+        let mut session = Self::new(NwkSKey::from(fnwksintkey.inner().0), appskey, devaddr);
+        // Without OptNeg, the network server implements LoRaWAN 1.0 and all network session keys
+        // are the same.
+        if decrypt.opt_neg() {
+            session.lorawan_1_1 = Some(Session1_1 {
+                snwksintkey: decrypt.derive_snwksintkey(nwkkey, joineui, &devnonce),
+                nwksenckey: decrypt.derive_nwksenckey(nwkkey, joineui, &devnonce),
+                afcnt_down: 0,
+                conf_fcnt_down: 0,
+                rekey_confirmed: false,
+                rejoin: Rejoin::new(decrypt.net_id().as_ref().try_into().unwrap()),
+            });
+        }
+        session
     }
 
     pub fn new(nwkskey: NwkSKey, appskey: AppSKey, devaddr: DevAddr<[u8; 4]>) -> Self {
@@ -83,6 +207,10 @@ impl Session {
             confirmed: false,
             fcnt_down: 0,
             fcnt_up: 0,
+            adr_ack_cnt: 0,
+            rx_settings: RxSettings::default(),
+            link_check: None,
+            lorawan_1_1: None,
             uplink: uplink::Uplink::default(),
 
             #[cfg(feature = "certification")]
@@ -112,6 +240,27 @@ impl Session {
     pub fn get_session_keys(&self) -> Option<SessionKeys> {
         Some(SessionKeys { nwkskey: self.nwkskey, appskey: self.appskey, devaddr: self.devaddr })
     }
+
+    /// Request link quality from the network with the next uplink. The previous answer is
+    /// discarded.
+    pub(crate) fn request_link_check(&mut self) {
+        self.link_check = None;
+        self.uplink.add_mac_command_once(LinkCheckReqCreator::new());
+    }
+
+    /// Request the network time with the next uplink.
+    pub(crate) fn request_device_time(&mut self) {
+        self.uplink.add_mac_command_once(DeviceTimeReqCreator::new());
+    }
+
+    /// Inform the network about the ping slot periodicity with the next uplink.
+    #[cfg(feature = "class-b")]
+    pub(crate) fn request_ping_slot_info(&mut self, periodicity: u8) {
+        let mut cmd = lorawan::maccommandcreator::PingSlotInfoReqCreator::new();
+        // Periodicity has been limited to 7 by the caller
+        let _ = cmd.set_periodicity(periodicity);
+        self.uplink.add_mac_command_once(cmd);
+    }
 }
 
 impl Session {
@@ -122,6 +271,10 @@ impl Session {
         configuration: &mut super::Configuration,
         #[cfg(feature = "certification")] certification: &mut super::certification::Certification,
         #[cfg(feature = "multicast")] multicast: &mut super::multicast::Multicast,
+        #[cfg(feature = "class-b")] class_b: &mut super::class_b::ClassB,
+        device_time: &mut super::device_time::DeviceTime,
+        dev_status: &DevStatus,
+        rx_info: &RxInfo,
         rx: &mut RadioBuffer<N>,
         dl: &mut Vec<Downlink, D>,
         ignore_mac: bool,
@@ -129,12 +282,6 @@ impl Session {
         if let Ok(PhyPayload::Data(DataPayload::Encrypted(encrypted_data))) =
             lorawan_parse(rx.as_mut_for_read(), C::default())
         {
-            // If ignore_mac is false, we're dealing with Class A downlink and
-            // therefore can clear uplinks which need to be retained for acknowledgment
-            if !ignore_mac {
-                self.uplink.clear_mac_commands(false);
-            }
-
             #[cfg(feature = "certification")]
             if let Some(port) = encrypted_data.f_port() {
                 if port > 0 {
@@ -144,35 +291,82 @@ impl Session {
             #[cfg(feature = "multicast")]
             if let Some(port) = encrypted_data.f_port() {
                 if multicast.is_in_range(port) {
-                    return multicast.handle_rx(dl, encrypted_data).into();
+                    return multicast.handle_rx(dl, encrypted_data, rx_info).into();
                 }
             }
-            let fcnt = encrypted_data.fhdr().fcnt() as u32;
             let confirmed = encrypted_data.is_confirmed();
-            if encrypted_data.validate_mic(self.nwkskey().inner(), fcnt)
-                && (fcnt > self.fcnt_down || fcnt == 0)
-            {
-                self.fcnt_down = fcnt;
+            let fctrl = encrypted_data.fhdr().fctrl();
+            let (ack, f_pending) = (fctrl.ack(), fctrl.f_pending());
+            // LoRaWAN 1.1 uses AFCntDown for downlinks with application data
+            let afcnt_down = matches!(encrypted_data.f_port(), Some(port) if port > 0);
+            let fcnt_down = match &self.lorawan_1_1 {
+                Some(session) if afcnt_down => session.afcnt_down,
+                _ => self.next_fcnt_down(),
+            };
+            let fcnt = super::fcnt_down_from_lsb(encrypted_data.fhdr().fcnt(), fcnt_down);
+            if let Some(fcnt) = fcnt.filter(|fcnt| match &self.lorawan_1_1 {
+                Some(session) => encrypted_data.validate_downlink_mic_1_1(
+                    session.snwksintkey.inner(),
+                    *fcnt,
+                    self.fcnt_up as u16,
+                ),
+                None => encrypted_data.validate_mic(self.nwkskey().inner(), *fcnt),
+            }) {
+                match &mut self.lorawan_1_1 {
+                    Some(session) => {
+                        if afcnt_down {
+                            session.afcnt_down = fcnt.saturating_add(1);
+                        } else {
+                            self.fcnt_down = fcnt;
+                        }
+                        if confirmed {
+                            session.conf_fcnt_down = fcnt as u16;
+                        }
+                    }
+                    None => self.fcnt_down = fcnt,
+                }
+                // If ignore_mac is false, we're dealing with Class A downlink and
+                // therefore can clear uplinks which need to be retained for acknowledgment.
+                // This also proves that the network still receives our uplinks.
+                if !ignore_mac {
+                    self.uplink.clear_mac_commands(false);
+                    self.uplink.clear_downlink_confirmation();
+                    self.adr_ack_cnt = 0;
+                }
                 // We can safely unwrap here because we already validated the MIC
-                let decrypted = encrypted_data
-                    .decrypt(
+                let decrypted = match &self.lorawan_1_1 {
+                    Some(session) => encrypted_data.decrypt_1_1(
+                        Some(session.nwksenckey.inner()),
+                        Some(self.appskey().inner()),
+                        fcnt,
+                    ),
+                    None => encrypted_data.decrypt(
                         Some(self.nwkskey().inner()),
                         Some(self.appskey().inner()),
-                        self.fcnt_down,
-                    )
-                    .unwrap();
+                        fcnt,
+                    ),
+                }
+                .unwrap();
 
                 if !ignore_mac {
                     // MAC commands may be in the FHDR or the FRMPayload
                     self.handle_downlink_macs(
                         configuration,
                         region,
+                        #[cfg(feature = "class-b")]
+                        class_b,
+                        device_time,
+                        dev_status,
                         MacCommandIterator::<DownlinkMacCommand<'_>>::new(decrypted.fhdr().data()),
                     );
                     if let FRMPayload::MACCommands(mac_cmds) = decrypted.frm_payload() {
                         self.handle_downlink_macs(
                             configuration,
                             region,
+                            #[cfg(feature = "class-b")]
+                            class_b,
+                            device_time,
+                            dev_status,
                             MacCommandIterator::<DownlinkMacCommand<'_>>::new(mac_cmds.data()),
                         );
                     }
@@ -182,21 +376,22 @@ impl Session {
                     self.uplink.set_downlink_confirmation();
                 }
 
-                return if self.fcnt_up == 0xFFFF_FFFF {
+                return if self.fcnt_up == 0xFFFF_FFFF || fcnt == 0xFFFF_FFFF {
                     // if the FCnt is used up, the session has expired
                     Response::SessionExpired
                 } else {
-                    // we can always increment fcnt_up when we receive a downlink
-                    self.fcnt_up += 1;
+                    // The uplink is done once a downlink is received, except for confirmed
+                    // uplinks which are only acknowledged by downlinks with the ACK bit set
+                    if ignore_mac || !self.confirmed || ack {
+                        self.fcnt_up += 1;
+                    }
                     if let (Some(fport), FRMPayload::Data(data)) =
                         (decrypted.f_port(), decrypted.frm_payload())
                     {
                         #[cfg(feature = "certification")]
                         if certification.fport(fport) {
                             use crate::mac::certification::Response::*;
-                            match certification
-                                .handle_message(data, self.fcnt_down.try_into().unwrap())
-                            {
+                            match certification.handle_message(data, fcnt as u16) {
                                 AdrBitChange(adr) => {
                                     self.override_adr = adr;
                                 }
@@ -230,9 +425,10 @@ impl Session {
                         // A data FRM payload will never exceed 256 bytes.
                         let data = Vec::from_slice(data).unwrap();
                         // TODO: propagate error type when heapless vec is full?
-                        let _ = dl.push(Downlink { data, fport });
+                        let metadata = rx_info.downlink_metadata(fcnt);
+                        let _ = dl.push(Downlink { data, fport, metadata });
                     }
-                    Response::DownlinkReceived(fcnt)
+                    Response::DownlinkReceived { fcnt, ack, f_pending }
                 };
             }
         }
@@ -240,7 +436,16 @@ impl Session {
     }
 
     pub(crate) fn rx2_complete(&mut self) -> Response {
-        // Until we handle NbTrans, there is no case where we should not increment FCntUp.
+        // The uplink is done (including any repetitions), so pending answers
+        // which don't need to be retained can be dropped.
+        self.uplink.clear_mac_commands(true);
+        self.uplink.clear_downlink_confirmation();
+        self.uplink_complete()
+    }
+
+    /// The uplink is done without acknowledgment, either because no downlink was received or
+    /// because the downlinks didn't acknowledge the confirmed uplink.
+    pub(crate) fn uplink_complete(&mut self) -> Response {
         if self.fcnt_up == 0xFFFF_FFFF {
             // if the FCnt is used up, the session has expired
             return Response::SessionExpired;
@@ -254,20 +459,112 @@ impl Session {
         }
     }
 
+    /// Accounts for a new uplink in ADR_ACK_CNT once its frame has been prepared and, if ADR is
+    /// enabled, steps the configuration of the following uplinks back towards the default TX
+    /// power, the lowest data rate and the default channels every ADR_ACK_DELAY uplinks once
+    /// ADR_ACK_LIMIT has been reached without a downlink.
+    pub(crate) fn adr_backoff(
+        &mut self,
+        region: &mut region::Configuration,
+        configuration: &mut super::Configuration,
+    ) {
+        self.adr_ack_cnt = self.adr_ack_cnt.saturating_add(1);
+        if !configuration.adr || self.adr_ack_cnt < ADR_ACK_LIMIT {
+            return;
+        }
+
+        let cnt = self.adr_ack_cnt - ADR_ACK_LIMIT;
+        if cnt >= ADR_ACK_DELAY && cnt % ADR_ACK_DELAY == 0 {
+            if configuration.tx_power.is_some() {
+                configuration.tx_power = None;
+            } else if let Some(dr) = region.get_lower_datarate(configuration.data_rate) {
+                configuration.data_rate = dr;
+                // Re-enable default channels once the lowest data rate is reached or when
+                // the current channel mask doesn't allow transmitting at the new data rate
+                if dr == region.get_min_datarate()
+                    || !region.channel_mask_validate(&region.channel_mask_get(), Some(dr))
+                {
+                    region.enable_default_channels();
+                }
+            } else {
+                region.enable_default_channels();
+            }
+        }
+    }
+
+    /// Whether the uplink should have the ADRACKReq bit set, given its ADR_ACK_CNT.
+    pub(crate) fn adr_ack_req(
+        &self,
+        adr_ack_cnt: u32,
+        region: &region::Configuration,
+        configuration: &super::Configuration,
+    ) -> bool {
+        // No point in requesting ADR acknowledgement when already using default TX power
+        // and the lowest data rate, as there's nothing left to back off from.
+        configuration.adr
+            && adr_ack_cnt >= ADR_ACK_LIMIT
+            && (configuration.tx_power.is_some()
+                || configuration.data_rate != region.get_min_datarate())
+    }
+
+    /// Maximum length of the application payload of the next uplink, given the maximum MAC
+    /// payload length of the data rate. Pending MAC commands which don't fit FOpts need a frame of
+    /// their own, leaving no room for application payload.
+    pub(crate) fn available_payload_length(&self, max_mac_payload_length: u8) -> u8 {
+        let mut mac_commands_len = self.uplink.mac_commands().len();
+        if self.rekey_ind_pending()
+            && !parse_uplink_mac_commands(self.uplink.mac_commands())
+                .any(|cmd| matches!(cmd, UplinkMacCommand::RekeyInd(_)))
+        {
+            mac_commands_len += RekeyIndCreator::new().payload_bytes().len() + 1;
+        }
+        if mac_commands_len > FOPTS_MAX_LEN {
+            // The commands which don't fit FOpts are held back for the following uplinks
+            mac_commands_len = self.uplink.mac_commands_len_within(FOPTS_MAX_LEN);
+        }
+        (max_mac_payload_length as usize)
+            .saturating_sub(FHDR_MIN_LEN + FPORT_LEN + mac_commands_len) as u8
+    }
+
+    /// Drop the pending MAC commands which exceed the maximum MAC payload length of the data rate,
+    /// for an uplink without application payload.
+    pub(crate) fn truncate_mac_commands(&mut self, max_mac_payload_length: u8) {
+        let max_len = (max_mac_payload_length as usize).saturating_sub(FHDR_MIN_LEN + FPORT_LEN);
+        self.uplink.truncate_mac_commands(max_len);
+    }
+
+    /// Frame counter expected for the next downlink. A last received frame counter of 0 can't be
+    /// told apart from no downlink at all, in which case frame 0 is accepted.
+    fn next_fcnt_down(&self) -> u32 {
+        match self.fcnt_down {
+            0 => 0,
+            fcnt => fcnt.saturating_add(1),
+        }
+    }
+
+    /// RekeyInd is sent with every uplink of LoRaWAN 1.1 sessions until the network answers with
+    /// RekeyConf.
+    fn rekey_ind_pending(&self) -> bool {
+        self.lorawan_1_1.as_ref().is_some_and(|session| !session.rekey_confirmed)
+    }
+
+    /// Prepare the radio buffer with the data frame. MAC commands which don't fit FOpts are sent
+    /// on FPort 0 if there is no application payload, otherwise they are held back for the
+    /// following uplinks.
     pub(crate) fn prepare_buffer<C: CryptoFactory + Default, const N: usize>(
         &mut self,
         data: &SendData<'_>,
+        mut fctrl: FCtrl,
+        tx_channel: &TxChannel,
         tx_buffer: &mut RadioBuffer<N>,
-    ) -> FcntUp {
+    ) -> Result<FcntUp, Error> {
         tx_buffer.clear();
         let fcnt = self.fcnt_up;
         let mut buf = [0u8; 256];
         let mut phy = DataPayloadCreator::new(&mut buf).unwrap();
 
-        let mut fctrl = FCtrl(0x0, true);
         if self.uplink.confirms_downlink() {
             fctrl.set_ack();
-            self.uplink.clear_downlink_confirmation();
         }
 
         #[cfg(feature = "certification")]
@@ -281,34 +578,59 @@ impl Session {
             self.confirmed = v;
         }
 
+        if self.rekey_ind_pending() {
+            let mut cmd = RekeyIndCreator::new();
+            cmd.set_dev_lorawan_version(1);
+            self.uplink.add_mac_command_once(cmd);
+        }
+        let (fport, max_mac_commands_len) =
+            if data.data.is_empty() && self.uplink.mac_commands().len() > FOPTS_MAX_LEN {
+                (0, uplink::MAX_PENDING_LEN)
+            } else {
+                (data.fport, FOPTS_MAX_LEN)
+            };
+
         phy.set_confirmed(self.confirmed)
             .set_fctrl(&fctrl)
-            .set_f_port(data.fport)
+            .set_f_port(fport)
             .set_dev_addr(self.devaddr)
             .set_fcnt(fcnt);
 
         let crypto_factory = C::default();
-        match phy.build(
-            data.data,
-            self.uplink.mac_commands(),
-            &self.nwkskey,
-            &self.appskey,
-            &crypto_factory,
-        ) {
-            Ok(packet) => {
-                self.uplink.clear_mac_commands(true);
-                tx_buffer.clear();
-                tx_buffer.extend_from_slice(packet).unwrap();
+        let mac_commands = self.uplink.mac_commands_to_send(max_mac_commands_len);
+        let packet = match &self.lorawan_1_1 {
+            Some(session) => {
+                if fctrl.ack() {
+                    phy.set_conf_fcnt(session.conf_fcnt_down);
+                }
+                phy.set_tx_dr_ch(tx_channel.data_rate as u8, tx_channel.index);
+                phy.build_1_1(
+                    data.data,
+                    mac_commands,
+                    &FNwkSIntKey::from(self.nwkskey.inner().0),
+                    &session.snwksintkey,
+                    &session.nwksenckey,
+                    &self.appskey,
+                    &crypto_factory,
+                )
             }
-            Err(e) => panic!("Error assembling packet! {:?} ", e),
-        }
-        fcnt
+            None => {
+                phy.build(data.data, mac_commands, &self.nwkskey, &self.appskey, &crypto_factory)
+            }
+        };
+        let packet = packet.map_err(|_| Error::PayloadTooLarge)?;
+        tx_buffer.clear();
+        tx_buffer.extend_from_slice(packet).map_err(|_| Error::PayloadTooLarge)?;
+        Ok(fcnt)
     }
 
     fn handle_downlink_macs(
         &mut self,
         configuration: &mut super::Configuration,
         region: &mut region::Configuration,
+        #[cfg(feature = "class-b")] class_b: &mut super::class_b::ClassB,
+        device_time: &mut super::device_time::DeviceTime,
+        dev_status: &DevStatus,
         cmds: MacCommandIterator<'_, DownlinkMacCommand<'_>>,
     ) {
         use DownlinkMacCommand::*;
@@ -317,24 +639,78 @@ impl Session {
         let mut num_adrreq = 0;
         while let Some(cmd) = cmd_iter.next() {
             match cmd {
-                #[cfg(feature = "experimental")]
                 DevStatusReq(..) => {
-                    // TODO: Fill with proper values
-                    // - Battery: (255 - unable to measure, 1..254 - battery level, 0 - external power source)
-                    // - RadioStatus: (SNR: -32..31)
-                    // For now we just return dummy values
+                    // Margin is the SNR of the downlink carrying the request, as a 6-bit
+                    // signed integer
                     let mut cmd = DevStatusAnsCreator::new();
-                    let _ = cmd.set_battery(255).set_margin(0);
+                    let _ = cmd
+                        .set_battery((dev_status.battery_level)().into())
+                        .set_margin(dev_status.snr.clamp(-32, 31));
                     self.uplink.add_mac_command(cmd);
                 }
-                DlChannelReq(_payload) => {
+                DlChannelReq(payload) => {
                     if region.has_fixed_channel_plan() {
                         // Regions with fixed channel plan ignore this command
                         continue;
                     }
-                    // TODO...
+                    let (ack_f, ack_u) = region
+                        .handle_dl_channel(payload.channel_index(), payload.frequency().value());
+
+                    // DlChannelAns is retained in all uplinks until a Class A downlink is
+                    // received by the end-device.
+                    let mut cmd = DlChannelAnsCreator::new();
+                    cmd.set_channel_frequency_ack(ack_f).set_uplink_frequency_exists_ack(ack_u);
+                    self.uplink.add_mac_command(cmd);
+                }
+                LinkCheckAns(payload) => {
+                    self.link_check = Some(LinkCheck {
+                        margin: payload.margin(),
+                        gateway_count: payload.gateway_count(),
+                    });
+                }
+                DeviceTimeAns(payload) => device_time.handle_answer(&payload),
+                RekeyConf(..) => {
+                    if let Some(session) = &mut self.lorawan_1_1 {
+                        session.rekey_confirmed = true;
+                    }
+                }
+                ForceRejoinReq(payload) => {
+                    if let Some(session) = &mut self.lorawan_1_1 {
+                        let data_rate = region
+                            .check_data_rate(payload.data_rate())
+                            .unwrap_or(configuration.data_rate);
+                        session.rejoin.handle_force_rejoin_req(&payload, data_rate);
+                    }
+                }
+                RejoinParamSetupReq(payload) => {
+                    if let Some(session) = &mut self.lorawan_1_1 {
+                        session.rejoin.handle_param_setup_req(&payload);
+                        // Rejoin-requests based on time are supported
+                        let mut cmd = RejoinParamSetupAnsCreator::new();
+                        cmd.set_time_ack(true);
+                        self.uplink.add_mac_command(cmd);
+                    }
+                }
+                DutyCycleReq(payload) => {
+                    region.set_max_duty_cycle(payload.max_duty_cycle_raw());
+                    self.uplink.add_mac_command(DutyCycleAnsCreator::new());
+                }
+                TXParamSetupReq(payload) => {
+                    if !region.supports_tx_param_setup() {
+                        // Regions without dwell time limitations ignore this command
+                        continue;
+                    }
+                    region.set_tx_params(region::TxParams {
+                        uplink_dwell_time: payload.uplink_dwell_time(),
+                        downlink_dwell_time: payload.downlink_dwell_time(),
+                        max_eirp: Some(payload.max_eirp()),
+                    });
+                    // Uplink dwell time may rule out the current data rate
+                    if region.check_data_rate(configuration.data_rate as u8).is_none() {
+                        configuration.data_rate = region.get_min_datarate();
+                    }
+                    self.uplink.add_mac_command(TXParamSetupAnsCreator::new());
                 }
-                #[cfg(feature = "experimental")]
                 LinkADRReq(payload) => {
                     // Contiguous LinkADRReq commands shall be processed in the
                     // order present in the downlink frame as a single atomic block
@@ -386,11 +762,15 @@ impl Session {
 
                     let cm_ack = region.channel_mask_validate(&channel_mask, dr);
 
-                    if dr.is_some() && pw.is_some() && cm_ack {
-                        // TODO: handle nbtrans
-                        configuration.data_rate = dr.unwrap();
-                        configuration.tx_power = pw.unwrap();
+                    if let (Some(dr), Some(pw), true) = (dr, pw, cm_ack) {
+                        configuration.data_rate = dr;
+                        configuration.tx_power = pw;
                         region.channel_mask_set(channel_mask.clone());
+                        // NbTrans of 0 keeps the current setting
+                        let nb_trans = payload.redundancy().number_of_transmissions();
+                        if nb_trans > 0 {
+                            configuration.nb_trans = nb_trans;
+                        }
                     }
 
                     // Add matching number of LinkADRAns responses
@@ -418,25 +798,28 @@ impl Session {
                     cmd.set_channel_frequency_ack(ack_f).set_data_rate_range_ack(ack_d);
                     self.uplink.add_mac_command(cmd);
                 }
-                #[cfg(feature = "experimental")]
                 RXParamSetupReq(payload) => {
                     let freq = payload.frequency().value();
                     let freq_ack = region.frequency_valid(freq);
-
-                    // TODO: Figure these out...
-                    // let dl = payload.dl_settings();
-                    // - rx1_dr_offset: dl.rx1_dr_offset()
-                    // - rx2_data_rate = dl.rx2_data_rate());
-                    if freq_ack {
-                        configuration.rx2_frequency = Some(freq);
+                    let dl = payload.dl_settings();
+                    let rx1_dr_offset_ack = region.rx1_dr_offset_valid(dl.rx1_dr_offset());
+                    let rx2_dr = region.check_rx2_data_rate(dl.rx2_data_rate());
+
+                    // Settings are only applied when all of them are acceptable
+                    if let (true, true, Some(rx2_dr)) = (freq_ack, rx1_dr_offset_ack, rx2_dr) {
+                        self.rx_settings = RxSettings {
+                            rx1_dr_offset: dl.rx1_dr_offset(),
+                            rx2_data_rate: Some(rx2_dr),
+                            rx2_frequency: Some(freq),
+                        };
                     }
 
                     // RXParamSetupReq has its own acknowledgment mechanism, requiring
                     // RXParamSetupAns with all uplinks until a Class A downlink is received
                     // by the end-device.
                     let mut cmd = RXParamSetupAnsCreator::new();
-                    cmd.set_rx1_data_rate_offset_ack(true)
-                        .set_rx2_data_rate_ack(true)
+                    cmd.set_rx1_data_rate_offset_ack(rx1_dr_offset_ack)
+                        .set_rx2_data_rate_ack(rx2_dr.is_some())
                         .set_channel_ack(freq_ack);
 
                     self.uplink.add_mac_command(cmd);
@@ -450,7 +833,24 @@ impl Session {
                     configuration.rx1_delay = super::del_to_delay_ms(payload.delay());
                     self.uplink.add_mac_command(RXTimingSetupAnsCreator::new());
                 }
-                _ => (),
+                #[cfg(feature = "class-b")]
+                PingSlotInfoAns(..) => class_b.handle_ping_slot_info_ans(),
+                #[cfg(feature = "class-b")]
+                PingSlotChannelReq(payload) => {
+                    let (ack_f, ack_d) = class_b.handle_ping_slot_channel_req(&payload, region);
+                    let mut cmd = PingSlotChannelAnsCreator::new();
+                    cmd.set_channel_frequency_ack(ack_f).set_data_rate_ack(ack_d);
+                    self.uplink.add_mac_command(cmd);
+                }
+                #[cfg(feature = "class-b")]
+                BeaconFreqReq(payload) => {
+                    let mut cmd = BeaconFreqAnsCreator::new();
+                    cmd.set_beacon_frequency_ack(class_b.handle_beacon_freq_req(&payload, region));
+                    self.uplink.add_mac_command(cmd);
+                }
+                // Class B commands are ignored without Class B support
+                #[cfg(not(feature = "class-b"))]
+                PingSlotInfoAns(..) | PingSlotChannelReq(..) | BeaconFreqReq(..) => (),
             }
         }
     }
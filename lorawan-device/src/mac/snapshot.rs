@@ -0,0 +1,452 @@
+//! Compact, versioned binary snapshot of the MAC state, allowing devices to power down between
+//! uplinks without losing the session and the configuration provided by the network.
+//!
+//! The snapshot contains the session, the data rate, TX power, RX window settings, the channel
+//! plan and channel mask, the parameters set with `TXParamSetupReq` and `DutyCycleReq`, the
+//! DevNonce counter and the rejoin-request counters. Timestamps referring to the local clock (duty-cycle timers, network time
+//! synchronization) are not part of it, as the clock may not survive a power cycle.
+use super::{
+    otaa, uplink::Uplink, ConfirmedRetryPolicy, DevNonceMode, ForcedRejoin, Mac, Rejoin,
+    RejoinParams, Session, Session1_1, State,
+};
+use crate::region::DR;
+use crate::{AppSKey, NwkSKey};
+use heapless::Vec;
+use lorawan::parser::DevAddr;
+use lorawan::types::RejoinType;
+
+/// Version of the snapshot format, stored in the first byte of the snapshot.
+pub const SNAPSHOT_VERSION: u8 = 1;
+
+/// Maximum length of a snapshot in bytes.
+pub const MAX_SNAPSHOT_LEN: usize = 349;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum SnapshotError {
+    /// The snapshot was created with an unsupported version of the format.
+    UnsupportedVersion(u8),
+    /// The snapshot was created for a different region than the one of the device.
+    RegionMismatch,
+    /// The snapshot is truncated or contains invalid values.
+    Invalid,
+}
+
+pub(crate) type Result<T = ()> = core::result::Result<T, SnapshotError>;
+
+/// Serialized MAC state, see [`Device::snapshot`](crate::async_device::Device::snapshot).
+///
+/// The snapshot is a plain byte string which may be stored in any non-volatile memory and is
+/// turned back into a snapshot with [`Snapshot::from_bytes`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Snapshot {
+    data: Vec<u8, MAX_SNAPSHOT_LEN>,
+}
+
+impl Snapshot {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        match bytes.first() {
+            Some(&SNAPSHOT_VERSION) => {}
+            Some(&version) => return Err(SnapshotError::UnsupportedVersion(version)),
+            None => return Err(SnapshotError::Invalid),
+        }
+        let data = Vec::from_slice(bytes).map_err(|_| SnapshotError::Invalid)?;
+        Ok(Self { data })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl AsRef<[u8]> for Snapshot {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+#[cfg(feature = "defmt-03")]
+impl defmt::Format for Snapshot {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(f, "Snapshot {{ len: {} }}", self.data.len())
+    }
+}
+
+pub(crate) struct Writer {
+    data: Vec<u8, MAX_SNAPSHOT_LEN>,
+}
+
+impl Writer {
+    fn new() -> Self {
+        let mut writer = Self { data: Vec::new() };
+        writer.u8(SNAPSHOT_VERSION);
+        writer
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        // MAX_SNAPSHOT_LEN accommodates the largest possible snapshot
+        self.data.extend_from_slice(bytes).unwrap();
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.bytes(&[value]);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn option<T>(&mut self, value: Option<T>, write: impl FnOnce(&mut Self, T)) {
+        self.bool(value.is_some());
+        if let Some(value) = value {
+            write(self, value);
+        }
+    }
+
+    fn finish(self) -> Snapshot {
+        Snapshot { data: self.data }
+    }
+}
+
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn bytes<const L: usize>(&mut self) -> Result<[u8; L]> {
+        if self.data.len() < L {
+            return Err(SnapshotError::Invalid);
+        }
+        let (head, tail) = self.data.split_at(L);
+        self.data = tail;
+        Ok(head.try_into().unwrap())
+    }
+
+    pub fn slice(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.data.len() < len {
+            return Err(SnapshotError::Invalid);
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    pub fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes::<1>()?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SnapshotError::Invalid),
+        }
+    }
+
+    pub fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.bytes()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes()?))
+    }
+
+    pub fn dr(&mut self) -> Result<DR> {
+        DR::try_from(self.u8()?).map_err(|_| SnapshotError::Invalid)
+    }
+
+    pub fn option<T>(&mut self, read: impl FnOnce(&mut Self) -> Result<T>) -> Result<Option<T>> {
+        match self.bool()? {
+            true => Ok(Some(read(self)?)),
+            false => Ok(None),
+        }
+    }
+}
+
+impl Mac {
+    pub(crate) fn snapshot(&self) -> Snapshot {
+        let mut w = Writer::new();
+        self.region.write_snapshot(&mut w);
+
+        let configuration = &self.configuration;
+        w.u8(configuration.data_rate as u8);
+        w.u32(configuration.rx1_delay);
+        w.u32(configuration.join_accept_delay1);
+        w.u32(configuration.join_accept_delay2);
+        w.option(configuration.tx_power, Writer::u8);
+        w.bool(configuration.adr);
+        w.u8(configuration.nb_trans);
+        w.u8(configuration.confirmed_retries.max_attempts);
+        w.bool(configuration.confirmed_retries.datarate_step_down);
+
+        w.bool(self.dev_nonce.get_mode() == DevNonceMode::Random);
+        w.u32(self.dev_nonce.get_next());
+        w.u16(self.rj_count1);
+
+        let session = match &self.state {
+            State::Joined(session) => Some(session),
+            // A join which is still in progress needs to be restarted
+            State::Otaa(_) | State::Unjoined => None,
+        };
+        w.option(session, write_session);
+        w.finish()
+    }
+
+    /// Restore the state from a snapshot. The device keeps its current state if the snapshot
+    /// can't be restored.
+    pub(crate) fn restore(&mut self, snapshot: &Snapshot) -> Result {
+        let mut r = Reader { data: &snapshot.data[1..] };
+        let mut region = self.region.clone();
+        region.restore_snapshot(&mut r)?;
+
+        let mut configuration = self.configuration;
+        configuration.data_rate = r.dr()?;
+        configuration.rx1_delay = r.u32()?;
+        configuration.join_accept_delay1 = r.u32()?;
+        configuration.join_accept_delay2 = r.u32()?;
+        configuration.tx_power = r.option(Reader::u8)?;
+        configuration.adr = r.bool()?;
+        configuration.nb_trans = r.u8()?;
+        configuration.confirmed_retries =
+            ConfirmedRetryPolicy { max_attempts: r.u8()?, datarate_step_down: r.bool()? };
+
+        let dev_nonce_mode = match r.bool()? {
+            true => DevNonceMode::Random,
+            false => DevNonceMode::Counter,
+        };
+        let dev_nonce = r.u32()?;
+        let rj_count1 = r.u16()?;
+
+        let session = r.option(read_session)?;
+        if !r.data.is_empty() {
+            return Err(SnapshotError::Invalid);
+        }
+
+        self.region = region;
+        self.configuration = configuration;
+        self.dev_nonce = otaa::DevNonceGenerator::default();
+        self.dev_nonce.set_mode(dev_nonce_mode);
+        self.dev_nonce.set_next(dev_nonce);
+        self.rj_count1 = rj_count1;
+        self.rejoin_request = None;
+        self.state = match session {
+            Some(session) => State::Joined(session),
+            None => State::Unjoined,
+        };
+        self.tx_attempts = 0;
+        self.max_tx_attempts = 0;
+        Ok(())
+    }
+}
+
+fn write_session(w: &mut Writer, session: &Session) {
+    w.bytes(session.nwkskey.as_ref());
+    w.bytes(session.appskey.as_ref());
+    w.bytes(session.devaddr.as_ref());
+    w.u32(session.fcnt_up);
+    w.u32(session.fcnt_down);
+    w.u32(session.adr_ack_cnt);
+    w.bool(session.confirmed);
+
+    let rx_settings = &session.rx_settings;
+    w.u8(rx_settings.rx1_dr_offset);
+    w.option(rx_settings.rx2_data_rate, |w, dr| w.u8(dr as u8));
+    w.option(rx_settings.rx2_frequency, Writer::u32);
+
+    w.option(session.link_check, |w, link_check| {
+        w.u8(link_check.margin);
+        w.u8(link_check.gateway_count);
+    });
+
+    w.option(session.lorawan_1_1.as_ref(), |w, session| {
+        w.bytes(session.snwksintkey.as_ref());
+        w.bytes(session.nwksenckey.as_ref());
+        w.u32(session.afcnt_down);
+        w.u16(session.conf_fcnt_down);
+        w.bool(session.rekey_confirmed);
+
+        let rejoin = &session.rejoin;
+        w.bytes(&rejoin.net_id);
+        w.u16(rejoin.rj_count0);
+        w.option(rejoin.params, |w, params| {
+            w.u8(params.max_time_n);
+            w.u8(params.max_count_n);
+        });
+        w.u32(rejoin.uplink_count);
+        // Forced rejoin-requests are due right away after restoring
+        w.option(rejoin.forced, |w, forced| {
+            w.u8(forced.rejoin_type.value());
+            w.u8(forced.data_rate as u8);
+            w.u8(forced.transmissions);
+            w.u8(forced.period);
+        });
+    });
+
+    // Pending MAC command answers, eg: sticky answers which are sent until a downlink is received
+    let mac_commands = session.uplink.mac_commands();
+    w.u8(mac_commands.len() as u8);
+    w.bytes(mac_commands);
+    w.bool(session.uplink.confirms_downlink());
+}
+
+fn read_session(r: &mut Reader<'_>) -> Result<Session> {
+    let nwkskey = NwkSKey::from(r.bytes::<16>()?);
+    let appskey = AppSKey::from(r.bytes::<16>()?);
+    let devaddr = DevAddr::from(r.bytes::<4>()?);
+    let mut session = Session::new(nwkskey, appskey, devaddr);
+    session.fcnt_up = r.u32()?;
+    session.fcnt_down = r.u32()?;
+    session.adr_ack_cnt = r.u32()?;
+    session.confirmed = r.bool()?;
+
+    session.rx_settings.rx1_dr_offset = r.u8()?;
+    session.rx_settings.rx2_data_rate = r.option(Reader::dr)?;
+    session.rx_settings.rx2_frequency = r.option(Reader::u32)?;
+
+    session.link_check =
+        r.option(|r| Ok(super::LinkCheck { margin: r.u8()?, gateway_count: r.u8()? }))?;
+
+    session.lorawan_1_1 = r.option(|r| {
+        Ok(Session1_1 {
+            snwksintkey: r.bytes::<16>()?.into(),
+            nwksenckey: r.bytes::<16>()?.into(),
+            afcnt_down: r.u32()?,
+            conf_fcnt_down: r.u16()?,
+            rekey_confirmed: r.bool()?,
+            rejoin: read_rejoin(r)?,
+        })
+    })?;
+
+    let len = r.u8()?;
+    let mac_commands = r.slice(len as usize)?;
+    let confirmed = r.bool()?;
+    session.uplink = Uplink::from_raw(mac_commands, confirmed).ok_or(SnapshotError::Invalid)?;
+    Ok(session)
+}
+
+fn read_rejoin(r: &mut Reader<'_>) -> Result<Rejoin> {
+    let mut rejoin = Rejoin::new(r.bytes::<3>()?);
+    rejoin.rj_count0 = r.u16()?;
+    rejoin.params = r.option(|r| Ok(RejoinParams { max_time_n: r.u8()?, max_count_n: r.u8()? }))?;
+    rejoin.uplink_count = r.u32()?;
+    rejoin.forced = r.option(|r| {
+        Ok(ForcedRejoin {
+            rejoin_type: RejoinType::try_from(r.u8()?).map_err(|_| SnapshotError::Invalid)?,
+            data_rate: r.dr()?,
+            transmissions: r.u8()?,
+            period: r.u8()?,
+            next_ms: 0,
+        })
+    })?;
+    Ok(rejoin)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mac::LinkCheck;
+    use crate::region::{Configuration, Region};
+
+    fn joined_mac(region: Region) -> Mac {
+        let mut mac = Mac::new(Configuration::new(region), 21, 2);
+        let mut session = Session::new([1; 16].into(), [2; 16].into(), DevAddr::from([1, 2, 3, 4]));
+        session.fcnt_up = 0x1234;
+        session.fcnt_down = 0x1_0002;
+        session.rx_settings.rx2_data_rate = Some(DR::_3);
+        session.rx_settings.rx2_frequency = Some(869_525_000);
+        session.link_check = Some(LinkCheck { margin: 20, gateway_count: 2 });
+        session.uplink = Uplink::from_raw(&[0x05, 0x07], true).unwrap();
+        mac.set_session(session);
+        mac
+    }
+
+    #[test]
+    #[cfg(feature = "region-eu868")]
+    fn restore_dynamic_plan() {
+        let mut mac = joined_mac(Region::EU868);
+        for index in 3..16 {
+            let range = lorawan::types::DataRateRange::new_range(DR::_0, DR::_5);
+            mac.region.handle_new_channel(index, 867_100_000 + index as u32 * 200_000, Some(range));
+        }
+        mac.region.handle_dl_channel(3, 868_500_000);
+        mac.region.set_max_duty_cycle(4);
+        mac.configuration.data_rate = DR::_4;
+        mac.configuration.tx_power = Some(2);
+        mac.set_dev_nonce(17);
+        mac.rj_count1 = 3;
+        // The largest possible snapshot fits
+        if let State::Joined(session) = &mut mac.state {
+            session.uplink = Uplink::from_raw(&[0x03; 15], true).unwrap();
+            session.lorawan_1_1 = Some(Session1_1 {
+                snwksintkey: [3; 16].into(),
+                nwksenckey: [4; 16].into(),
+                afcnt_down: 0x2_0001,
+                conf_fcnt_down: 0x1234,
+                rekey_confirmed: true,
+                rejoin: Rejoin {
+                    rj_count0: 2,
+                    params: Some(RejoinParams { max_time_n: 4, max_count_n: 5 }),
+                    uplink_count: 7,
+                    forced: Some(ForcedRejoin {
+                        rejoin_type: RejoinType::Type2,
+                        data_rate: DR::_1,
+                        transmissions: 3,
+                        period: 2,
+                        next_ms: 0,
+                    }),
+                    ..Rejoin::new([4, 5, 6])
+                },
+            });
+        }
+
+        let snapshot = mac.snapshot();
+        let snapshot = Snapshot::from_bytes(snapshot.as_bytes()).unwrap();
+        let mut restored = Mac::new(Configuration::new(Region::EU868), 21, 2);
+        restored.restore(&snapshot).unwrap();
+
+        assert_eq!(restored.snapshot(), snapshot);
+        assert_eq!(restored.configuration, mac.configuration);
+        assert_eq!(restored.get_dev_nonce(), 17);
+        let session = restored.get_session().unwrap();
+        assert_eq!(session.fcnt_up, 0x1234);
+        assert_eq!(session.fcnt_down, 0x1_0002);
+        assert_eq!(session.devaddr, DevAddr::from([1, 2, 3, 4]));
+        assert_eq!(session.link_check, Some(LinkCheck { margin: 20, gateway_count: 2 }));
+        assert_eq!(session.uplink.mac_commands(), &[0x03; 15]);
+        let session_1_1 = session.lorawan_1_1.as_ref().unwrap();
+        assert_eq!(session_1_1.afcnt_down, 0x2_0001);
+        assert_eq!(session_1_1.conf_fcnt_down, 0x1234);
+        assert_eq!(
+            session_1_1.rejoin,
+            mac.get_session().unwrap().lorawan_1_1.as_ref().unwrap().rejoin
+        );
+        assert_eq!(restored.rj_count1, 3);
+    }
+
+    #[test]
+    #[cfg(all(feature = "region-eu868", feature = "region-us915"))]
+    fn restore_errors() {
+        assert_eq!(Snapshot::from_bytes(&[]), Err(SnapshotError::Invalid));
+        assert_eq!(Snapshot::from_bytes(&[2, 0]), Err(SnapshotError::UnsupportedVersion(2)));
+
+        let snapshot = joined_mac(Region::EU868).snapshot();
+        let mut mac = Mac::new(Configuration::new(Region::US915), 21, 2);
+        assert_eq!(mac.restore(&snapshot), Err(SnapshotError::RegionMismatch));
+
+        let snapshot = joined_mac(Region::US915).snapshot();
+        let truncated = Snapshot::from_bytes(&snapshot.as_bytes()[..40]).unwrap();
+        assert_eq!(mac.restore(&truncated), Err(SnapshotError::Invalid));
+        assert!(mac.get_session().is_none());
+
+        mac.restore(&snapshot).unwrap();
+        assert_eq!(mac.get_session().unwrap().fcnt_up, 0x1234);
+    }
+}
@@ -1,16 +1,30 @@
 use lorawan::maccommands::{parse_uplink_mac_commands, SerializableMacCommand, UplinkMacCommand};
-use lorawan::packet_length::phy::mac::fhdr::FOPTS_MAX_LEN;
 
 #[cfg(feature = "serde")]
 mod serde;
 
+/// Maximum length of the pending MAC commands. Commands which don't fit FOpts are held back for
+/// the following uplinks, or sent in FRMPayload on FPort 0 by an uplink without application
+/// payload.
+pub(crate) const MAX_PENDING_LEN: usize = 32;
+
 #[derive(Default, Debug, Clone, PartialEq)]
 pub struct Uplink {
-    pending: heapless::Vec<u8, FOPTS_MAX_LEN>,
+    pending: heapless::Vec<u8, MAX_PENDING_LEN>,
     confirmed: bool,
+    /// Length of the pending MAC commands sent with the last uplink, if some were held back.
+    sent_len: Option<usize>,
 }
 
 impl Uplink {
+    /// Restore pending MAC commands, eg: from a snapshot. Returns `None` if they don't fit.
+    pub(crate) fn from_raw(mac_commands: &[u8], confirmed: bool) -> Option<Self> {
+        Some(Self {
+            pending: heapless::Vec::from_slice(mac_commands).ok()?,
+            confirmed,
+            sent_len: None,
+        })
+    }
     pub fn set_downlink_confirmation(&mut self) {
         self.confirmed = true;
     }
@@ -20,29 +34,66 @@ impl Uplink {
     pub fn confirms_downlink(&self) -> bool {
         self.confirmed
     }
+    /// Add a MAC command to the next uplink. Commands which don't fit anymore are dropped, the
+    /// network repeats requests which are not answered.
     pub fn add_mac_command<M: SerializableMacCommand>(&mut self, cmd: M) {
-        let _ = self.pending.push(cmd.cid());
-        self.pending.extend_from_slice(cmd.payload_bytes()).unwrap();
+        if self.pending.len() + 1 + cmd.payload_bytes().len() <= MAX_PENDING_LEN {
+            let _ = self.pending.push(cmd.cid());
+            let _ = self.pending.extend_from_slice(cmd.payload_bytes());
+        }
+    }
+    /// Add a MAC command unless a command with the same CID is already pending, eg: for
+    /// requests which expect a single answer.
+    pub fn add_mac_command_once<M: SerializableMacCommand>(&mut self, cmd: M) {
+        if !parse_uplink_mac_commands(&self.pending).any(|c| c.cid() == cmd.cid()) {
+            self.add_mac_command(cmd);
+        }
     }
     pub fn clear_mac_commands(&mut self, retain_acks: bool) {
+        let sent_len =
+            self.sent_len.take().map_or(self.pending.len(), |len| len.min(self.pending.len()));
+        let mut data: heapless::Vec<u8, MAX_PENDING_LEN> = heapless::Vec::new();
         // Certain commands have to be retained until their acknowledgment is confirmed
         if retain_acks {
-            let mut data: heapless::Vec<u8, FOPTS_MAX_LEN> = heapless::Vec::new();
-            let _: heapless::Vec<_, FOPTS_MAX_LEN> = parse_uplink_mac_commands(&self.pending)
-                .filter(|cmd| matches!(cmd, UplinkMacCommand::RXParamSetupAns(_)))
-                .map(|c| {
-                    let _ = data.push(c.cid());
-                    data.extend_from_slice(c.payload_bytes()).unwrap();
-                })
-                .collect();
-            self.pending = data;
-        } else {
-            self.pending.clear();
+            for cmd in parse_uplink_mac_commands(&self.pending[..sent_len]).filter(|cmd| {
+                matches!(
+                    cmd,
+                    UplinkMacCommand::RXParamSetupAns(_) | UplinkMacCommand::DlChannelAns(_)
+                )
+            }) {
+                let _ = data.push(cmd.cid());
+                data.extend_from_slice(cmd.payload_bytes()).unwrap();
+            }
         }
+        // Commands held back from the last uplink have not been sent yet
+        data.extend_from_slice(&self.pending[sent_len..]).unwrap();
+        self.pending = data;
     }
     pub fn mac_commands(&self) -> &[u8] {
         &self.pending
     }
+    /// Length of the pending MAC commands which fit `max_len` bytes, without splitting commands.
+    pub(crate) fn mac_commands_len_within(&self, max_len: usize) -> usize {
+        let mut len = 0;
+        for cmd in parse_uplink_mac_commands(&self.pending) {
+            if len + 1 + cmd.payload_bytes().len() > max_len {
+                break;
+            }
+            len += 1 + cmd.payload_bytes().len();
+        }
+        len
+    }
+    /// Drop the pending MAC commands which don't fit `max_len` bytes, without splitting commands.
+    pub(crate) fn truncate_mac_commands(&mut self, max_len: usize) {
+        self.pending.truncate(self.mac_commands_len_within(max_len));
+    }
+    /// The pending MAC commands which fit `max_len` bytes, to be sent with the next uplink. The
+    /// others are held back: they stay pending when the sent ones are cleared.
+    pub(crate) fn mac_commands_to_send(&mut self, max_len: usize) -> &[u8] {
+        let len = self.mac_commands_len_within(max_len);
+        self.sent_len = (len < self.pending.len()).then_some(len);
+        &self.pending[..len]
+    }
 }
 
 #[cfg(feature = "defmt-03")]
@@ -60,6 +111,7 @@ impl defmt::Format for Uplink {
 #[cfg(test)]
 mod test {
     use super::*;
+    use lorawan::maccommandcreator::DevStatusAnsCreator;
     use lorawan::maccommands::{parse_uplink_mac_commands, LinkADRAnsCreator, UplinkMacCommand};
     #[test]
     fn two_link_adr_ans() {
@@ -71,4 +123,37 @@ mod test {
         assert!(matches!(mac_commands.next().unwrap(), UplinkMacCommand::LinkADRAns(_)));
         assert!(mac_commands.next().is_none());
     }
+
+    #[test]
+    fn mac_commands_overflow() {
+        let mut uplink = Uplink::default();
+        // LinkADRAns is 2 bytes long, DevStatusAns 3 bytes
+        for _ in 0..15 {
+            uplink.add_mac_command(LinkADRAnsCreator::new());
+        }
+        assert_eq!(uplink.mac_commands().len(), 30);
+        uplink.add_mac_command(DevStatusAnsCreator::new());
+        assert_eq!(uplink.mac_commands().len(), 30);
+        uplink.add_mac_command(LinkADRAnsCreator::new());
+        assert_eq!(uplink.mac_commands().len(), MAX_PENDING_LEN);
+
+        uplink.truncate_mac_commands(11);
+        assert_eq!(uplink.mac_commands().len(), 10);
+    }
+
+    #[test]
+    fn mac_commands_held_back() {
+        let mut uplink = Uplink::default();
+        uplink.add_mac_command(DevStatusAnsCreator::new());
+        uplink.add_mac_command(LinkADRAnsCreator::new());
+        uplink.add_mac_command(DevStatusAnsCreator::new());
+        assert_eq!(uplink.mac_commands_to_send(6), &[6, 0, 0, 3, 0]);
+        // Only the commands which have been sent are cleared
+        uplink.clear_mac_commands(false);
+        assert_eq!(uplink.mac_commands(), &[6, 0, 0]);
+
+        assert_eq!(uplink.mac_commands_to_send(6), &[6, 0, 0]);
+        uplink.clear_mac_commands(true);
+        assert!(uplink.mac_commands().is_empty());
+    }
 }
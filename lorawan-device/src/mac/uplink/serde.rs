@@ -1,5 +1,4 @@
-use crate::mac::uplink::Uplink;
-use lorawan::packet_length::phy::mac::fhdr::FOPTS_MAX_LEN;
+use crate::mac::uplink::{Uplink, MAX_PENDING_LEN};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 #[cfg(feature = "serde")]
@@ -9,13 +8,49 @@ impl Serialize for Uplink {
         let mut state = serializer.serialize_struct("Uplink", 3)?;
         state.serialize_field("confirmed", &self.confirmed)?;
         state.serialize_field("pending_len", &(self.pending.len() as u8))?;
-        let mut full_array = [0u8; FOPTS_MAX_LEN];
-        full_array[..self.pending.len()].copy_from_slice(&self.pending);
-        state.serialize_field("pending_data", &full_array)?;
+        state.serialize_field("pending_data", self.pending.as_slice())?;
         state.end()
     }
 }
 
+/// Pending MAC commands of any length up to `MAX_PENDING_LEN`, so that sessions serialized with
+/// a smaller buffer can still be restored.
+struct PendingData(heapless::Vec<u8, MAX_PENDING_LEN>);
+
+impl<'de> Deserialize<'de> for PendingData {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use core::fmt;
+        use serde::de::{self, SeqAccess, Visitor};
+
+        struct PendingDataVisitor;
+
+        impl<'de> Visitor<'de> for PendingDataVisitor {
+            type Value = PendingData;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(formatter, "at most {MAX_PENDING_LEN} bytes")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<PendingData, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut data = heapless::Vec::new();
+                while let Some(byte) = seq.next_element()? {
+                    data.push(byte)
+                        .map_err(|_| de::Error::invalid_length(data.len() + 1, &self))?;
+                }
+                Ok(PendingData(data))
+            }
+        }
+
+        deserializer.deserialize_seq(PendingDataVisitor)
+    }
+}
+
 #[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for Uplink {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -48,7 +83,7 @@ impl<'de> Deserialize<'de> for Uplink {
             {
                 let mut confirmed: Option<bool> = None;
                 let mut pending_len: Option<u8> = None;
-                let mut pending_data: Option<[u8; FOPTS_MAX_LEN]> = None;
+                let mut pending_data: Option<PendingData> = None;
 
                 while let Some(key) = map.next_key::<Field>()? {
                     match key {
@@ -79,16 +114,13 @@ impl<'de> Deserialize<'de> for Uplink {
                 let pending_data =
                     pending_data.ok_or_else(|| de::Error::missing_field("pending_data"))?;
 
-                if pending_len as usize > FOPTS_MAX_LEN {
-                    return Err(de::Error::custom("pending_len exceeds maximum size"));
+                let mut pending = pending_data.0;
+                if pending_len as usize > pending.len() {
+                    return Err(de::Error::custom("pending_len exceeds pending_data"));
                 }
+                pending.truncate(pending_len as usize);
 
-                let mut pending = heapless::Vec::new();
-                pending
-                    .extend_from_slice(&pending_data[..pending_len as usize])
-                    .map_err(|_| de::Error::custom("failed to create heapless::Vec"))?;
-
-                Ok(Uplink { pending, confirmed })
+                Ok(Uplink { pending, confirmed, sent_len: None })
             }
         }
 
@@ -128,7 +160,7 @@ mod tests {
     #[test]
     fn test_serde_max_size() {
         let mut uplink = Uplink::default();
-        let max_data = [42u8; FOPTS_MAX_LEN];
+        let max_data = [42u8; MAX_PENDING_LEN];
         uplink.pending.extend_from_slice(&max_data).unwrap();
 
         let json = serde_json::to_string(&uplink).unwrap();
@@ -136,4 +168,21 @@ mod tests {
         assert!(!decoded.confirms_downlink());
         assert_eq!(decoded.mac_commands(), &max_data);
     }
+
+    #[test]
+    fn test_serde_15_byte_pending_data() {
+        // Earlier versions serialized a 15-byte buffer
+        let json =
+            r#"{"confirmed":true,"pending_len":2,"pending_data":[2,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}"#;
+        let decoded: Uplink = serde_json::from_str(json).unwrap();
+        assert!(decoded.confirms_downlink());
+        assert_eq!(decoded.mac_commands(), &[2, 0]);
+    }
+
+    #[test]
+    fn test_serde_pending_data_too_long() {
+        let json = serde_json::to_string(&[1u8; MAX_PENDING_LEN + 1][..]).unwrap();
+        let json = format!(r#"{{"confirmed":false,"pending_len":1,"pending_data":{json}}}"#);
+        assert!(serde_json::from_str::<Uplink>(&json).is_err());
+    }
 }
@@ -39,17 +39,23 @@ where
                 radio,
                 rng,
                 tx_buffer: RadioBuffer::new(),
+                uplink: state::PendingUplink::new(),
                 mac: Mac::new(region, R::MAX_RADIO_POWER, R::ANTENNA_GAIN),
                 downlink: Vec::new(),
             },
         }
     }
 
+    /// Join the network. With the DevNonce counter, persist `get_dev_nonce() + 1` before calling
+    /// `join`, see [`crate::async_device::Device::join`].
     pub fn join(&mut self, join_mode: JoinMode) -> Result<Response, Error<R>> {
         match join_mode {
             JoinMode::OTAA { deveui, appeui, appkey } => {
                 self.handle_event(Event::Join(NetworkCredentials::new(appeui, deveui, appkey)))
             }
+            JoinMode::OTAA1_1 { deveui, appeui, appkey, nwkkey } => self.handle_event(Event::Join(
+                NetworkCredentials::new_1_1(appeui, deveui, appkey, nwkkey),
+            )),
             JoinMode::ABP { devaddr, appskey, nwkskey } => {
                 self.shared.mac.join_abp(nwkskey, appskey, devaddr);
                 Ok(Response::JoinSuccess)
@@ -69,6 +75,22 @@ where
         self.shared.mac.configuration.data_rate = datarate
     }
 
+    /// Enables Adaptive Data Rate. See [`crate::async_device::Device::enable_adr`].
+    pub fn enable_adr(&mut self) {
+        self.shared.mac.configuration.adr = true
+    }
+
+    /// Disables Adaptive Data Rate. See [`crate::async_device::Device::disable_adr`].
+    pub fn disable_adr(&mut self) {
+        self.shared.mac.configuration.adr = false
+    }
+
+    /// Set the retry policy for confirmed uplinks. See
+    /// [`crate::async_device::Device::set_confirmed_retry_policy`].
+    pub fn set_confirmed_retry_policy(&mut self, policy: mac::ConfirmedRetryPolicy) {
+        self.shared.mac.configuration.confirmed_retries = policy;
+    }
+
     pub fn ready_to_send_data(&self) -> bool {
         matches!(&self.state, State::Idle(_)) && self.shared.mac.is_joined()
     }
@@ -77,6 +99,91 @@ where
         self.handle_event(Event::SendDataRequest(SendData { data, fport, confirmed }))
     }
 
+    /// Send the pending MAC commands on FPort 0, without application payload. See
+    /// [`crate::async_device::Device::send_mac_commands`].
+    pub fn send_mac_commands(&mut self) -> Result<Response, Error<R>> {
+        if self.shared.mac.is_joined() && !self.has_pending_mac_commands() {
+            return Err(mac::Error::NoMacCommands.into());
+        }
+        self.send(&[], 0, false)
+    }
+
+    /// Whether MAC commands are pending for the next uplink.
+    pub fn has_pending_mac_commands(&self) -> bool {
+        self.shared.mac.has_pending_mac_commands()
+    }
+
+    /// Select how the DevNonce of OTAA join requests is generated. See
+    /// [`crate::async_device::Device::set_dev_nonce_mode`].
+    pub fn set_dev_nonce_mode(&mut self, mode: mac::DevNonceMode) {
+        self.shared.mac.set_dev_nonce_mode(mode);
+    }
+
+    /// Restore the DevNonce counter, ie: the DevNonce to use for the next join request.
+    pub fn set_dev_nonce(&mut self, dev_nonce: u32) {
+        self.shared.mac.set_dev_nonce(dev_nonce);
+    }
+
+    pub fn get_dev_nonce(&self) -> u32 {
+        self.shared.mac.get_dev_nonce()
+    }
+
+    /// Returns the DevNonce counter if it has changed since it was last restored or taken. The
+    /// DevNonce of a join request is also provided with [`Response::UplinkSending`]. The counter
+    /// needs to be persisted before joining, see [`crate::async_device::Device::join`].
+    pub fn take_dev_nonce_update(&mut self) -> Option<u32> {
+        self.shared.mac.take_dev_nonce_update()
+    }
+
+    /// Set the function which provides the battery level reported in DevStatusAns. See
+    /// [`crate::async_device::Device::set_battery_level_callback`].
+    pub fn set_battery_level_callback(&mut self, battery_level: fn() -> mac::BatteryLevel) {
+        self.shared.mac.set_battery_level_callback(battery_level);
+    }
+
+    /// Maximum length of the application payload of the next uplink. See
+    /// [`crate::async_device::Device::available_payload_length`].
+    pub fn available_payload_length(&self) -> u8 {
+        self.shared.mac.available_payload_length()
+    }
+
+    /// Request link quality from the network with the next uplink. See
+    /// [`crate::async_device::Device::request_link_check`].
+    pub fn request_link_check(&mut self) -> Result<(), Error<R>> {
+        Ok(self.shared.mac.request_link_check()?)
+    }
+
+    pub fn get_link_check(&self) -> Option<mac::LinkCheck> {
+        self.shared.mac.get_link_check()
+    }
+
+    /// Request the network time with the next uplink. See
+    /// [`crate::async_device::Device::request_device_time`].
+    pub fn request_device_time(&mut self) -> Result<(), Error<R>> {
+        Ok(self.shared.mac.request_device_time()?)
+    }
+
+    /// Current network (GPS) time, based on the last DeviceTimeAns and [`Timings::get_time_ms`].
+    pub fn get_network_time(&self) -> Option<mac::GpsTime> {
+        self.shared.mac.get_gps_time(self.shared.radio.get_time_ms())
+    }
+
+    /// Snapshot of the MAC state: the session and the configuration provided by the network,
+    /// such as the channel plan, data rate, TX power and RX window settings. Store it before
+    /// powering down and bring the device back with [`Device::restore`].
+    pub fn snapshot(&self) -> mac::Snapshot {
+        self.shared.mac.snapshot()
+    }
+
+    /// Restore the MAC state from a snapshot taken with [`Device::snapshot`], on a device
+    /// created for the same region. Any operation in progress is abandoned; the device is left
+    /// unchanged if restoring fails.
+    pub fn restore(&mut self, snapshot: &mac::Snapshot) -> Result<(), mac::SnapshotError> {
+        self.shared.mac.restore(snapshot)?;
+        self.state = State::default();
+        Ok(())
+    }
+
     pub fn get_fcnt_up(&self) -> Option<u32> {
         self.shared.mac.get_fcnt_up()
     }
@@ -103,6 +210,7 @@ where
             &mut self.shared.radio,
             &mut self.shared.rng,
             &mut self.shared.tx_buffer,
+            &mut self.shared.uplink,
             &mut self.shared.downlink,
             event,
         );
@@ -115,6 +223,7 @@ pub(crate) struct Shared<R: PhyRxTx + Timings, RNG: RngCore, const N: usize, con
     pub(crate) radio: R,
     pub(crate) rng: RNG,
     pub(crate) tx_buffer: RadioBuffer<N>,
+    pub(crate) uplink: state::PendingUplink<N>,
     pub(crate) mac: Mac,
     pub(crate) downlink: Vec<Downlink, D>,
 }
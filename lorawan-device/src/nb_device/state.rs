@@ -40,7 +40,7 @@ else(Ready)║ ╚═════════════╝   ║
  */
 use super::super::*;
 use super::{
-    mac::{Frame, Mac, Window},
+    mac::{Frame, Mac, SendData, Window},
     radio, Event, RadioBuffer, Response, Timings,
 };
 
@@ -50,6 +50,7 @@ pub enum State {
     SendingData(SendingData),
     WaitingForRxWindow(WaitingForRxWindow),
     WaitingForRx(WaitingForRx),
+    WaitingForRetransmission(WaitingForRetransmission),
 }
 
 macro_rules! into_state {
@@ -64,7 +65,7 @@ macro_rules! into_state {
     )*};
 }
 
-into_state!(Idle, SendingData, WaitingForRxWindow, WaitingForRx);
+into_state!(Idle, SendingData, WaitingForRxWindow, WaitingForRx, WaitingForRetransmission);
 
 impl Default for State {
     fn default() -> Self {
@@ -90,6 +91,9 @@ pub enum Error {
     TxRequestDuringTx,
     NewSessionWhileWaitingForRx,
     SendDataWhileWaitingForRx,
+    RadioEventWhileWaitingForRetransmission,
+    NewSessionWhileWaitingForRetransmission,
+    SendDataWhileWaitingForRetransmission,
     BufferTooSmall,
     UnexpectedRadioResponse,
 }
@@ -101,6 +105,7 @@ impl<R: radio::PhyRxTx> From<Error> for super::Error<R> {
 }
 
 impl State {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn handle_event<
         R: radio::PhyRxTx + Timings,
         C: CryptoFactory + Default,
@@ -113,14 +118,20 @@ impl State {
         radio: &mut R,
         rng: &mut RNG,
         buf: &mut RadioBuffer<N>,
+        uplink: &mut PendingUplink<N>,
         dl: &mut Vec<Downlink, D>,
         event: Event<'_, R>,
     ) -> (Self, Result<Response, super::Error<R>>) {
         match self {
-            State::Idle(s) => s.handle_event::<R, C, RNG, N>(mac, radio, rng, buf, event),
+            State::Idle(s) => s.handle_event::<R, C, RNG, N>(mac, radio, rng, buf, uplink, event),
             State::SendingData(s) => s.handle_event::<R, N>(mac, radio, event),
             State::WaitingForRxWindow(s) => s.handle_event::<R, N>(mac, radio, event),
-            State::WaitingForRx(s) => s.handle_event::<R, C, N, D>(mac, radio, buf, event, dl),
+            State::WaitingForRx(s) => {
+                s.handle_event::<R, C, RNG, N, D>(mac, radio, rng, buf, uplink, event, dl)
+            }
+            State::WaitingForRetransmission(s) => {
+                s.handle_event::<R, C, RNG, N>(mac, radio, rng, buf, uplink, event)
+            }
         }
     }
 }
@@ -140,6 +151,7 @@ impl Idle {
         radio: &mut R,
         rng: &mut RNG,
         buf: &mut RadioBuffer<N>,
+        uplink: &mut PendingUplink<N>,
         event: Event<'_, R>,
     ) -> (State, Result<Response, super::Error<R>>) {
         enum IntermediateResponse<R: radio::PhyRxTx> {
@@ -150,15 +162,23 @@ impl Idle {
         let response = match event {
             // tolerate unexpected timeout
             Event::Join(creds) => {
-                let (tx_config, dev_nonce) = mac.join_otaa::<C, RNG, N>(rng, creds, buf);
-                IntermediateResponse::RadioTx((Frame::Join, tx_config, dev_nonce as u32))
+                match mac.join_otaa::<C, RNG, N>(rng, creds, buf, radio.get_time_ms()) {
+                    Err(e) => IntermediateResponse::EarlyReturn(Err(e.into())),
+                    Ok((tx_config, dev_nonce)) => {
+                        IntermediateResponse::RadioTx((Frame::Join, tx_config, dev_nonce as u32))
+                    }
+                }
             }
             Event::TimeoutFired => IntermediateResponse::EarlyReturn(Ok(Response::NoUpdate)),
             Event::RadioEvent(_radio_event) => {
                 IntermediateResponse::EarlyReturn(Err(Error::RadioEventWhileIdle.into()))
             }
             Event::SendDataRequest(send_data) => {
-                let tx_config = mac.send::<C, RNG, N>(rng, buf, &send_data);
+                // Keep a copy of the payload in case the uplink needs to be repeated
+                if uplink.set(&send_data).is_err() {
+                    return (State::Idle(self), Err(Error::BufferTooSmall.into()));
+                }
+                let tx_config = mac.send::<C, RNG, N>(rng, buf, &send_data, radio.get_time_ms());
                 match tx_config {
                     Err(e) => IntermediateResponse::EarlyReturn(Err(e.into())),
                     Ok((tx_config, fcnt_up)) => {
@@ -170,29 +190,92 @@ impl Idle {
         match response {
             IntermediateResponse::EarlyReturn(response) => (State::Idle(self), response),
             IntermediateResponse::RadioTx((frame, tx_config, fcnt_up)) => {
-                let event: radio::Event<'_, R> =
-                    radio::Event::TxRequest(tx_config, buf.as_ref_for_read());
-                match radio.handle_event(event) {
-                    Ok(response) => {
-                        match response {
-                            // intermediate state where we wait for Join to complete sending
-                            // allows for asynchronous sending
-                            radio::Response::Txing => (
-                                State::SendingData(SendingData { frame }),
-                                Ok(Response::UplinkSending(fcnt_up)),
-                            ),
-                            // directly jump to waiting for RxWindow
-                            // allows for synchronous sending
-                            radio::Response::TxDone(ms) => {
-                                data_rxwindow1_timeout::<R, N>(frame, mac, radio, ms)
-                            }
-                            _ => (State::Idle(self), Err(Error::UnexpectedRadioResponse.into())),
-                        }
-                    }
-                    Err(e) => (State::Idle(self), Err(super::Error::Radio(e))),
+                transmit::<R, N>(frame, mac, radio, buf, tx_config, fcnt_up)
+            }
+        }
+    }
+}
+
+/// Copy of the current uplink, used for repeating unconfirmed uplinks according to NbTrans and
+/// for retransmitting unacknowledged confirmed uplinks.
+pub(crate) struct PendingUplink<const N: usize> {
+    data: Vec<u8, N>,
+    fport: u8,
+    confirmed: bool,
+}
+
+impl<const N: usize> PendingUplink<N> {
+    pub(crate) fn new() -> Self {
+        Self { data: Vec::new(), fport: 0, confirmed: false }
+    }
+
+    fn set(&mut self, send_data: &SendData<'_>) -> Result<(), ()> {
+        self.data = Vec::from_slice(send_data.data)?;
+        self.fport = send_data.fport;
+        self.confirmed = send_data.confirmed;
+        Ok(())
+    }
+
+    fn send_data(&self) -> SendData<'_> {
+        SendData { data: &self.data, fport: self.fport, confirmed: self.confirmed }
+    }
+}
+
+/// Repeat the previous uplink with the same frame counter. If that fails, the uplink is given up.
+fn retransmit<
+    R: radio::PhyRxTx + Timings,
+    C: CryptoFactory + Default,
+    RNG: RngCore,
+    const N: usize,
+>(
+    frame: Frame,
+    mac: &mut Mac,
+    radio: &mut R,
+    rng: &mut RNG,
+    buf: &mut RadioBuffer<N>,
+    uplink: &PendingUplink<N>,
+) -> (State, Result<Response, super::Error<R>>) {
+    let (state, response) =
+        match mac.retransmit::<C, RNG, N>(rng, buf, &uplink.send_data(), radio.get_time_ms()) {
+            Ok((tx_config, fcnt_up)) => {
+                transmit::<R, N>(frame, mac, radio, buf, tx_config, fcnt_up)
+            }
+            Err(e) => (State::Idle(Idle), Err(e.into())),
+        };
+    if let (State::Idle(_), Err(_)) = (&state, &response) {
+        // The frame has been on air, so its frame counter must not be reused
+        mac.abandon_uplink();
+    }
+    (state, response)
+}
+
+fn transmit<R: radio::PhyRxTx + Timings, const N: usize>(
+    frame: Frame,
+    mac: &mut Mac,
+    radio: &mut R,
+    buf: &mut RadioBuffer<N>,
+    tx_config: radio::TxConfig,
+    fcnt_up: u32,
+) -> (State, Result<Response, super::Error<R>>) {
+    let event: radio::Event<'_, R> = radio::Event::TxRequest(tx_config, buf.as_ref_for_read());
+    match radio.handle_event(event) {
+        Ok(response) => {
+            match response {
+                // intermediate state where we wait for Join to complete sending
+                // allows for asynchronous sending
+                radio::Response::Txing => (
+                    State::SendingData(SendingData { frame }),
+                    Ok(Response::UplinkSending(fcnt_up)),
+                ),
+                // directly jump to waiting for RxWindow
+                // allows for synchronous sending
+                radio::Response::TxDone(ms) => {
+                    data_rxwindow1_timeout::<R, N>(frame, mac, radio, ms)
                 }
+                _ => (State::Idle(Idle), Err(Error::UnexpectedRadioResponse.into())),
             }
         }
+        Err(e) => (State::Idle(Idle), Err(super::Error::Radio(e))),
     }
 }
 
@@ -273,8 +356,13 @@ impl WaitingForRxWindow {
                             // RxWindow2 can last however long
                             Rx::_2(time) => time + radio.get_rx_window_duration_ms(),
                         };
+                        let frequency = rx_config.frequency;
                         (
-                            State::WaitingForRx(self.into()),
+                            State::WaitingForRx(WaitingForRx {
+                                frame: self.frame,
+                                window: self.window,
+                                frequency,
+                            }),
                             Ok(Response::TimeoutRequest(window_close)),
                         )
                     }
@@ -297,29 +385,29 @@ impl WaitingForRxWindow {
     }
 }
 
-impl From<WaitingForRxWindow> for WaitingForRx {
-    fn from(val: WaitingForRxWindow) -> WaitingForRx {
-        WaitingForRx { frame: val.frame, window: val.window }
-    }
-}
-
 #[derive(Copy, Clone)]
 pub struct WaitingForRx {
     frame: Frame,
     window: Rx,
+    /// Frequency of the open receive window
+    frequency: u32,
 }
 
 impl WaitingForRx {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn handle_event<
         R: radio::PhyRxTx + Timings,
         C: CryptoFactory + Default,
+        RNG: RngCore,
         const N: usize,
         const D: usize,
     >(
         self,
         mac: &mut Mac,
         radio: &mut R,
+        rng: &mut RNG,
         buf: &mut RadioBuffer<N>,
+        uplink: &PendingUplink<N>,
         event: Event<'_, R>,
         dl: &mut Vec<Downlink, D>,
     ) -> (State, Result<Response, super::Error<R>>) {
@@ -329,7 +417,7 @@ impl WaitingForRx {
                 // send the transmit request to the radio
                 match radio.handle_event(radio_event) {
                     Ok(response) => match response {
-                        radio::Response::RxDone(_quality) => {
+                        radio::Response::RxDone(quality) => {
                             // copy from radio buffer to mac buffer
                             buf.clear();
                             if let Err(()) =
@@ -340,11 +428,21 @@ impl WaitingForRx {
                                     Err(Error::BufferTooSmall.into()),
                                 );
                             }
-                            match mac.handle_rx::<C, N, D>(buf, dl) {
+                            let window = match self.window {
+                                Rx::_1(_) => RxWindow::Rx1,
+                                Rx::_2(_) => RxWindow::Rx2,
+                            };
+                            match mac.handle_rx::<C, N, D>(buf, dl, quality, window, self.frequency)
+                            {
                                 // NoUpdate can occur when a stray radio packet is received. Maintain state
                                 mac::Response::NoUpdate => {
                                     (State::WaitingForRx(self), Ok(Response::NoUpdate))
                                 }
+                                // The downlink did not acknowledge the confirmed uplink
+                                mac::Response::RetransmitRequest => self
+                                    .retransmit_request::<R, C, RNG, N>(
+                                        mac, radio, rng, buf, uplink,
+                                    ),
                                 // Any other type of update indicates we are done receiving. Change to Idle
                                 r => (State::Idle(Idle), Ok(r.into())),
                             }
@@ -373,11 +471,14 @@ impl WaitingForRx {
                             Ok(Response::TimeoutRequest(t2)),
                         )
                     }
-                    // Timeout during second RxWindow leads to giving up
-                    Rx::_2(_) => {
-                        let response = mac.rx2_complete();
-                        (State::Idle(Idle), Ok(response.into()))
-                    }
+                    // Timeout during second RxWindow leads to giving up...
+                    Rx::_2(_) => match mac.rx2_complete() {
+                        // ...unless the unconfirmed uplink needs to be repeated (NbTrans)
+                        mac::Response::RetransmitRequest => {
+                            self.retransmit_request::<R, C, RNG, N>(mac, radio, rng, buf, uplink)
+                        }
+                        response => (State::Idle(Idle), Ok(response.into())),
+                    },
                 }
             }
             Event::Join(_) => {
@@ -390,6 +491,71 @@ impl WaitingForRx {
     }
 }
 
+impl WaitingForRx {
+    /// Repeat the uplink right away, or after ACK_TIMEOUT for unacknowledged confirmed uplinks.
+    fn retransmit_request<
+        R: radio::PhyRxTx + Timings,
+        C: CryptoFactory + Default,
+        RNG: RngCore,
+        const N: usize,
+    >(
+        self,
+        mac: &mut Mac,
+        radio: &mut R,
+        rng: &mut RNG,
+        buf: &mut RadioBuffer<N>,
+        uplink: &PendingUplink<N>,
+    ) -> (State, Result<Response, super::Error<R>>) {
+        if !uplink.confirmed {
+            return retransmit::<R, C, RNG, N>(self.frame, mac, radio, rng, buf, uplink);
+        }
+        let (Rx::_1(time) | Rx::_2(time)) = self.window;
+        let timeout = time + radio.get_rx_window_duration_ms() + mac.get_ack_timeout(rng);
+        (
+            State::WaitingForRetransmission(WaitingForRetransmission { frame: self.frame }),
+            Ok(Response::TimeoutRequest(timeout)),
+        )
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct WaitingForRetransmission {
+    frame: Frame,
+}
+
+impl WaitingForRetransmission {
+    pub(crate) fn handle_event<
+        R: radio::PhyRxTx + Timings,
+        C: CryptoFactory + Default,
+        RNG: RngCore,
+        const N: usize,
+    >(
+        self,
+        mac: &mut Mac,
+        radio: &mut R,
+        rng: &mut RNG,
+        buf: &mut RadioBuffer<N>,
+        uplink: &PendingUplink<N>,
+        event: Event<'_, R>,
+    ) -> (State, Result<Response, super::Error<R>>) {
+        match event {
+            // ACK_TIMEOUT has passed
+            Event::TimeoutFired => {
+                retransmit::<R, C, RNG, N>(self.frame, mac, radio, rng, buf, uplink)
+            }
+            Event::RadioEvent(_) => {
+                (self.into(), Err(Error::RadioEventWhileWaitingForRetransmission.into()))
+            }
+            Event::Join(_) => {
+                (self.into(), Err(Error::NewSessionWhileWaitingForRetransmission.into()))
+            }
+            Event::SendDataRequest(_) => {
+                (self.into(), Err(Error::SendDataWhileWaitingForRetransmission.into()))
+            }
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 enum Rx {
     _1(u32),
@@ -402,6 +568,7 @@ fn data_rxwindow1_timeout<R: radio::PhyRxTx + Timings, const N: usize>(
     radio: &mut R,
     timestamp_ms: u32,
 ) -> (State, Result<Response, super::Error<R>>) {
+    mac.tx_done(radio.get_time_ms());
     let delay = mac.get_rx_delay(&frame, &Window::_1);
     let t1 = (delay as i32 + timestamp_ms as i32 + radio.get_rx_window_offset_ms()) as u32;
     (
@@ -54,6 +54,43 @@ fn test_unconfirmed_uplink_no_downlink() {
     let response = device.handle_event(Event::TimeoutFired).unwrap(); // end Rx2
     assert!(matches!(response, Response::RxComplete));
 }
+
+#[test]
+fn test_send_mac_commands_none_pending() {
+    let mut device = test_device();
+    device.join(get_abp_credentials()).unwrap();
+    assert!(!device.has_pending_mac_commands());
+    let response = device.send_mac_commands();
+    assert!(matches!(response, Err(Error::Mac(mac::Error::NoMacCommands))));
+    assert!(device.ready_to_send_data());
+}
+
+#[test]
+fn test_unconfirmed_uplink_nb_trans() {
+    let mut device = test_device();
+    device.join(get_abp_credentials()).unwrap();
+    device.shared.mac.configuration.nb_trans = 2;
+    let response = device.send(&[0; 1], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    for _ in 0..3 {
+        device.handle_event(Event::TimeoutFired).unwrap();
+    }
+    // end Rx2 of the first transmission triggers a repetition
+    let mut first = device.get_radio().take_last_uplink().unwrap();
+    let response = device.handle_event(Event::TimeoutFired).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    assert_eq!(device.get_fcnt_up(), Some(0));
+    // the repeated frame has the same frame counter and payload
+    let mut repeated = device.get_radio().take_last_uplink().unwrap();
+    assert_eq!(first.get_payload(), repeated.get_payload());
+    for _ in 0..3 {
+        device.handle_event(Event::TimeoutFired).unwrap();
+    }
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // end Rx2
+    assert!(matches!(response, Response::RxComplete));
+    assert_eq!(device.get_fcnt_up(), Some(1));
+}
+
 #[test]
 fn test_confirmed_uplink_no_ack() {
     let mut device = test_device();
@@ -71,6 +108,47 @@ fn test_confirmed_uplink_no_ack() {
     assert!(matches!(response, Response::NoAck));
 }
 
+#[test]
+fn test_confirmed_uplink_retransmission() {
+    use lorawan::parser::{DataHeader, DataPayload, PhyPayload};
+
+    let mut device = test_device();
+    device.join(get_abp_credentials()).unwrap();
+    device.set_confirmed_retry_policy(mac::ConfirmedRetryPolicy {
+        max_attempts: 2,
+        datarate_step_down: false,
+    });
+    let response = device.send(&[0; 1], 1, true).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    for _ in 0..3 {
+        device.handle_event(Event::TimeoutFired).unwrap();
+    }
+    // The retransmission waits for ACK_TIMEOUT after the end of Rx2...
+    match device.handle_event(Event::TimeoutFired).unwrap() {
+        Response::TimeoutRequest(t) => assert!((3100..=5100).contains(&t)),
+        _ => panic!(),
+    }
+    let mut first = device.get_radio().take_last_uplink().unwrap();
+    // ...and repeats the confirmed frame
+    let response = device.handle_event(Event::TimeoutFired).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let mut repeated = device.get_radio().take_last_uplink().unwrap();
+    assert_eq!(first.get_payload(), repeated.get_payload());
+    match repeated.get_payload() {
+        PhyPayload::Data(DataPayload::Encrypted(data)) => {
+            assert!(data.is_confirmed());
+            assert_eq!(data.fhdr().fcnt(), 0);
+        }
+        _ => panic!(),
+    }
+    for _ in 0..3 {
+        device.handle_event(Event::TimeoutFired).unwrap();
+    }
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // end Rx2
+    assert!(matches!(response, Response::NoAck));
+    assert_eq!(device.get_fcnt_up(), Some(1));
+}
+
 #[test]
 fn test_confirmed_uplink_with_ack_rx1() {
     let mut device = test_device();
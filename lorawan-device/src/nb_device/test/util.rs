@@ -20,12 +20,17 @@ pub struct TestRadio {
     rxtx_handler: Option<RxTxHandler>,
     buffer: [u8; 256],
     buffer_index: usize,
+    time_ms: core::cell::Cell<u64>,
 }
 
 impl TestRadio {
     pub fn set_rxtx_handler(&mut self, handler: RxTxHandler) {
         self.rxtx_handler = Some(handler);
     }
+
+    pub fn take_last_uplink(&mut self) -> Option<Uplink> {
+        self.last_uplink.take()
+    }
 }
 
 impl Default for TestRadio {
@@ -36,6 +41,7 @@ impl Default for TestRadio {
             rxtx_handler: None,
             buffer: [0; 256],
             buffer_index: 0,
+            time_ms: Default::default(),
         }
     }
 }
@@ -95,4 +101,9 @@ impl Timings for TestRadio {
     fn get_rx_window_duration_ms(&self) -> u32 {
         100
     }
+    fn get_time_ms(&self) -> u64 {
+        // An hour passes between uplinks, so that duty-cycle limits don't get in the way
+        self.time_ms.set(self.time_ms.get() + 3_600_000);
+        self.time_ms.get()
+    }
 }
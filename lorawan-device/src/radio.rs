@@ -16,6 +16,12 @@ pub enum RxMode {
     Single {
         ms: u32,
     },
+    /// Single shot receive of a Class B beacon of `len` bytes, with the same timeout as `Single`.
+    /// Beacons are transmitted with an implicit header, without CRC and with non-inverted IQ.
+    Beacon {
+        ms: u32,
+        len: u8,
+    },
 }
 
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
@@ -30,6 +36,17 @@ pub struct RxConfig {
 pub struct TxConfig {
     pub pw: i8,
     pub rf: RfConfig,
+    /// Listen-before-talk which must precede the transmission, if required by the region
+    pub lbt: Option<Lbt>,
+}
+
+/// Listen-before-talk parameters: the channel may only be used if the RSSI measured on it stays
+/// below `threshold_dbm` for at least `scan_time_us`.
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lbt {
+    pub threshold_dbm: i16,
+    pub scan_time_us: u32,
 }
 
 impl TxConfig {
@@ -5,10 +5,12 @@ pub(crate) const RECEIVE_DELAY1: u32 = 1000;
 pub(crate) const RECEIVE_DELAY2: u32 = RECEIVE_DELAY1 + 1000; // must be RECEIVE_DELAY + 1 s
 pub(crate) const JOIN_ACCEPT_DELAY1: u32 = 5000;
 pub(crate) const JOIN_ACCEPT_DELAY2: u32 = 6000;
-pub(crate) const MAX_FCNT_GAP: usize = 16384;
-pub(crate) const ADR_ACK_LIMIT: usize = 64;
-pub(crate) const ADR_ACK_DELAY: usize = 32;
-pub(crate) const ACK_TIMEOUT: usize = 2; // random delay between 1 and 3 seconds
+pub(crate) const MAX_FCNT_GAP: u32 = 16384;
+pub(crate) const ADR_ACK_LIMIT: u32 = 64;
+pub(crate) const ADR_ACK_DELAY: u32 = 32;
+// Number of channel selections to try for finding another channel for NbTrans repetitions
+pub(crate) const MAX_RETRANSMIT_CHANNEL_ATTEMPTS: usize = 8;
+pub(crate) const ACK_TIMEOUT: u32 = 2000; // random delay between 1 and 3 seconds
 
 // Although there are 16 possible slots, last one is not defined as Datarate
 pub(crate) const NUM_DATARATES: u8 = 15;
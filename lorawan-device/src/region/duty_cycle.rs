@@ -0,0 +1,58 @@
+//! Airtime accounting for the aggregated duty cycle set with `DutyCycleReq` and the join-request
+//! backoff of RP002-1.0.x. Regional sub-band limits are handled by the channel plans.
+use super::Frame;
+
+const HOUR_MS: u64 = 60 * 60 * 1000;
+
+/// Region-independent duty-cycle limits, applying on top of the regional sub-band limits.
+#[derive(Clone, Default)]
+pub(crate) struct DutyCycle {
+    /// MaxDCycle from `DutyCycleReq`; the aggregated duty cycle is 1 / 2^MaxDCycle.
+    max_duty_cycle: u8,
+    blocked_until: u64,
+    /// Time of the first join-request since the device was last joined
+    join_started: Option<u64>,
+    join_blocked_until: u64,
+}
+
+impl DutyCycle {
+    pub fn set_max_duty_cycle(&mut self, max_duty_cycle: u8) {
+        self.max_duty_cycle = max_duty_cycle;
+    }
+
+    pub fn max_duty_cycle(&self) -> u8 {
+        self.max_duty_cycle
+    }
+
+    /// Restart join-request backoff, eg: after the device has joined successfully.
+    pub fn reset_join_backoff(&mut self) {
+        self.join_started = None;
+        self.join_blocked_until = 0;
+    }
+
+    /// Milliseconds until a frame may be transmitted, 0 if it may be transmitted now.
+    pub fn time_until_available(&self, frame: &Frame, now_ms: u64) -> u64 {
+        let wait = self.blocked_until.saturating_sub(now_ms);
+        match frame {
+            Frame::Join => wait.max(self.join_blocked_until.saturating_sub(now_ms)),
+            Frame::Data => wait,
+        }
+    }
+
+    pub fn register_transmission(&mut self, frame: &Frame, now_ms: u64, airtime_ms: u64) {
+        if self.max_duty_cycle > 0 {
+            self.blocked_until = now_ms + (airtime_ms << self.max_duty_cycle.min(15));
+        }
+        if let Frame::Join = frame {
+            // Join-request duty cycle is 1% during the first hour, 0.1% during the next 10 hours
+            // and 0.01% afterwards.
+            let started = *self.join_started.get_or_insert(now_ms);
+            let duty_cycle_inverse = match now_ms - started {
+                t if t < HOUR_MS => 100,
+                t if t < 11 * HOUR_MS => 1000,
+                _ => 10000,
+            };
+            self.join_blocked_until = now_ms + airtime_ms * duty_cycle_inverse;
+        }
+    }
+}
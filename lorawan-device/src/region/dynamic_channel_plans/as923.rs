@@ -29,6 +29,15 @@ impl<const DEFAULT_RX2: u32, const OFFSET: u32> ChannelRegion for AS923Region<DE
             _ => None,
         }
     }
+
+    fn supports_tx_param_setup() -> bool {
+        true
+    }
+
+    #[cfg(feature = "class-b")]
+    fn beacon_channel() -> Option<BeaconChannel> {
+        Some(BeaconChannel::new(923_400_000 - OFFSET, DR::_3, (2, 0)))
+    }
 }
 
 fn as924_generic_freq_check(f: u32) -> bool {
@@ -60,12 +69,30 @@ impl<const DEFAULT_RX2: u32, const OFFSET: u32> DynamicChannelRegion
         DEFAULT_RX2
     }
 
+    fn get_default_rx2_datarate() -> DR {
+        DR::_2
+    }
+
+    fn max_rx1_dr_offset() -> u8 {
+        7
+    }
+
+    // RX1DROffset values 6 and 7 increase the data rate
+    fn get_rx1_datarate(tx_datarate: DR, rx1_dr_offset: u8) -> DR {
+        let dr = match rx1_dr_offset {
+            6 => tx_datarate as u8 + 1,
+            7 => tx_datarate as u8 + 2,
+            _ => (tx_datarate as u8).saturating_sub(rx1_dr_offset),
+        };
+        DR::try_from(dr.min(5)).unwrap()
+    }
+
     // Although Network gateways SHALL always listen on following frequencies
     // with DR0..=DR5, the default Join-Request Data Rate SHALL utilize DR2..=DR5
     // (SF10/125 kHz – SF7/125 kHz).
     fn init_channels(channels: &mut ChannelPlan) {
-        channels[0] = Some(Channel::new(923200000 - OFFSET, DR::_2, DR::_5));
-        channels[1] = Some(Channel::new(923400000 - OFFSET, DR::_2, DR::_5));
+        channels[0] = Some(Channel::new(923200000 - OFFSET, DR::_0, DR::_5));
+        channels[1] = Some(Channel::new(923400000 - OFFSET, DR::_0, DR::_5));
     }
 }
 
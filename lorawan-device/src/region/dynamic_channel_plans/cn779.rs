@@ -0,0 +1,134 @@
+/// CN779 region support (779..787 MHz)
+///
+/// CN779-787 end-devices SHALL support one of the two following data rate options:
+/// 1. DR0 to DR5 (minimum set supported for certification)
+/// 2. DR0 to DR7
+///
+/// Current status: DR0..DR5 (minimum set is supported)
+use super::*;
+
+/// 12.15 dBm
+const MAX_EIRP: u8 = 12;
+
+const BANDS: [Band; 1] = [Band::new(779_000_000, 787_000_000, 100)];
+
+pub(crate) type CN779 = DynamicChannelPlan<CN779Region>;
+
+#[derive(Default, Clone)]
+#[allow(clippy::upper_case_acronyms)]
+pub struct CN779Region;
+
+fn cn779_freq_check(f: u32) -> bool {
+    (779_000_000..=787_000_000).contains(&f)
+}
+
+impl<R: DynamicChannelRegion> DynamicChannelPlan<R> {
+    pub fn new_cn779() -> Self {
+        Self::new(cn779_freq_check)
+    }
+}
+
+impl ChannelRegion for CN779Region {
+    fn datarates() -> &'static [Option<Datarate>; NUM_DATARATES as usize] {
+        &DATARATES
+    }
+
+    fn tx_power_adjust(pw: u8) -> Option<u8> {
+        match pw {
+            0..=5 => Some(MAX_EIRP - (2 * pw)),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "class-b")]
+    fn beacon_channel() -> Option<BeaconChannel> {
+        Some(BeaconChannel::new(785_000_000, DR::_3, (2, 0)))
+    }
+}
+
+impl DynamicChannelRegion for CN779Region {
+    fn join_channels() -> u8 {
+        3
+    }
+
+    fn get_default_rx2() -> u32 {
+        786_000_000
+    }
+
+    fn bands() -> &'static [Band] {
+        &BANDS
+    }
+
+    fn init_channels(channels: &mut ChannelPlan) {
+        channels[0] = Some(Channel::new(779_500_000, DR::_0, DR::_5));
+        channels[1] = Some(Channel::new(779_700_000, DR::_0, DR::_5));
+        channels[2] = Some(Channel::new(779_900_000, DR::_0, DR::_5));
+    }
+}
+
+use super::{Bandwidth, Datarate, SpreadingFactor};
+
+pub(crate) const DATARATES: [Option<Datarate>; NUM_DATARATES as usize] = [
+    // DR0
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_12,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 59,
+        max_mac_payload_size_with_dwell_time: 59,
+    }),
+    // DR1
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_11,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 59,
+        max_mac_payload_size_with_dwell_time: 59,
+    }),
+    // DR2
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_10,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 59,
+        max_mac_payload_size_with_dwell_time: 59,
+    }),
+    // DR3
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_9,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 123,
+        max_mac_payload_size_with_dwell_time: 123,
+    }),
+    // DR4
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_8,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 250,
+        max_mac_payload_size_with_dwell_time: 250,
+    }),
+    // DR5
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_7,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 250,
+        max_mac_payload_size_with_dwell_time: 250,
+    }),
+    None,
+    /*
+    // TODO: DR6: Can be enabled once DR7 is implemented
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_7,
+        bandwidth: Bandwidth::_250KHz,
+        max_mac_payload_size: 250,
+        max_mac_payload_size_with_dwell_time: 250,
+    }),
+    */
+    // TODO: DR7: FSK: 50 kbps
+    None,
+    // DR8..DR14: RFU
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+];
@@ -9,6 +9,8 @@ use super::*;
 
 const MAX_EIRP: u8 = 16;
 
+const BANDS: [Band; 1] = [Band::new(433_050_000, 434_790_000, 10)];
+
 pub(crate) type EU433 = DynamicChannelPlan<EU433Region>;
 
 #[derive(Default, Clone)]
@@ -36,6 +38,11 @@ impl ChannelRegion for EU433Region {
             _ => None,
         }
     }
+
+    #[cfg(feature = "class-b")]
+    fn beacon_channel() -> Option<BeaconChannel> {
+        Some(BeaconChannel::new(434_665_000, DR::_3, (2, 0)))
+    }
 }
 
 impl DynamicChannelRegion for EU433Region {
@@ -47,6 +54,10 @@ impl DynamicChannelRegion for EU433Region {
         434_665_000
     }
 
+    fn bands() -> &'static [Band] {
+        &BANDS
+    }
+
     fn init_channels(channels: &mut ChannelPlan) {
         channels[0] = Some(Channel::new(433_175_000, DR::_0, DR::_5));
         channels[1] = Some(Channel::new(433_375_000, DR::_0, DR::_5));
@@ -10,6 +10,21 @@ use super::*;
 
 const MAX_EIRP: u8 = 16;
 
+// Sub-bands of ETSI EN 300 220 used by LoRaWAN
+const BANDS: [Band; 6] = [
+    Band::new(863_000_000, 864_999_999, 1000),
+    // g
+    Band::new(865_000_000, 868_000_000, 100),
+    // g1
+    Band::new(868_000_001, 868_600_000, 100),
+    // g2
+    Band::new(868_700_000, 869_200_000, 1000),
+    // g3
+    Band::new(869_400_000, 869_650_000, 10),
+    // g4
+    Band::new(869_700_000, 870_000_000, 100),
+];
+
 pub(crate) type EU868 = DynamicChannelPlan<EU868Region>;
 
 #[derive(Default, Clone)]
@@ -37,6 +52,11 @@ impl ChannelRegion for EU868Region {
             _ => None,
         }
     }
+
+    #[cfg(feature = "class-b")]
+    fn beacon_channel() -> Option<BeaconChannel> {
+        Some(BeaconChannel::new(869_525_000, DR::_3, (2, 0)))
+    }
 }
 
 impl DynamicChannelRegion for EU868Region {
@@ -48,6 +68,10 @@ impl DynamicChannelRegion for EU868Region {
         869_525_000
     }
 
+    fn bands() -> &'static [Band] {
+        &BANDS
+    }
+
     fn init_channels(channels: &mut ChannelPlan) {
         channels[0] = Some(Channel::new(868_100_000, DR::_0, DR::_5));
         channels[1] = Some(Channel::new(868_300_000, DR::_0, DR::_5));
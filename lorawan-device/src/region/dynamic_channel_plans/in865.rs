@@ -46,6 +46,24 @@ impl DynamicChannelRegion for IN865Region {
         866_550_000
     }
 
+    fn get_default_rx2_datarate() -> DR {
+        DR::_2
+    }
+
+    fn max_rx1_dr_offset() -> u8 {
+        7
+    }
+
+    // RX1DROffset values 6 and 7 increase the data rate
+    fn get_rx1_datarate(tx_datarate: DR, rx1_dr_offset: u8) -> DR {
+        let dr = match rx1_dr_offset {
+            6 => tx_datarate as u8 + 1,
+            7 => tx_datarate as u8 + 2,
+            _ => (tx_datarate as u8).saturating_sub(rx1_dr_offset),
+        };
+        DR::try_from(dr.min(5)).unwrap()
+    }
+
     fn init_channels(channels: &mut ChannelPlan) {
         channels[0] = Some(Channel::new(865_062_500, DR::_0, DR::_5));
         channels[1] = Some(Channel::new(865_402_500, DR::_0, DR::_5));
@@ -0,0 +1,129 @@
+/// KR920 region support (920.9..923.3 MHz)
+///
+/// KR920-923 end-devices SHALL support DR0 to DR5 and perform listen-before-talk before every
+/// transmission.
+///
+/// Current status: DR0..DR5 is supported
+use super::*;
+
+const MAX_EIRP: u8 = 14;
+/// Maximum EIRP for frequencies from 920.9 to 921.9 MHz
+const MAX_EIRP_LOW_FREQUENCIES: u8 = 10;
+
+/// Channels are sensed for at least 5 ms, and are busy above -65 dBm
+const LBT: Lbt = Lbt { threshold_dbm: -65, scan_time_us: 5_000 };
+
+pub(crate) type KR920 = DynamicChannelPlan<KR920Region>;
+
+#[derive(Default, Clone)]
+#[allow(clippy::upper_case_acronyms)]
+pub struct KR920Region;
+
+fn kr920_freq_check(f: u32) -> bool {
+    (920_900_000..=923_300_000).contains(&f)
+}
+
+impl<R: DynamicChannelRegion> DynamicChannelPlan<R> {
+    pub fn new_kr920() -> Self {
+        Self::new(kr920_freq_check)
+    }
+}
+
+impl ChannelRegion for KR920Region {
+    fn datarates() -> &'static [Option<Datarate>; NUM_DATARATES as usize] {
+        &DATARATES
+    }
+
+    fn tx_power_adjust(pw: u8) -> Option<u8> {
+        match pw {
+            0..=7 => Some(MAX_EIRP - (2 * pw)),
+            _ => None,
+        }
+    }
+
+    fn max_eirp_at(frequency: u32) -> Option<u8> {
+        (frequency < 922_000_000).then_some(MAX_EIRP_LOW_FREQUENCIES)
+    }
+
+    fn listen_before_talk() -> Option<Lbt> {
+        Some(LBT)
+    }
+
+    #[cfg(feature = "class-b")]
+    fn beacon_channel() -> Option<BeaconChannel> {
+        Some(BeaconChannel::new(923_100_000, DR::_3, (2, 0)))
+    }
+}
+
+impl DynamicChannelRegion for KR920Region {
+    fn join_channels() -> u8 {
+        3
+    }
+
+    fn get_default_rx2() -> u32 {
+        921_900_000
+    }
+
+    fn init_channels(channels: &mut ChannelPlan) {
+        channels[0] = Some(Channel::new(922_100_000, DR::_0, DR::_5));
+        channels[1] = Some(Channel::new(922_300_000, DR::_0, DR::_5));
+        channels[2] = Some(Channel::new(922_500_000, DR::_0, DR::_5));
+    }
+}
+
+use super::{Bandwidth, Datarate, SpreadingFactor};
+
+pub(crate) const DATARATES: [Option<Datarate>; NUM_DATARATES as usize] = [
+    // DR0
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_12,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 59,
+        max_mac_payload_size_with_dwell_time: 59,
+    }),
+    // DR1
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_11,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 59,
+        max_mac_payload_size_with_dwell_time: 59,
+    }),
+    // DR2
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_10,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 59,
+        max_mac_payload_size_with_dwell_time: 59,
+    }),
+    // DR3
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_9,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 123,
+        max_mac_payload_size_with_dwell_time: 123,
+    }),
+    // DR4
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_8,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 250,
+        max_mac_payload_size_with_dwell_time: 250,
+    }),
+    // DR5
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_7,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 250,
+        max_mac_payload_size_with_dwell_time: 250,
+    }),
+    // DR6..DR14: RFU
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+];
@@ -9,12 +9,18 @@ use lorawan::types::DataRateRange;
     feature = "region-as923-4"
 ))]
 mod as923;
+#[cfg(feature = "region-cn779")]
+mod cn779;
 #[cfg(feature = "region-eu433")]
 mod eu433;
 #[cfg(feature = "region-eu868")]
 mod eu868;
 #[cfg(feature = "region-in865")]
 mod in865;
+#[cfg(feature = "region-kr920")]
+mod kr920;
+#[cfg(feature = "region-ru864")]
+mod ru864;
 
 #[cfg(feature = "region-as923-1")]
 pub(crate) use as923::AS923_1;
@@ -24,23 +30,101 @@ pub(crate) use as923::AS923_2;
 pub(crate) use as923::AS923_3;
 #[cfg(feature = "region-as923-4")]
 pub(crate) use as923::AS923_4;
+#[cfg(feature = "region-cn779")]
+pub(crate) use cn779::CN779;
 #[cfg(feature = "region-eu433")]
 pub(crate) use eu433::EU433;
 #[cfg(feature = "region-eu868")]
 pub(crate) use eu868::EU868;
 #[cfg(feature = "region-in865")]
 pub(crate) use in865::IN865;
+#[cfg(feature = "region-kr920")]
+pub(crate) use kr920::KR920;
+#[cfg(feature = "region-ru864")]
+pub(crate) use ru864::RU864;
 
 #[derive(Clone, Copy)]
 pub(crate) struct Channel {
     frequency: u32,
-    _datarates: DataRateRange,
+    /// RX1 downlink frequency, which equals the uplink frequency unless set with DlChannelReq
+    dl_frequency: u32,
+    datarates: DataRateRange,
 }
 
 impl Channel {
     /// Initialize Channel with frequency and supported minimum and maximum data rates
     fn new(f: u32, dr_min: DR, dr_max: DR) -> Self {
-        Self { frequency: f, _datarates: DataRateRange::new_range(dr_min, dr_max) }
+        Self::with_range(f, DataRateRange::new_range(dr_min, dr_max))
+    }
+
+    fn with_range(f: u32, datarates: DataRateRange) -> Self {
+        Self { frequency: f, dl_frequency: f, datarates }
+    }
+
+    /// Whether the data rate may be used on this channel
+    fn supports(&self, datarate: DR) -> bool {
+        (self.datarates.min_data_rate()..=self.datarates.max_data_rate())
+            .contains(&(datarate as u8))
+    }
+}
+
+/// Maximum number of sub-bands with a separate duty-cycle limit in a region.
+pub(crate) const MAX_BANDS: usize = 6;
+
+/// Sub-band sharing a common duty-cycle limit.
+pub(crate) struct Band {
+    pub min_frequency: u32,
+    pub max_frequency: u32,
+    /// Inverse of the duty cycle, eg: 100 for 1%.
+    pub duty_cycle_inverse: u32,
+}
+
+impl Band {
+    #[cfg(any(
+        feature = "region-cn779",
+        feature = "region-eu433",
+        feature = "region-eu868",
+        feature = "region-ru864"
+    ))]
+    pub const fn new(min_frequency: u32, max_frequency: u32, duty_cycle_inverse: u32) -> Self {
+        Self { min_frequency, max_frequency, duty_cycle_inverse }
+    }
+
+    fn contains(&self, frequency: u32) -> bool {
+        (self.min_frequency..=self.max_frequency).contains(&frequency)
+    }
+}
+
+/// Tracks until when each sub-band of a region is blocked.
+#[derive(Clone, Default)]
+pub(crate) struct BandTimers {
+    blocked_until: [u64; MAX_BANDS],
+}
+
+impl BandTimers {
+    fn band_index(bands: &[Band], frequency: u32) -> Option<usize> {
+        bands.iter().position(|band| band.contains(frequency))
+    }
+
+    /// Milliseconds until a transmission on the frequency is allowed, 0 if it is allowed now.
+    pub fn time_until_available(&self, bands: &[Band], frequency: u32, now_ms: u64) -> u64 {
+        match Self::band_index(bands, frequency) {
+            Some(index) => self.blocked_until[index].saturating_sub(now_ms),
+            None => 0,
+        }
+    }
+
+    pub fn register_transmission(
+        &mut self,
+        bands: &[Band],
+        frequency: u32,
+        now_ms: u64,
+        airtime_ms: u64,
+    ) {
+        if let Some(index) = Self::band_index(bands, frequency) {
+            self.blocked_until[index] =
+                now_ms + airtime_ms * bands[index].duty_cycle_inverse as u64;
+        }
     }
 }
 
@@ -51,9 +135,8 @@ pub(crate) struct DynamicChannelPlan<R: DynamicChannelRegion> {
     channels: ChannelPlan,
     channel_mask: ChannelMask<9>,
     last_tx_channel: u8,
+    band_timers: BandTimers,
     _fixed_channel_region: PhantomData<R>,
-    rx1_offset: usize,
-    rx2_dr: usize,
 
     frequency_valid: fn(u32) -> bool,
 }
@@ -67,18 +150,33 @@ impl<R: DynamicChannelRegion> DynamicChannelPlan<R> {
             channel_mask: Default::default(),
             channels,
             last_tx_channel: Default::default(),
+            band_timers: Default::default(),
             _fixed_channel_region: Default::default(),
-            rx1_offset: Default::default(),
-            rx2_dr: Default::default(),
             frequency_valid: freq_fn,
         }
     }
 
-    fn get_channel(&self, index: usize) -> Option<u32> {
-        if let Some(channel) = self.channels[index] {
-            return Some(channel.frequency);
-        }
-        None
+    /// Milliseconds until the channel is no longer blocked by the duty cycle of its sub-band.
+    fn time_until_available(&self, frequency: u32, now_ms: u64) -> u64 {
+        self.band_timers.time_until_available(R::bands(), frequency, now_ms)
+    }
+
+    /// Frequencies of the channels which may be used for transmitting the frame, regardless of
+    /// duty cycle. Only channels supporting `datarate` are considered, if given.
+    fn usable_frequencies(&self, frame: &Frame, datarate: DR) -> impl Iterator<Item = u32> + '_ {
+        let join = matches!(frame, Frame::Join);
+        let count = if join {
+            R::join_channels() as usize
+        } else {
+            self.channels.len()
+        };
+        self.channels[..count]
+            .iter()
+            .enumerate()
+            .filter(move |(i, _)| join || self.channel_mask.is_enabled(*i).unwrap())
+            .filter_map(|(_, channel)| channel.as_ref())
+            .filter(move |channel| channel.supports(datarate))
+            .map(|channel| channel.frequency)
     }
 
     fn get_random_in_range<RNG: RngCore>(&self, rng: &mut RNG) -> usize {
@@ -112,6 +210,20 @@ pub(crate) trait DynamicChannelRegion: ChannelRegion {
     fn join_channels() -> u8;
     fn init_channels(channels: &mut ChannelPlan);
     fn get_default_rx2() -> u32;
+    fn get_default_rx2_datarate() -> DR {
+        DR::_0
+    }
+    fn max_rx1_dr_offset() -> u8 {
+        5
+    }
+    /// RX1 data rate for the uplink data rate and a valid RX1DROffset
+    fn get_rx1_datarate(tx_datarate: DR, rx1_dr_offset: u8) -> DR {
+        DR::try_from((tx_datarate as u8).saturating_sub(rx1_dr_offset)).unwrap()
+    }
+    /// Sub-bands with duty-cycle limits
+    fn bands() -> &'static [Band] {
+        &[]
+    }
 }
 
 impl<R: DynamicChannelRegion> RegionHandler for DynamicChannelPlan<R> {
@@ -136,9 +248,13 @@ impl<R: DynamicChannelRegion> RegionHandler for DynamicChannelPlan<R> {
                 }
             }
             // Type 1
-            Some(CfList::FixedChannel(_cf_list)) => {
-                // TODO: dynamic channel plans have corresponding fixed channel lists,
-                // however, this feature is entirely optional
+            Some(CfList::FixedChannel(channel_mask)) => {
+                // The mask is ignored if it enables undefined channels or none at all
+                let defined = |i: usize| self.channels.get(i).is_some_and(Option::is_some);
+                let mut enabled = (0..72).filter(|&i| channel_mask.is_enabled(i).unwrap());
+                if enabled.clone().next().is_some() && enabled.all(defined) {
+                    self.channel_mask = channel_mask;
+                }
             }
             None => {}
         }
@@ -191,16 +307,18 @@ impl<R: DynamicChannelRegion> RegionHandler for DynamicChannelPlan<R> {
         Some(())
     }
 
-    fn channel_mask_validate(&self, channel_mask: &ChannelMask<9>, _dr: Option<DR>) -> bool {
-        // TODO: We should also check whether DR and txpower for all(?) channels is valid
-        (0..NUM_CHANNELS_DYNAMIC).any(|i| {
-            if channel_mask.is_enabled(i as usize).unwrap() {
-                self.channels[i as usize].is_some()
-            } else {
-                false
-            }
+    fn channel_mask_validate(&self, channel_mask: &ChannelMask<9>, dr: Option<DR>) -> bool {
+        // At least one enabled channel has to be defined and support the requested data rate
+        (0..NUM_CHANNELS_DYNAMIC as usize).any(|i| {
+            channel_mask.is_enabled(i).unwrap()
+                && self.channels[i].is_some_and(|c| dr.map_or(true, |dr| c.supports(dr)))
         })
-        // (2..9).all(|i| channel_mask.get_index(i) == 0)
+    }
+
+    fn enable_default_channels(&mut self) {
+        for i in 0..R::join_channels() {
+            self.channel_mask.set_channel(i as usize, true);
+        }
     }
 
     fn get_tx_dr_and_frequency<RNG: RngCore>(
@@ -208,50 +326,89 @@ impl<R: DynamicChannelRegion> RegionHandler for DynamicChannelPlan<R> {
         rng: &mut RNG,
         datarate: DR,
         frame: &Frame,
-    ) -> (Datarate, u32) {
-        match frame {
+        now_ms: u64,
+    ) -> Option<(Datarate, u32)> {
+        // Either no usable channel supports the data rate, or all of them are blocked by duty cycle
+        if self.time_until_channel_available(frame, datarate, now_ms) != 0
+            || self.usable_frequencies(frame, datarate).next().is_none()
+        {
+            return None;
+        }
+        let usable = |index: usize| match self.channels[index] {
+            Some(channel) => {
+                channel.supports(datarate)
+                    && self.time_until_available(channel.frequency, now_ms) == 0
+            }
+            None => false,
+        };
+        let index = match frame {
             Frame::Join => {
                 // There are at most 3 join channels in dynamic regions,
-                // keep sampling until we get a valid channel.
-                let mut index = (rng.next_u32() & 0b11) as u8;
-                while index >= R::join_channels() {
-                    index = (rng.next_u32() & 0b11) as u8;
+                // keep sampling until we get a valid channel which is not blocked by duty cycle.
+                let mut index = (rng.next_u32() & 0b11) as usize;
+                while index >= R::join_channels() as usize || !usable(index) {
+                    index = (rng.next_u32() & 0b11) as usize;
                 }
-                self.last_tx_channel = index;
-
-                // SAFETY: Join channels SHALL be always present
-                let channel = self.channels[index as usize].unwrap();
-                (R::datarates()[datarate as usize].clone().unwrap(), channel.frequency)
+                index
             }
             Frame::Data => {
-                let mut channel = self.get_random_in_range(rng);
-                loop {
-                    if self.channel_mask.is_enabled(channel).unwrap() {
-                        if let Some(freq) = self.get_channel(channel) {
-                            self.last_tx_channel = channel as u8;
-                            return (R::datarates()[datarate as usize].clone().unwrap(), freq);
-                        }
-                    }
-                    channel = self.get_random_in_range(rng)
+                let mut index = self.get_random_in_range(rng);
+                while !self.channel_mask.is_enabled(index).unwrap() || !usable(index) {
+                    index = self.get_random_in_range(rng);
                 }
+                index
             }
-        }
+        };
+        self.last_tx_channel = index as u8;
+        let frequency = self.channels[index].unwrap().frequency;
+        Some((R::datarates()[datarate as usize].clone().unwrap(), frequency))
+    }
+
+    fn time_until_channel_available(&self, frame: &Frame, datarate: DR, now_ms: u64) -> u64 {
+        self.usable_frequencies(frame, datarate)
+            .map(|freq| self.time_until_available(freq, now_ms))
+            .min()
+            .unwrap_or(0)
+    }
+
+    fn register_transmission(&mut self, frequency: u32, now_ms: u64, airtime_ms: u64) {
+        self.band_timers.register_transmission(R::bands(), frequency, now_ms, airtime_ms);
+    }
+
+    fn get_last_tx_channel(&self) -> u8 {
+        self.last_tx_channel
+    }
+
+    fn get_last_tx_frequency(&self) -> u32 {
+        self.channels[self.last_tx_channel as usize].map(|c| c.frequency).unwrap_or_default()
     }
 
     fn get_rx_frequency(&self, _frame: &Frame, window: &Window) -> u32 {
         match window {
             // TODO: implement RxOffset but first need to implement RxOffset MacCommand
-            Window::_1 => self.get_channel(self.last_tx_channel as usize).unwrap(),
+            Window::_1 => self.channels[self.last_tx_channel as usize].unwrap().dl_frequency,
             Window::_2 => R::get_default_rx2(),
         }
     }
 
-    fn get_rx_datarate(&self, tx_datarate: DR, _frame: &Frame, window: &Window) -> Datarate {
-        let datarate = match window {
-            Window::_1 => tx_datarate as usize + self.rx1_offset,
-            Window::_2 => self.rx2_dr,
-        };
-        R::datarates()[datarate].clone().unwrap()
+    fn datarates(&self) -> &'static [Option<Datarate>; NUM_DATARATES as usize] {
+        R::datarates()
+    }
+
+    fn get_rx1_datarate(&self, tx_datarate: DR, rx1_dr_offset: u8) -> DR {
+        R::get_rx1_datarate(tx_datarate, rx1_dr_offset)
+    }
+
+    fn get_default_rx2_datarate(&self) -> DR {
+        R::get_default_rx2_datarate()
+    }
+
+    fn rx1_dr_offset_valid(&self, rx1_dr_offset: u8) -> bool {
+        rx1_dr_offset <= R::max_rx1_dr_offset()
+    }
+
+    fn check_rx2_data_rate(&self, data_rate: u8) -> Option<DR> {
+        self.check_data_rate(data_rate)
     }
 
     fn check_tx_power(&self, tx_power: u8) -> Option<u8> {
@@ -262,6 +419,23 @@ impl<R: DynamicChannelRegion> RegionHandler for DynamicChannelPlan<R> {
         (self.frequency_valid)(freq)
     }
 
+    fn supports_tx_param_setup(&self) -> bool {
+        R::supports_tx_param_setup()
+    }
+
+    fn max_eirp_at(&self, frequency: u32) -> Option<u8> {
+        R::max_eirp_at(frequency)
+    }
+
+    fn listen_before_talk(&self) -> Option<Lbt> {
+        R::listen_before_talk()
+    }
+
+    #[cfg(feature = "class-b")]
+    fn beacon_channel(&self) -> Option<BeaconChannel> {
+        R::beacon_channel()
+    }
+
     fn has_fixed_channel_plan(&self) -> bool {
         false
     }
@@ -289,10 +463,59 @@ impl<R: DynamicChannelRegion> RegionHandler for DynamicChannelPlan<R> {
                 .all(|c| (R::datarates()[c as usize]).is_some());
 
             if freq_valid && dr_supported {
-                self.channels[index as usize] = Some(Channel { frequency: freq, _datarates: r });
+                self.channels[index as usize] = Some(Channel::with_range(freq, r));
             }
             return (freq_valid, dr_supported);
         }
         (freq_valid, false)
     }
+
+    fn handle_dl_channel(&mut self, index: u8, freq: u32) -> (bool, bool) {
+        let freq_valid = self.frequency_valid(freq);
+        match self.channels.get_mut(index as usize) {
+            Some(Some(channel)) => {
+                if freq_valid {
+                    channel.dl_frequency = freq;
+                }
+                (freq_valid, true)
+            }
+            _ => (freq_valid, false),
+        }
+    }
+
+    fn write_snapshot(&self, w: &mut Writer) {
+        w.bytes(self.channel_mask.as_ref());
+        w.u8(self.last_tx_channel);
+        for channel in self.channels.iter() {
+            w.option(channel.as_ref(), |w, channel| {
+                w.u32(channel.frequency);
+                w.u32(channel.dl_frequency);
+                w.u8(channel.datarates.raw_value());
+            });
+        }
+    }
+
+    fn restore_snapshot(&mut self, r: &mut Reader<'_>) -> snapshot::Result {
+        let channel_mask = ChannelMask::from(r.bytes::<9>()?);
+        let last_tx_channel = r.u8()?;
+        if last_tx_channel >= NUM_CHANNELS_DYNAMIC {
+            return Err(SnapshotError::Invalid);
+        }
+        let mut channels: ChannelPlan = [None; NUM_CHANNELS_DYNAMIC as usize];
+        for channel in channels.iter_mut() {
+            *channel = r.option(|r| {
+                let frequency = r.u32()?;
+                let dl_frequency = r.u32()?;
+                let datarates = DataRateRange::new(r.u8()?).map_err(|_| SnapshotError::Invalid)?;
+                if !self.frequency_valid(frequency) || !self.frequency_valid(dl_frequency) {
+                    return Err(SnapshotError::Invalid);
+                }
+                Ok(Channel { frequency, dl_frequency, datarates })
+            })?;
+        }
+        self.channel_mask = channel_mask;
+        self.last_tx_channel = last_tx_channel;
+        self.channels = channels;
+        Ok(())
+    }
 }
@@ -70,36 +70,44 @@ impl ChannelRegion for AU915Region {
             _ => None,
         }
     }
+
+    fn supports_tx_param_setup() -> bool {
+        true
+    }
+
+    #[cfg(feature = "class-b")]
+    fn beacon_channel() -> Option<BeaconChannel> {
+        Some(BeaconChannel::new(923_300_000, DR::_8, (5, 3)).hopping(8))
+    }
 }
 
 impl FixedChannelRegion for AU915Region {
-    fn uplink_channels() -> &'static [u32; 72] {
+    fn uplink_channels(&self) -> &'static [u32] {
         &UPLINK_CHANNEL_MAP
     }
-    fn downlink_channels() -> &'static [u32; 8] {
+    fn downlink_channels(&self) -> &'static [u32] {
         &DOWNLINK_CHANNEL_MAP
     }
-    fn get_default_rx2() -> u32 {
+    fn get_default_rx2(&self, _uplink_channel: u8) -> u32 {
         DEFAULT_RX2
     }
-    fn get_rx_datarate(tx_datarate: DR, _frame: &Frame, window: &Window) -> Datarate {
-        let datarate = match window {
-            Window::_1 => {
-                // no support for RX1 DR Offset
-                match tx_datarate {
-                    DR::_0 => DR::_8,
-                    DR::_1 => DR::_9,
-                    DR::_2 => DR::_10,
-                    DR::_3 => DR::_11,
-                    DR::_4 => DR::_12,
-                    DR::_5 => DR::_13,
-                    DR::_6 => DR::_13,
-                    DR::_7 => DR::_9,
-                    _ => panic!("Invalid TX datarate"),
-                }
-            }
-            Window::_2 => DR::_8,
+    fn max_rx1_dr_offset() -> u8 {
+        5
+    }
+    fn get_rx1_datarate(tx_datarate: DR, rx1_dr_offset: u8) -> DR {
+        // RX1 data rate for RX1DROffset 0, which decreases down to DR8 with the offset
+        let datarate: u8 = match tx_datarate {
+            DR::_0 => 8,
+            DR::_1 => 9,
+            DR::_2 => 10,
+            DR::_3 => 11,
+            DR::_4 => 12,
+            DR::_5 => 13,
+            // DR6 is mapped to DR13 with RX1DROffset of both 0 and 1
+            DR::_6 => 14,
+            DR::_7 => 9,
+            _ => panic!("Invalid TX datarate"),
         };
-        DATARATES[datarate as usize].clone().unwrap()
+        DR::try_from(datarate.saturating_sub(rx1_dr_offset).clamp(8, 13)).unwrap()
     }
 }
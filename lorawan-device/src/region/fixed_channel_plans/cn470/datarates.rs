@@ -0,0 +1,64 @@
+use super::{Bandwidth, Datarate, SpreadingFactor, NUM_DATARATES};
+
+pub(crate) const DATARATES: [Option<Datarate>; NUM_DATARATES as usize] = [
+    // DR0
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_12,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 59,
+        max_mac_payload_size_with_dwell_time: 59,
+    }),
+    // DR1
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_11,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 31,
+        max_mac_payload_size_with_dwell_time: 31,
+    }),
+    // DR2
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_10,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 94,
+        max_mac_payload_size_with_dwell_time: 94,
+    }),
+    // DR3
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_9,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 192,
+        max_mac_payload_size_with_dwell_time: 192,
+    }),
+    // DR4
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_8,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 250,
+        max_mac_payload_size_with_dwell_time: 250,
+    }),
+    // DR5
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_7,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 250,
+        max_mac_payload_size_with_dwell_time: 250,
+    }),
+    // TODO: DR6: SF7/500 kHz
+    None,
+    // TODO: DR7: FSK 50 kbps
+    None,
+    // DR8: RFU
+    None,
+    // DR9: RFU
+    None,
+    // DR10: RFU
+    None,
+    // DR11: RFU
+    None,
+    // DR12: RFU
+    None,
+    // DR13: RFU
+    None,
+    // DR14: RFU
+    None,
+];
@@ -0,0 +1,29 @@
+const CHANNEL_SPACING: u32 = 200_000;
+
+/// Channel map of `N` channels 200 kHz apart, the second half starting at `second_half`.
+const fn channel_map<const N: usize>(first_half: u32, second_half: u32) -> [u32; N] {
+    let mut map = [0; N];
+    let mut i = 0;
+    while i < N / 2 {
+        map[i] = first_half + i as u32 * CHANNEL_SPACING;
+        map[N / 2 + i] = second_half + i as u32 * CHANNEL_SPACING;
+        i += 1;
+    }
+    map
+}
+
+/// 20 MHz plan A: channels 0-31 from 470.3 MHz, channels 32-63 from 503.5 MHz
+pub(crate) const UPLINK_CHANNEL_MAP_20MHZ_A: [u32; 64] = channel_map(470_300_000, 503_500_000);
+pub(crate) const DOWNLINK_CHANNEL_MAP_20MHZ_A: [u32; 32] = channel_map(483_900_000, 487_100_000);
+
+/// 20 MHz plan B: channels 0-31 from 476.9 MHz, channels 32-63 from 496.9 MHz
+pub(crate) const UPLINK_CHANNEL_MAP_20MHZ_B: [u32; 64] = channel_map(476_900_000, 496_900_000);
+pub(crate) const DOWNLINK_CHANNEL_MAP_20MHZ_B: [u32; 32] = channel_map(490_300_000, 493_500_000);
+
+/// 26 MHz plan A: channels 0-47 from 470.3 MHz
+pub(crate) const UPLINK_CHANNEL_MAP_26MHZ_A: [u32; 48] = channel_map(470_300_000, 475_100_000);
+pub(crate) const DOWNLINK_CHANNEL_MAP_26MHZ_A: [u32; 24] = channel_map(490_100_000, 492_500_000);
+
+/// 26 MHz plan B: channels 0-47 from 480.3 MHz
+pub(crate) const UPLINK_CHANNEL_MAP_26MHZ_B: [u32; 48] = channel_map(480_300_000, 485_100_000);
+pub(crate) const DOWNLINK_CHANNEL_MAP_26MHZ_B: [u32; 24] = channel_map(500_100_000, 502_500_000);
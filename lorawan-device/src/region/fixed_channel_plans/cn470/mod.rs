@@ -0,0 +1,171 @@
+/// CN470 region support (470..510 MHz)
+///
+/// CN470-510 end-devices SHALL support DR0 to DR5 and one of the four channel plans of
+/// RP002-1.0.4: the 20 MHz plans A and B, with 64 uplink and 32 downlink channels, and the 26 MHz
+/// plans A and B, with 48 uplink and 24 downlink channels.
+///
+/// Current status: DR0..DR5 is supported, DR6 (SF7/500 kHz) and DR7 (FSK) are unimplemented
+use super::*;
+
+mod frequencies;
+use frequencies::*;
+
+mod datarates;
+use datarates::*;
+
+/// 19.15 dBm
+const MAX_EIRP: u8 = 19;
+
+/// Channel plans of the CN470 region.
+///
+/// The plan of the network is not known before joining, so join-requests cycle through all of
+/// them and the plan whose join-request got accepted is used from then on, unless a plan is set
+/// with [`CN470::set_channel_plan`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Cn470Plan {
+    #[default]
+    _20MHzA,
+    _20MHzB,
+    _26MHzA,
+    _26MHzB,
+}
+
+const PLANS: [Cn470Plan; 4] =
+    [Cn470Plan::_20MHzA, Cn470Plan::_20MHzB, Cn470Plan::_26MHzA, Cn470Plan::_26MHzB];
+
+/// State struct for the `CN470` region. This struct may be created directly to set the channel
+/// plan of the network, which is required for ABP. It can then be turned into a [`Configuration`]
+/// as it implements [`Into<Configuration>`].
+///
+/// # Example: Setting up the channel plan
+///
+/// ```
+/// use lorawan_device::region::{Cn470Plan, Configuration, CN470};
+///
+/// let mut cn470 = CN470::new();
+/// cn470.set_channel_plan(Cn470Plan::_26MHzA);
+/// let configuration: Configuration = cn470.into();
+/// ```
+#[derive(Clone)]
+pub struct CN470(pub(crate) FixedChannelPlan<CN470Region>);
+
+impl CN470 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `plan` only, instead of searching the plan of the network while joining.
+    pub fn set_channel_plan(&mut self, plan: Cn470Plan) {
+        self.0.region.plan = plan;
+        self.0.region.next_join_plan = None;
+    }
+
+    pub fn get_max_payload_length(datarate: DR, repeater_compatible: bool, dwell_time: bool) -> u8 {
+        CN470Region::get_max_payload_length(datarate, repeater_compatible, dwell_time)
+    }
+}
+
+fn cn470_default_freq(f: u32) -> bool {
+    (470_000_000..=510_000_000).contains(&f)
+}
+
+impl Default for CN470 {
+    fn default() -> CN470 {
+        CN470(FixedChannelPlan::new(cn470_default_freq))
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct CN470Region {
+    plan: Cn470Plan,
+    /// Index in [`PLANS`] of the plan of the next join-request, while searching the plan of the
+    /// network.
+    next_join_plan: Option<u8>,
+}
+
+impl Default for CN470Region {
+    fn default() -> Self {
+        Self { plan: Cn470Plan::default(), next_join_plan: Some(0) }
+    }
+}
+
+impl ChannelRegion for CN470Region {
+    fn datarates() -> &'static [Option<Datarate>; NUM_DATARATES as usize] {
+        &DATARATES
+    }
+
+    fn tx_power_adjust(pw: u8) -> Option<u8> {
+        match pw {
+            0..=7 => Some(MAX_EIRP - (2 * pw)),
+            _ => None,
+        }
+    }
+}
+
+impl FixedChannelRegion for CN470Region {
+    fn uplink_channels(&self) -> &'static [u32] {
+        match self.plan {
+            Cn470Plan::_20MHzA => &UPLINK_CHANNEL_MAP_20MHZ_A,
+            Cn470Plan::_20MHzB => &UPLINK_CHANNEL_MAP_20MHZ_B,
+            Cn470Plan::_26MHzA => &UPLINK_CHANNEL_MAP_26MHZ_A,
+            Cn470Plan::_26MHzB => &UPLINK_CHANNEL_MAP_26MHZ_B,
+        }
+    }
+    fn downlink_channels(&self) -> &'static [u32] {
+        match self.plan {
+            Cn470Plan::_20MHzA => &DOWNLINK_CHANNEL_MAP_20MHZ_A,
+            Cn470Plan::_20MHzB => &DOWNLINK_CHANNEL_MAP_20MHZ_B,
+            Cn470Plan::_26MHzA => &DOWNLINK_CHANNEL_MAP_26MHZ_A,
+            Cn470Plan::_26MHzB => &DOWNLINK_CHANNEL_MAP_26MHZ_B,
+        }
+    }
+    fn get_default_rx2(&self, uplink_channel: u8) -> u32 {
+        // The 20 MHz plans use a different RX2 frequency for each of their two uplink groups
+        match (self.plan, uplink_channel < 32) {
+            (Cn470Plan::_20MHzA, true) => 485_300_000,
+            (Cn470Plan::_20MHzA, false) => 486_900_000,
+            (Cn470Plan::_20MHzB, true) => 496_500_000,
+            (Cn470Plan::_20MHzB, false) => 498_300_000,
+            (Cn470Plan::_26MHzA, _) => 492_500_000,
+            (Cn470Plan::_26MHzB, _) => 502_500_000,
+        }
+    }
+    fn get_default_rx2_datarate() -> DR {
+        DR::_1
+    }
+    fn min_rx2_datarate() -> u8 {
+        0
+    }
+    fn max_rx1_dr_offset() -> u8 {
+        5
+    }
+    fn get_rx1_datarate(tx_datarate: DR, rx1_dr_offset: u8) -> DR {
+        DR::try_from((tx_datarate as u8).saturating_sub(rx1_dr_offset)).unwrap()
+    }
+    fn next_join_channel<RNG: RngCore>(&mut self, rng: &mut RNG) -> Option<u8> {
+        if let Some(index) = self.next_join_plan {
+            self.plan = PLANS[index as usize];
+            self.next_join_plan = Some((index + 1) % PLANS.len() as u8);
+        }
+        Some((rng.next_u32() as usize % self.uplink_channels().len()) as u8)
+    }
+    fn join_accepted(&mut self) {
+        self.next_join_plan = None;
+    }
+    fn write_snapshot(&self, w: &mut Writer) {
+        w.u8(self.plan as u8);
+        w.u8(self.next_join_plan.unwrap_or(u8::MAX));
+    }
+    fn restore_snapshot(&mut self, r: &mut Reader<'_>) -> snapshot::Result {
+        let plan = *PLANS.get(r.u8()? as usize).ok_or(SnapshotError::Invalid)?;
+        let next_join_plan = match r.u8()? {
+            u8::MAX => None,
+            index if (index as usize) < PLANS.len() => Some(index),
+            _ => return Err(SnapshotError::Invalid),
+        };
+        self.plan = plan;
+        self.next_join_plan = next_join_plan;
+        Ok(())
+    }
+}
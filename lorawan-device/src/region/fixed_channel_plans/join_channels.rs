@@ -42,6 +42,7 @@ impl JoinChannels {
         }
     }
 
+    #[cfg(any(feature = "region-us915", feature = "region-au915"))]
     pub(crate) fn set_join_bias(&mut self, subband: Subband, max_retries: usize) {
         self.preferred_subband = Some(subband);
         self.max_retries = max_retries;
@@ -162,6 +163,7 @@ impl AvailableChannels {
 /// This macro implements public functions relating to a fixed plan region. This is preferred to a
 /// trait implementation because the user does not have to worry about importing the trait to make
 /// use of these functions.
+#[cfg(any(feature = "region-us915", feature = "region-au915"))]
 macro_rules! impl_join_bias {
     ($region:ident) => {
         impl $region {
@@ -216,8 +218,9 @@ mod test {
     use crate::mac::Response;
     use crate::{
         mac::{Mac, SendData},
+        radio::RxQuality,
         test_util::{get_key, handle_join_request, Uplink},
-        AppEui, AppKey, DevEui, NetworkCredentials,
+        AppEui, AppKey, DevEui, NetworkCredentials, RxWindow,
     };
     use heapless::Vec;
     use lorawan::default_crypto::DefaultFactory;
@@ -300,15 +303,18 @@ mod test {
         let mut mac = Mac::new(us915.into(), 21, 2);
 
         let mut buf: RadioBuffer<255> = RadioBuffer::new();
-        let (tx_config, _len) = mac.join_otaa::<DefaultFactory, _, 255>(
-            &mut rand::rngs::OsRng,
-            NetworkCredentials::new(
-                AppEui::from([0x0; 8]),
-                DevEui::from([0x0; 8]),
-                AppKey::from(get_key()),
-            ),
-            &mut buf,
-        );
+        let (tx_config, _len) = mac
+            .join_otaa::<DefaultFactory, _, 255>(
+                &mut rand::rngs::OsRng,
+                NetworkCredentials::new(
+                    AppEui::from([0x0; 8]),
+                    DevEui::from([0x0; 8]),
+                    AppKey::from(get_key()),
+                ),
+                &mut buf,
+                0,
+            )
+            .unwrap();
         // Confirm that the join request occurs on our subband
         assert!(
             tx_config.rf.frequency >= 903_900_000,
@@ -329,7 +335,13 @@ mod test {
         let len = handle_join_request::<0>(Some(uplink), tx_config.rf, &mut rx_buf);
         buf.clear();
         buf.extend_from_slice(&rx_buf[..len]).unwrap();
-        let response = mac.handle_rx::<DefaultFactory, 255, 3>(&mut buf, &mut downlinks);
+        let response = mac.handle_rx::<DefaultFactory, 255, 3>(
+            &mut buf,
+            &mut downlinks,
+            RxQuality::new(0, 0),
+            RxWindow::Rx1,
+            0,
+        );
         if let Response::JoinSuccess = response {
         } else {
             panic!("Did not receive join success");
@@ -339,6 +351,7 @@ mod test {
                 &mut rand::rngs::OsRng,
                 &mut buf,
                 &SendData { fport: 1, data: &[0x0; 1], confirmed: false },
+                0,
             )
             .unwrap();
         // Confirm that the first data frame occurs on our subband
@@ -361,15 +374,18 @@ mod test {
         let mut mac = Mac::new(us915.into(), 21, 2);
 
         let mut buf: RadioBuffer<255> = RadioBuffer::new();
-        let (tx_config, _len) = mac.join_otaa::<DefaultFactory, _, 255>(
-            &mut rand::rngs::OsRng,
-            NetworkCredentials::new(
-                AppEui::from([0x0; 8]),
-                DevEui::from([0x0; 8]),
-                AppKey::from(get_key()),
-            ),
-            &mut buf,
-        );
+        let (tx_config, _len) = mac
+            .join_otaa::<DefaultFactory, _, 255>(
+                &mut rand::rngs::OsRng,
+                NetworkCredentials::new(
+                    AppEui::from([0x0; 8]),
+                    DevEui::from([0x0; 8]),
+                    AppKey::from(get_key()),
+                ),
+                &mut buf,
+                0,
+            )
+            .unwrap();
         // Confirm that the join request occurs on our subband
         assert!(
             tx_config.rf.frequency >= 903_900_000,
@@ -390,7 +406,13 @@ mod test {
         let len = handle_join_request::<0>(Some(uplink), tx_config.rf, &mut rx_buf);
         buf.clear();
         buf.extend_from_slice(&rx_buf[..len]).unwrap();
-        let response = mac.handle_rx::<DefaultFactory, 255, 3>(&mut buf, &mut downlinks);
+        let response = mac.handle_rx::<DefaultFactory, 255, 3>(
+            &mut buf,
+            &mut downlinks,
+            RxQuality::new(0, 0),
+            RxWindow::Rx1,
+            0,
+        );
         if let Response::JoinSuccess = response {
         } else {
             panic!("Did not receive JoinSuccess")
@@ -401,6 +423,7 @@ mod test {
                     &mut rand::rngs::OsRng,
                     &mut buf,
                     &SendData { fport: 1, data: &[0x0; 1], confirmed: false },
+                    0,
                 )
                 .unwrap();
             // Confirm that the first data frame occurs on our subband
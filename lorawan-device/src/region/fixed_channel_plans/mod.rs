@@ -1,5 +1,4 @@
 use super::*;
-use core::marker::PhantomData;
 use lorawan::maccommands::ChannelMask;
 
 mod join_channels;
@@ -7,11 +6,15 @@ use join_channels::JoinChannels;
 
 #[cfg(feature = "region-au915")]
 mod au915;
+#[cfg(feature = "region-cn470")]
+mod cn470;
 #[cfg(feature = "region-us915")]
 mod us915;
 
 #[cfg(feature = "region-au915")]
 pub use au915::AU915;
+#[cfg(feature = "region-cn470")]
+pub use cn470::{Cn470Plan, CN470};
 #[cfg(feature = "region-us915")]
 pub use us915::US915;
 
@@ -42,23 +45,25 @@ impl From<Subband> for usize {
 pub(crate) struct FixedChannelPlan<F: FixedChannelRegion> {
     last_tx_channel: u8,
     channel_mask: ChannelMask<9>,
-    _fixed_channel_region: PhantomData<F>,
+    region: F,
     join_channels: JoinChannels,
 
     frequency_valid: fn(u32) -> bool,
 }
 
-impl<F: FixedChannelRegion> FixedChannelPlan<F> {
+impl<F: FixedChannelRegion + Default> FixedChannelPlan<F> {
     pub fn new(freq_fn: fn(u32) -> bool) -> Self {
         Self {
             last_tx_channel: Default::default(),
             channel_mask: Default::default(),
-            _fixed_channel_region: Default::default(),
+            region: Default::default(),
             join_channels: Default::default(),
             frequency_valid: freq_fn,
         }
     }
+}
 
+impl<F: FixedChannelRegion> FixedChannelPlan<F> {
     pub fn set_125k_channels(
         &self,
         channel_mask: &mut ChannelMask<9>,
@@ -96,13 +101,41 @@ impl<F: FixedChannelRegion> FixedChannelPlan<F> {
         }
         None
     }
+
+    /// Number of 125 kHz uplink channels, which are followed by the 500 kHz ones (if any).
+    fn num_125k_channels(&self) -> usize {
+        self.region.uplink_channels().len().min(64)
+    }
 }
 
-pub(crate) trait FixedChannelRegion: ChannelRegion {
-    fn uplink_channels() -> &'static [u32; 72];
-    fn downlink_channels() -> &'static [u32; 8];
-    fn get_default_rx2() -> u32;
-    fn get_rx_datarate(tx_datarate: DR, frame: &Frame, window: &Window) -> Datarate;
+pub(crate) trait FixedChannelRegion: ChannelRegion + Clone {
+    /// Uplink channels of the channel plan in use. Channels 64 and above are 500 kHz channels.
+    fn uplink_channels(&self) -> &'static [u32];
+    /// RX1 channels, uplink channel `n` being answered on downlink channel `n % len`.
+    fn downlink_channels(&self) -> &'static [u32];
+    /// Default RX2 frequency following an uplink on `uplink_channel`.
+    fn get_default_rx2(&self, uplink_channel: u8) -> u32;
+    fn get_default_rx2_datarate() -> DR {
+        DR::_8
+    }
+    /// Lowest data rate allowed for RX2.
+    fn min_rx2_datarate() -> u8 {
+        8
+    }
+    fn max_rx1_dr_offset() -> u8;
+    /// RX1 data rate for the uplink data rate and a valid RX1DROffset
+    fn get_rx1_datarate(tx_datarate: DR, rx1_dr_offset: u8) -> DR;
+    /// Channel for the next join-request, for regions which don't use the sub-band rotation of
+    /// [`JoinChannels`]. The data rate of the join-request is then the configured one.
+    fn next_join_channel<RNG: RngCore>(&mut self, _rng: &mut RNG) -> Option<u8> {
+        None
+    }
+    /// Called when a join-accept is received.
+    fn join_accepted(&mut self) {}
+    fn write_snapshot(&self, _w: &mut Writer) {}
+    fn restore_snapshot(&mut self, _r: &mut Reader<'_>) -> snapshot::Result {
+        Ok(())
+    }
 }
 
 impl<F: FixedChannelRegion> RegionHandler for FixedChannelPlan<F> {
@@ -110,6 +143,7 @@ impl<F: FixedChannelRegion> RegionHandler for FixedChannelPlan<F> {
         &mut self,
         join_accept: &DecryptedJoinAcceptPayload<T, C>,
     ) {
+        self.region.join_accepted();
         if let Some(CfList::FixedChannel(channel_mask)) = join_accept.c_f_list() {
             self.channel_mask_set(channel_mask);
         }
@@ -130,13 +164,15 @@ impl<F: FixedChannelRegion> RegionHandler for FixedChannelPlan<F> {
         ch_mask_ctl: u8,
         ch_mask: ChannelMask<2>,
     ) -> Option<()> {
+        let has_500k_channels = self.region.uplink_channels().len() > 64;
         match ch_mask_ctl {
-            0..=4 => {
+            // Banks of 16 channels
+            0..=4 if (ch_mask_ctl as usize) * 16 < self.region.uplink_channels().len() => {
                 let base_index = ch_mask_ctl as usize * 2;
                 channel_mask.set_bank(base_index, ch_mask.get_index(0));
                 channel_mask.set_bank(base_index + 1, ch_mask.get_index(1));
             }
-            5 => {
+            5 if has_500k_channels => {
                 let ch_mask: u16 =
                     ch_mask.get_index(0) as u16 | ((ch_mask.get_index(1) as u16) << 8);
                 channel_mask.set_bank(0, ((ch_mask & 0b1) * 0xFF) as u8);
@@ -152,7 +188,7 @@ impl<F: FixedChannelRegion> RegionHandler for FixedChannelPlan<F> {
             6 => {
                 self.set_125k_channels(channel_mask, true, ch_mask);
             }
-            7 => {
+            7 if has_500k_channels => {
                 self.set_125k_channels(channel_mask, false, ch_mask);
             }
             _ => {
@@ -170,7 +206,10 @@ impl<F: FixedChannelRegion> RegionHandler for FixedChannelPlan<F> {
                     Bandwidth::_500KHz => (64..=71).any(|i| channel_mask.is_enabled(i).unwrap()),
                     Bandwidth::_125KHz => {
                         // Check that at least two channels are enabled
-                        (0..64).filter(|&i| channel_mask.is_enabled(i).unwrap()).take(2).count()
+                        (0..self.num_125k_channels())
+                            .filter(|&i| channel_mask.is_enabled(i).unwrap())
+                            .take(2)
+                            .count()
                             == 2
                     }
                     _ => true,
@@ -180,23 +219,34 @@ impl<F: FixedChannelRegion> RegionHandler for FixedChannelPlan<F> {
         false
     }
 
+    fn enable_default_channels(&mut self) {
+        self.channel_mask_set(ChannelMask::default());
+    }
+
     fn get_tx_dr_and_frequency<RNG: RngCore>(
         &mut self,
         rng: &mut RNG,
         datarate: DR,
         frame: &Frame,
-    ) -> (Datarate, u32) {
+        _now_ms: u64,
+    ) -> Option<(Datarate, u32)> {
         match frame {
             Frame::Join => {
-                let channel = self.join_channels.get_next_channel(rng);
-                let dr = if channel < 64 {
-                    DR::_0
-                } else {
-                    DR::_4
+                let (channel, dr) = match self.region.next_join_channel(rng) {
+                    Some(channel) => (channel, datarate),
+                    None => {
+                        let channel = self.join_channels.get_next_channel(rng);
+                        let dr = if channel < 64 {
+                            DR::_0
+                        } else {
+                            DR::_4
+                        };
+                        (channel, dr)
+                    }
                 };
                 self.last_tx_channel = channel;
                 let data_rate = F::datarates()[dr as usize].clone().unwrap();
-                (data_rate, F::uplink_channels()[channel as usize])
+                Some((data_rate, self.region.uplink_channels()[channel as usize]))
             }
             Frame::Data => {
                 // The join bias gets reset after receiving CFList in Join Frame
@@ -218,40 +268,70 @@ impl<F: FixedChannelRegion> RegionHandler for FixedChannelPlan<F> {
                 } else {
                     // For the data frame, the datarate impacts which channel sets we can choose
                     // from. If the datarate bandwidth is 500 kHz, we must use
-                    // channels 64..=71. Else, we must use 0-63
-                    let datarate = F::datarates()[datarate as usize].clone().unwrap();
-                    if datarate.bandwidth == Bandwidth::_500KHz {
-                        let mut channel = (rng.next_u32() & 0b111) as u8;
-                        // keep selecting a random channel until we find one that is enabled
-                        while !self.channel_mask.is_enabled((channel + 64).into()).unwrap() {
-                            channel = (rng.next_u32() & 0b111) as u8;
-                        }
-                        (datarate, 64 + channel)
+                    // channels 64..=71. Else, we must use the 125 kHz ones.
+                    let datarate = F::datarates()[datarate as usize].clone()?;
+                    let channels = if datarate.bandwidth == Bandwidth::_500KHz {
+                        64..self.region.uplink_channels().len()
                     } else {
-                        let mut channel = (rng.next_u32() & 0b111111) as u8;
-                        // keep selecting a random channel until we find one that is enabled
-                        while !self.channel_mask.is_enabled(channel.into()).unwrap() {
-                            channel = (rng.next_u32() & 0b111111) as u8;
-                        }
-                        (datarate, channel)
+                        0..self.num_125k_channels()
+                    };
+                    // None of the channels of the data rate's bandwidth may be enabled
+                    if !channels.clone().any(|i| self.channel_mask.is_enabled(i).unwrap()) {
+                        return None;
                     }
+                    let mut channel = channels.start + rng.next_u32() as usize % channels.len();
+                    // keep selecting a random channel until we find one that is enabled
+                    while !self.channel_mask.is_enabled(channel).unwrap() {
+                        channel = channels.start + rng.next_u32() as usize % channels.len();
+                    }
+                    (datarate, channel as u8)
                 };
                 self.last_tx_channel = channel;
-                (data_rate, F::uplink_channels()[channel as usize])
+                Some((data_rate, self.region.uplink_channels()[channel as usize]))
             }
         }
     }
 
+    fn get_last_tx_channel(&self) -> u8 {
+        self.last_tx_channel
+    }
+
+    fn get_last_tx_frequency(&self) -> u32 {
+        self.region.uplink_channels()[self.last_tx_channel as usize]
+    }
+
     fn get_rx_frequency(&self, _frame: &Frame, window: &Window) -> u32 {
-        let channel = self.last_tx_channel % 8;
         match window {
-            Window::_1 => F::downlink_channels()[channel as usize],
-            Window::_2 => F::get_default_rx2(),
+            Window::_1 => {
+                let downlink_channels = self.region.downlink_channels();
+                downlink_channels[self.last_tx_channel as usize % downlink_channels.len()]
+            }
+            Window::_2 => self.region.get_default_rx2(self.last_tx_channel),
         }
     }
 
-    fn get_rx_datarate(&self, tx_datarate: DR, frame: &Frame, window: &Window) -> Datarate {
-        F::get_rx_datarate(tx_datarate, frame, window)
+    fn datarates(&self) -> &'static [Option<Datarate>; NUM_DATARATES as usize] {
+        F::datarates()
+    }
+
+    fn get_rx1_datarate(&self, tx_datarate: DR, rx1_dr_offset: u8) -> DR {
+        F::get_rx1_datarate(tx_datarate, rx1_dr_offset)
+    }
+
+    fn get_default_rx2_datarate(&self) -> DR {
+        F::get_default_rx2_datarate()
+    }
+
+    fn rx1_dr_offset_valid(&self, rx1_dr_offset: u8) -> bool {
+        rx1_dr_offset <= F::max_rx1_dr_offset()
+    }
+
+    fn check_rx2_data_rate(&self, data_rate: u8) -> Option<DR> {
+        // Downlink data rates start at DR8 in US915 and AU915
+        if data_rate < F::min_rx2_datarate() {
+            return None;
+        }
+        self.check_data_rate(data_rate)
     }
 
     fn check_tx_power(&self, tx_power: u8) -> Option<u8> {
@@ -262,6 +342,23 @@ impl<F: FixedChannelRegion> RegionHandler for FixedChannelPlan<F> {
         (self.frequency_valid)(freq)
     }
 
+    fn supports_tx_param_setup(&self) -> bool {
+        F::supports_tx_param_setup()
+    }
+
+    fn max_eirp_at(&self, frequency: u32) -> Option<u8> {
+        F::max_eirp_at(frequency)
+    }
+
+    fn listen_before_talk(&self) -> Option<Lbt> {
+        F::listen_before_talk()
+    }
+
+    #[cfg(feature = "class-b")]
+    fn beacon_channel(&self) -> Option<BeaconChannel> {
+        F::beacon_channel()
+    }
+
     fn has_fixed_channel_plan(&self) -> bool {
         true
     }
@@ -269,4 +366,28 @@ impl<F: FixedChannelRegion> RegionHandler for FixedChannelPlan<F> {
     fn handle_new_channel(&mut self, _: u8, _: u32, _: Option<DataRateRange>) -> (bool, bool) {
         unreachable!()
     }
+
+    fn handle_dl_channel(&mut self, _: u8, _: u32) -> (bool, bool) {
+        unreachable!()
+    }
+
+    fn write_snapshot(&self, w: &mut Writer) {
+        w.bytes(self.channel_mask.as_ref());
+        w.u8(self.last_tx_channel);
+        self.region.write_snapshot(w);
+    }
+
+    fn restore_snapshot(&mut self, r: &mut Reader<'_>) -> snapshot::Result {
+        let channel_mask = ChannelMask::from(r.bytes::<9>()?);
+        let last_tx_channel = r.u8()?;
+        let mut region = self.region.clone();
+        region.restore_snapshot(r)?;
+        if last_tx_channel as usize >= region.uplink_channels().len() {
+            return Err(SnapshotError::Invalid);
+        }
+        self.channel_mask = channel_mask;
+        self.last_tx_channel = last_tx_channel;
+        self.region = region;
+        Ok(())
+    }
 }
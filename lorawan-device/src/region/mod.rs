@@ -6,10 +6,13 @@ use lorawan::{
 };
 use rand_core::RngCore;
 
-use crate::mac::{Frame, Window};
+use crate::mac::snapshot::{self, Reader, SnapshotError, Writer};
+use crate::mac::{Frame, RxSettings, Window};
 pub(crate) mod constants;
+mod duty_cycle;
 pub(crate) use crate::radio::*;
 use constants::*;
+use duty_cycle::DutyCycle;
 // For backward compatibility
 pub use lorawan::types::DR;
 
@@ -21,7 +24,11 @@ pub use lorawan::types::DR;
     feature = "region-eu433",
     feature = "region-eu868",
     feature = "region-in865",
+    feature = "region-kr920",
+    feature = "region-ru864",
+    feature = "region-cn779",
     feature = "region-au915",
+    feature = "region-cn470",
     feature = "region-us915"
 )))]
 compile_error!("You must enable at least one region! eg: `region-eu868`, `region-us915`...");
@@ -33,7 +40,10 @@ compile_error!("You must enable at least one region! eg: `region-eu868`, `region
     feature = "region-as923-4",
     feature = "region-eu433",
     feature = "region-eu868",
-    feature = "region-in865"
+    feature = "region-in865",
+    feature = "region-kr920",
+    feature = "region-ru864",
+    feature = "region-cn779"
 ))]
 mod dynamic_channel_plans;
 #[cfg(feature = "region-as923-1")]
@@ -44,14 +54,20 @@ pub(crate) use dynamic_channel_plans::AS923_2;
 pub(crate) use dynamic_channel_plans::AS923_3;
 #[cfg(feature = "region-as923-4")]
 pub(crate) use dynamic_channel_plans::AS923_4;
+#[cfg(feature = "region-cn779")]
+pub(crate) use dynamic_channel_plans::CN779;
 #[cfg(feature = "region-eu433")]
 pub(crate) use dynamic_channel_plans::EU433;
 #[cfg(feature = "region-eu868")]
 pub(crate) use dynamic_channel_plans::EU868;
 #[cfg(feature = "region-in865")]
 pub(crate) use dynamic_channel_plans::IN865;
+#[cfg(feature = "region-kr920")]
+pub(crate) use dynamic_channel_plans::KR920;
+#[cfg(feature = "region-ru864")]
+pub(crate) use dynamic_channel_plans::RU864;
 
-#[cfg(any(feature = "region-us915", feature = "region-au915"))]
+#[cfg(any(feature = "region-us915", feature = "region-au915", feature = "region-cn470"))]
 mod fixed_channel_plans;
 #[cfg(any(feature = "region-us915", feature = "region-au915"))]
 pub use fixed_channel_plans::Subband;
@@ -59,6 +75,8 @@ pub use fixed_channel_plans::Subband;
 pub use fixed_channel_plans::AU915;
 #[cfg(feature = "region-us915")]
 pub use fixed_channel_plans::US915;
+#[cfg(feature = "region-cn470")]
+pub use fixed_channel_plans::{Cn470Plan, CN470};
 
 pub(crate) trait ChannelRegion {
     fn datarates() -> &'static [Option<Datarate>; NUM_DATARATES as usize];
@@ -80,6 +98,77 @@ pub(crate) trait ChannelRegion {
     }
 
     fn tx_power_adjust(pw: u8) -> Option<u8>;
+
+    /// Whether the region requires the device to implement `TXParamSetupReq`
+    fn supports_tx_param_setup() -> bool {
+        false
+    }
+
+    /// Maximum EIRP for frequencies which are more restricted than the rest of the band
+    fn max_eirp_at(_frequency: u32) -> Option<u8> {
+        None
+    }
+
+    /// Listen-before-talk required before every transmission
+    fn listen_before_talk() -> Option<Lbt> {
+        None
+    }
+
+    /// Beacon channel of regions supporting Class B
+    #[cfg(feature = "class-b")]
+    fn beacon_channel() -> Option<BeaconChannel> {
+        None
+    }
+}
+
+/// Beacon channel of a region, which is also the default ping slot channel.
+#[cfg(feature = "class-b")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BeaconChannel {
+    /// Frequency of the beacons, or of the first channel when hopping
+    frequency: u32,
+    /// Number of channels, 600 kHz apart, beacons and ping slots hop across
+    hopping_channels: u8,
+    pub data_rate: DR,
+    /// Size of the RFU fields before the time and after the gateway specific part
+    pub rfu: (usize, usize),
+}
+
+#[cfg(feature = "class-b")]
+impl BeaconChannel {
+    #[cfg(any(
+        feature = "region-as923-1",
+        feature = "region-as923-2",
+        feature = "region-as923-3",
+        feature = "region-as923-4",
+        feature = "region-au915",
+        feature = "region-cn779",
+        feature = "region-eu433",
+        feature = "region-eu868",
+        feature = "region-kr920",
+        feature = "region-ru864",
+        feature = "region-us915"
+    ))]
+    pub(crate) const fn new(frequency: u32, data_rate: DR, rfu: (usize, usize)) -> Self {
+        Self { frequency, hopping_channels: 1, data_rate, rfu }
+    }
+
+    #[cfg(any(feature = "region-us915", feature = "region-au915"))]
+    pub(crate) const fn hopping(mut self, channels: u8) -> Self {
+        self.hopping_channels = channels;
+        self
+    }
+
+    /// Length of the beacon frame
+    pub fn len(&self) -> usize {
+        self.rfu.0 + 4 + 2 + 7 + self.rfu.1 + 2
+    }
+
+    /// Frequency of the channel selected by `index`, which is taken modulo the number of
+    /// hopping channels.
+    pub fn frequency(&self, index: u32) -> u32 {
+        self.frequency + (index % self.hopping_channels as u32) * 600_000
+    }
 }
 
 #[derive(Clone)]
@@ -89,11 +178,23 @@ pub(crate) trait ChannelRegion {
 /// fine-tuning, like for example [`US915`] or [`AU915`].
 pub struct Configuration {
     state: State,
+    tx_params: TxParams,
+    duty_cycle: DutyCycle,
+}
+
+/// Transmit parameters set by the network with `TXParamSetupReq`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TxParams {
+    pub uplink_dwell_time: bool,
+    pub downlink_dwell_time: bool,
+    /// Maximum EIRP in dBm, the region default applies if unset
+    pub max_eirp: Option<u8>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-/// Regions supported by this crate: AS923_1, AS923_2, AS923_3, AS923_4, AU915, EU868, EU433, IN865, US915.
+/// Regions supported by this crate: AS923_1, AS923_2, AS923_3, AS923_4, AU915, CN470, CN779, EU868, EU433,
+/// IN865, KR920, RU864, US915.
 ///
 /// Each region is individually feature-gated (eg: `region-eu868`), however, by default, all regions are enabled.
 ///
@@ -114,6 +215,14 @@ pub enum Region {
     EU433,
     #[cfg(feature = "region-in865")]
     IN865,
+    #[cfg(feature = "region-kr920")]
+    KR920,
+    #[cfg(feature = "region-ru864")]
+    RU864,
+    #[cfg(feature = "region-cn779")]
+    CN779,
+    #[cfg(feature = "region-cn470")]
+    CN470,
     #[cfg(feature = "region-us915")]
     US915,
 }
@@ -136,6 +245,14 @@ enum State {
     EU433(EU433),
     #[cfg(feature = "region-in865")]
     IN865(IN865),
+    #[cfg(feature = "region-kr920")]
+    KR920(KR920),
+    #[cfg(feature = "region-ru864")]
+    RU864(RU864),
+    #[cfg(feature = "region-cn779")]
+    CN779(CN779),
+    #[cfg(feature = "region-cn470")]
+    CN470(CN470),
     #[cfg(feature = "region-us915")]
     US915(US915),
 }
@@ -159,6 +276,14 @@ impl State {
             Region::EU433 => State::EU433(EU433::new_eu433()),
             #[cfg(feature = "region-in865")]
             Region::IN865 => State::IN865(IN865::new_in865()),
+            #[cfg(feature = "region-kr920")]
+            Region::KR920 => State::KR920(KR920::new_kr920()),
+            #[cfg(feature = "region-ru864")]
+            Region::RU864 => State::RU864(RU864::new_ru864()),
+            #[cfg(feature = "region-cn779")]
+            Region::CN779 => State::CN779(CN779::new_cn779()),
+            #[cfg(feature = "region-cn470")]
+            Region::CN470 => State::CN470(CN470::default()),
             #[cfg(feature = "region-us915")]
             Region::US915 => State::US915(US915::default()),
         }
@@ -183,10 +308,50 @@ impl State {
             Self::EU868(_) => Region::EU868,
             #[cfg(feature = "region-in865")]
             Self::IN865(_) => Region::IN865,
+            #[cfg(feature = "region-kr920")]
+            Self::KR920(_) => Region::KR920,
+            #[cfg(feature = "region-ru864")]
+            Self::RU864(_) => Region::RU864,
+            #[cfg(feature = "region-cn779")]
+            Self::CN779(_) => Region::CN779,
+            #[cfg(feature = "region-cn470")]
+            Self::CN470(_) => Region::CN470,
             #[cfg(feature = "region-us915")]
             Self::US915(_) => Region::US915,
         }
     }
+
+    /// Identifies the region in snapshots, independently of the enabled region features.
+    fn snapshot_id(&self) -> u8 {
+        match self {
+            #[cfg(feature = "region-as923-1")]
+            Self::AS923_1(_) => 1,
+            #[cfg(feature = "region-as923-2")]
+            Self::AS923_2(_) => 2,
+            #[cfg(feature = "region-as923-3")]
+            Self::AS923_3(_) => 3,
+            #[cfg(feature = "region-as923-4")]
+            Self::AS923_4(_) => 4,
+            #[cfg(feature = "region-au915")]
+            Self::AU915(_) => 5,
+            #[cfg(feature = "region-eu433")]
+            Self::EU433(_) => 6,
+            #[cfg(feature = "region-eu868")]
+            Self::EU868(_) => 7,
+            #[cfg(feature = "region-in865")]
+            Self::IN865(_) => 8,
+            #[cfg(feature = "region-us915")]
+            Self::US915(_) => 9,
+            #[cfg(feature = "region-kr920")]
+            Self::KR920(_) => 10,
+            #[cfg(feature = "region-ru864")]
+            Self::RU864(_) => 12,
+            #[cfg(feature = "region-cn779")]
+            Self::CN779(_) => 13,
+            #[cfg(feature = "region-cn470")]
+            Self::CN470(_) => 11,
+        }
+    }
 }
 
 /// This datarate type is used internally for defining [`Bandwidth`]/[`SpreadingFactor`] per
@@ -217,6 +382,14 @@ macro_rules! mut_region_dispatch {
         State::EU433(state) => state.$t(),
         #[cfg(feature = "region-in865")]
         State::IN865(state) => state.$t(),
+        #[cfg(feature = "region-kr920")]
+        State::KR920(state) => state.$t(),
+        #[cfg(feature = "region-ru864")]
+        State::RU864(state) => state.$t(),
+        #[cfg(feature = "region-cn779")]
+        State::CN779(state) => state.$t(),
+        #[cfg(feature = "region-cn470")]
+        State::CN470(state) => state.0.$t(),
         #[cfg(feature = "region-us915")]
         State::US915(state) => state.0.$t(),
     }
@@ -239,6 +412,14 @@ macro_rules! mut_region_dispatch {
         State::EU433(state) => state.$t($($arg)*),
         #[cfg(feature = "region-in865")]
         State::IN865(state) => state.$t($($arg)*),
+        #[cfg(feature = "region-kr920")]
+        State::KR920(state) => state.$t($($arg)*),
+        #[cfg(feature = "region-ru864")]
+        State::RU864(state) => state.$t($($arg)*),
+        #[cfg(feature = "region-cn779")]
+        State::CN779(state) => state.$t($($arg)*),
+        #[cfg(feature = "region-cn470")]
+        State::CN470(state) => state.0.$t($($arg)*),
         #[cfg(feature = "region-us915")]
         State::US915(state) => state.0.$t($($arg)*),
     }
@@ -264,6 +445,14 @@ macro_rules! region_dispatch {
         State::EU433(state) => state.$t(),
         #[cfg(feature = "region-in865")]
         State::IN865(state) => state.$t(),
+        #[cfg(feature = "region-kr920")]
+        State::KR920(state) => state.$t(),
+        #[cfg(feature = "region-ru864")]
+        State::RU864(state) => state.$t(),
+        #[cfg(feature = "region-cn779")]
+        State::CN779(state) => state.$t(),
+        #[cfg(feature = "region-cn470")]
+        State::CN470(state) => state.0.$t(),
         #[cfg(feature = "region-us915")]
         State::US915(state) => state.0.$t(),
     }
@@ -286,6 +475,14 @@ macro_rules! region_dispatch {
         State::EU433(state) => state.$t($($arg)*),
         #[cfg(feature = "region-in865")]
         State::IN865(state) => state.$t($($arg)*),
+        #[cfg(feature = "region-kr920")]
+        State::KR920(state) => state.$t($($arg)*),
+        #[cfg(feature = "region-ru864")]
+        State::RU864(state) => state.$t($($arg)*),
+        #[cfg(feature = "region-cn779")]
+        State::CN779(state) => state.$t($($arg)*),
+        #[cfg(feature = "region-cn470")]
+        State::CN470(state) => state.0.$t($($arg)*),
         #[cfg(feature = "region-us915")]
         State::US915(state) => state.0.$t($($arg)*),
     }
@@ -311,6 +508,14 @@ macro_rules! region_static_dispatch {
         State::EU433(_) => dynamic_channel_plans::EU433::$t(),
         #[cfg(feature = "region-in865")]
         State::IN865(_) => dynamic_channel_plans::IN865::$t(),
+        #[cfg(feature = "region-kr920")]
+        State::KR920(_) => dynamic_channel_plans::KR920::$t(),
+        #[cfg(feature = "region-ru864")]
+        State::RU864(_) => dynamic_channel_plans::RU864::$t(),
+        #[cfg(feature = "region-cn779")]
+        State::CN779(_) => dynamic_channel_plans::CN779::$t(),
+        #[cfg(feature = "region-cn470")]
+        State::CN470(_) => fixed_channel_plans::CN470::$t(),
         #[cfg(feature = "region-us915")]
         State::US915(_) => fixed_channel_plans::US915::$t(),
     }
@@ -333,6 +538,14 @@ macro_rules! region_static_dispatch {
         State::EU433(_) => dynamic_channel_plans::EU433::$t($($arg)*),
         #[cfg(feature = "region-in865")]
         State::IN865(_) => dynamic_channel_plans::IN865::$t($($arg)*),
+        #[cfg(feature = "region-kr920")]
+        State::KR920(_) => dynamic_channel_plans::KR920::$t($($arg)*),
+        #[cfg(feature = "region-ru864")]
+        State::RU864(_) => dynamic_channel_plans::RU864::$t($($arg)*),
+        #[cfg(feature = "region-cn779")]
+        State::CN779(_) => dynamic_channel_plans::CN779::$t($($arg)*),
+        #[cfg(feature = "region-cn470")]
+        State::CN470(_) => fixed_channel_plans::CN470::$t($($arg)*),
         #[cfg(feature = "region-us915")]
         State::US915(_) => fixed_channel_plans::US915::$t($($arg)*),
     }
@@ -345,7 +558,13 @@ impl Configuration {
     }
 
     fn with_state(state: State) -> Configuration {
-        Configuration { state }
+        Configuration { state, tx_params: TxParams::default(), duty_cycle: DutyCycle::default() }
+    }
+
+    /// Maximum MAC payload length for the data rate, taking the uplink dwell time currently
+    /// configured by the network into account.
+    pub fn max_payload_length(&self, datarate: DR, repeater_compatible: bool) -> u8 {
+        self.get_max_payload_length(datarate, repeater_compatible, self.tx_params.uplink_dwell_time)
     }
 
     pub fn get_max_payload_length(
@@ -363,16 +582,21 @@ impl Configuration {
         )
     }
 
+    /// Create TX configuration for the frame, `None` if no enabled channel supports the data rate.
     pub(crate) fn create_tx_config<RNG: RngCore>(
         &mut self,
         rng: &mut RNG,
         datarate: DR,
         frame: &Frame,
-    ) -> TxConfig {
-        let (dr, frequency) = self.get_tx_dr_and_frequency(rng, datarate, frame);
-        TxConfig {
-            // We can do this safely, as default output power will be positive
-            pw: self.check_tx_power(0).unwrap().unwrap() as i8,
+        now_ms: u64,
+    ) -> Option<TxConfig> {
+        let (dr, frequency) = self.get_tx_dr_and_frequency(rng, datarate, frame, now_ms)?;
+        // We can do this safely, as default output power will be positive
+        let pw = self.check_tx_power(0).unwrap().unwrap();
+        let pw = region_dispatch!(self, max_eirp_at, frequency).map_or(pw, |max| pw.min(max));
+        Some(TxConfig {
+            pw: pw as i8,
+            lbt: region_dispatch!(self, listen_before_talk),
             rf: RfConfig {
                 frequency,
                 bb: BaseBandModulationParams::new(
@@ -381,15 +605,100 @@ impl Configuration {
                     self.get_coding_rate(),
                 ),
             },
+        })
+    }
+
+    /// Create TX configuration for a repeated data frame. Repetitions hop to a channel different
+    /// from the previous transmission, unless no other channel happens to be available.
+    pub(crate) fn create_retransmit_tx_config<RNG: RngCore>(
+        &mut self,
+        rng: &mut RNG,
+        datarate: DR,
+        now_ms: u64,
+    ) -> Option<TxConfig> {
+        let previous = self.get_last_tx_frequency();
+        let mut tx_config = self.create_tx_config(rng, datarate, &Frame::Data, now_ms)?;
+        for _ in 0..MAX_RETRANSMIT_CHANNEL_ATTEMPTS {
+            if tx_config.rf.frequency != previous {
+                break;
+            }
+            tx_config = self.create_tx_config(rng, datarate, &Frame::Data, now_ms)?;
         }
+        Some(tx_config)
+    }
+
+    /// Milliseconds until duty-cycle limits allow transmitting the frame at `datarate` on at least
+    /// one of the enabled channels supporting it, 0 if it may be transmitted now.
+    pub(crate) fn time_until_available(&self, frame: &Frame, datarate: DR, now_ms: u64) -> u64 {
+        let channel = region_dispatch!(self, time_until_channel_available, frame, datarate, now_ms);
+        channel.max(self.duty_cycle.time_until_available(frame, now_ms))
+    }
+
+    /// Account the airtime of a frame of `len` bytes transmitted with the TX configuration.
+    pub(crate) fn register_transmission(
+        &mut self,
+        frame: &Frame,
+        tx_config: &TxConfig,
+        len: usize,
+        now_ms: u64,
+    ) {
+        let airtime_us = tx_config.rf.bb.time_on_air_us(Some(8), true, len as u8);
+        let airtime_ms = airtime_us.div_ceil(1000) as u64;
+        mut_region_dispatch!(
+            self,
+            register_transmission,
+            tx_config.rf.frequency,
+            now_ms,
+            airtime_ms
+        );
+        self.duty_cycle.register_transmission(frame, now_ms, airtime_ms);
+    }
+
+    /// Set the aggregated duty cycle to 1 / 2^`max_duty_cycle`, 0 removes the limit.
+    pub(crate) fn set_max_duty_cycle(&mut self, max_duty_cycle: u8) {
+        self.duty_cycle.set_max_duty_cycle(max_duty_cycle);
+    }
+
+    pub(crate) fn reset_join_backoff(&mut self) {
+        self.duty_cycle.reset_join_backoff();
+    }
+
+    fn get_last_tx_frequency(&self) -> u32 {
+        region_dispatch!(self, get_last_tx_frequency)
+    }
+
+    /// Index of the channel used for the previous transmission
+    pub(crate) fn get_last_tx_channel(&self) -> u8 {
+        region_dispatch!(self, get_last_tx_channel)
     }
 
     pub(crate) fn check_data_rate(&self, data_rate: u8) -> Option<DR> {
-        region_dispatch!(self, check_data_rate, data_rate)
+        let dr = region_dispatch!(self, check_data_rate, data_rate)?;
+        // Data rates without payload capacity can't be used while uplink dwell time applies
+        if self.tx_params.uplink_dwell_time && self.get_max_payload_length(dr, false, true) == 0 {
+            return None;
+        }
+        Some(dr)
     }
 
     pub(crate) fn check_tx_power(&self, tx_power: u8) -> Option<Option<u8>> {
-        region_dispatch!(self, check_tx_power, tx_power).map(Some)
+        let power = region_dispatch!(self, check_tx_power, tx_power)?;
+        Some(Some(match self.tx_params.max_eirp {
+            // TXPower is defined relative to MaxEIRP
+            Some(max_eirp) => {
+                let default_max = region_dispatch!(self, check_tx_power, 0).unwrap();
+                max_eirp.saturating_sub(default_max - power)
+            }
+            None => power,
+        }))
+    }
+
+    pub(crate) fn supports_tx_param_setup(&self) -> bool {
+        region_dispatch!(self, supports_tx_param_setup)
+    }
+
+    pub(crate) fn set_tx_params(&mut self, tx_params: TxParams) {
+        self.tx_params = tx_params;
     }
 
     fn get_tx_dr_and_frequency<RNG: RngCore>(
@@ -397,14 +706,25 @@ impl Configuration {
         rng: &mut RNG,
         datarate: DR,
         frame: &Frame,
-    ) -> (Datarate, u32) {
-        mut_region_dispatch!(self, get_tx_dr_and_frequency, rng, datarate, frame)
+        now_ms: u64,
+    ) -> Option<(Datarate, u32)> {
+        mut_region_dispatch!(self, get_tx_dr_and_frequency, rng, datarate, frame, now_ms)
     }
 
-    pub(crate) fn get_rx_config(&self, datarate: DR, frame: &Frame, window: &Window) -> RfConfig {
-        let dr = self.get_rx_datarate(datarate, frame, window);
+    pub(crate) fn get_rx_config(
+        &self,
+        datarate: DR,
+        rx_settings: &RxSettings,
+        frame: &Frame,
+        window: &Window,
+    ) -> RfConfig {
+        let dr = self.get_rx_datarate(datarate, rx_settings, window);
+        let frequency = match (window, rx_settings.rx2_frequency) {
+            (Window::_2, Some(frequency)) => frequency,
+            _ => self.get_rx_frequency(frame, window),
+        };
         RfConfig {
-            frequency: self.get_rx_frequency(frame, window),
+            frequency,
             bb: BaseBandModulationParams::new(
                 dr.spreading_factor,
                 dr.bandwidth,
@@ -445,6 +765,10 @@ impl Configuration {
         region_dispatch!(self, channel_mask_validate, channel_mask, dr)
     }
 
+    pub(crate) fn enable_default_channels(&mut self) {
+        mut_region_dispatch!(self, enable_default_channels)
+    }
+
     pub(crate) fn get_rx_frequency(&self, frame: &Frame, window: &Window) -> u32 {
         region_dispatch!(self, get_rx_frequency, frame, window)
     }
@@ -453,17 +777,90 @@ impl Configuration {
         region_dispatch!(self, get_default_datarate)
     }
 
-    pub(crate) fn get_rx_datarate(&self, datarate: DR, frame: &Frame, window: &Window) -> Datarate {
-        region_dispatch!(self, get_rx_datarate, datarate, frame, window)
+    /// Lowest (most robust) uplink data rate supported by the region.
+    pub(crate) fn get_min_datarate(&self) -> DR {
+        // Every region defines at least one uplink data rate
+        (0..NUM_DATARATES).find_map(|dr| self.check_data_rate(dr)).unwrap()
+    }
+
+    /// Next lower uplink data rate supported by the region, if any.
+    pub(crate) fn get_lower_datarate(&self, datarate: DR) -> Option<DR> {
+        (0..datarate as u8).rev().find_map(|dr| self.check_data_rate(dr))
+    }
+
+    pub(crate) fn get_rx_datarate(
+        &self,
+        datarate: DR,
+        rx_settings: &RxSettings,
+        window: &Window,
+    ) -> Datarate {
+        let dr = self.get_rx_dr(datarate, rx_settings, window);
+        region_dispatch!(self, datarates)[dr as usize].clone().unwrap()
+    }
+
+    /// Data rate of the receive window following an uplink at `datarate`.
+    pub(crate) fn get_rx_dr(&self, datarate: DR, rx_settings: &RxSettings, window: &Window) -> DR {
+        let mut dr = match window {
+            Window::_1 => {
+                region_dispatch!(self, get_rx1_datarate, datarate, rx_settings.rx1_dr_offset)
+            }
+            Window::_2 => rx_settings
+                .rx2_data_rate
+                .unwrap_or_else(|| region_dispatch!(self, get_default_rx2_datarate)),
+        };
+        // Skip data rates which can't be used with downlink dwell time
+        if self.tx_params.downlink_dwell_time {
+            while self.get_max_payload_length(dr, false, true) == 0 {
+                match DR::try_from(dr as u8 + 1) {
+                    Ok(next) => dr = next,
+                    Err(_) => break,
+                }
+            }
+        }
+        dr
+    }
+
+    pub(crate) fn rx1_dr_offset_valid(&self, rx1_dr_offset: u8) -> bool {
+        region_dispatch!(self, rx1_dr_offset_valid, rx1_dr_offset)
+    }
+
+    pub(crate) fn check_rx2_data_rate(&self, data_rate: u8) -> Option<DR> {
+        let dr = region_dispatch!(self, check_rx2_data_rate, data_rate)?;
+        if self.tx_params.downlink_dwell_time && self.get_max_payload_length(dr, false, true) == 0 {
+            return None;
+        }
+        Some(dr)
     }
 
     // Unicast: The RXC parameters are identical to the RX2 parameters, and they use the same
     // channel and data rate. Modifying the RX2 parameters using the appropriate MAC
     // commands also modifies the RXC parameters.
     #[cfg(feature = "class-c")]
-    pub(crate) fn get_rxc_config(&self, datarate: DR) -> RfConfig {
-        let dr = self.get_rx_datarate(datarate, &Frame::Data, &Window::_2);
-        let frequency = self.get_rx_frequency(&Frame::Data, &Window::_2);
+    pub(crate) fn get_rxc_config(&self, rx_settings: &RxSettings) -> RfConfig {
+        // RX2 data rate doesn't depend on the uplink data rate
+        let dr = self.get_rx_datarate(DR::_0, rx_settings, &Window::_2);
+        let frequency = rx_settings
+            .rx2_frequency
+            .unwrap_or_else(|| self.get_rx_frequency(&Frame::Data, &Window::_2));
+        RfConfig {
+            frequency,
+            bb: BaseBandModulationParams::new(
+                dr.spreading_factor,
+                dr.bandwidth,
+                self.get_coding_rate(),
+            ),
+        }
+    }
+
+    #[cfg(feature = "class-b")]
+    pub(crate) fn beacon_channel(&self) -> Option<BeaconChannel> {
+        region_dispatch!(self, beacon_channel)
+    }
+
+    /// RF configuration of beacons and ping slots.
+    #[cfg(feature = "class-b")]
+    pub(crate) fn get_class_b_rf_config(&self, frequency: u32, datarate: DR) -> RfConfig {
+        let dr = region_dispatch!(self, datarates)[datarate as usize].clone().unwrap();
         RfConfig {
             frequency,
             bb: BaseBandModulationParams::new(
@@ -499,6 +896,32 @@ impl Configuration {
     ) -> (bool, bool) {
         mut_region_dispatch!(self, handle_new_channel, index, freq, data_rates)
     }
+
+    pub(crate) fn handle_dl_channel(&mut self, index: u8, freq: u32) -> (bool, bool) {
+        mut_region_dispatch!(self, handle_dl_channel, index, freq)
+    }
+
+    pub(crate) fn write_snapshot(&self, w: &mut Writer) {
+        w.u8(self.state.snapshot_id());
+        w.bool(self.tx_params.uplink_dwell_time);
+        w.bool(self.tx_params.downlink_dwell_time);
+        w.option(self.tx_params.max_eirp, Writer::u8);
+        w.u8(self.duty_cycle.max_duty_cycle());
+        region_dispatch!(self, write_snapshot, w)
+    }
+
+    pub(crate) fn restore_snapshot(&mut self, r: &mut Reader<'_>) -> snapshot::Result {
+        if r.u8()? != self.state.snapshot_id() {
+            return Err(SnapshotError::RegionMismatch);
+        }
+        self.tx_params = TxParams {
+            uplink_dwell_time: r.bool()?,
+            downlink_dwell_time: r.bool()?,
+            max_eirp: r.option(Reader::u8)?,
+        };
+        self.duty_cycle.set_max_duty_cycle(r.u8()?);
+        mut_region_dispatch!(self, restore_snapshot, r)
+    }
 }
 
 macro_rules! from_region {
@@ -521,12 +944,20 @@ from_region!(AS923_3);
 from_region!(AS923_4);
 #[cfg(feature = "region-in865")]
 from_region!(IN865);
+#[cfg(feature = "region-kr920")]
+from_region!(KR920);
+#[cfg(feature = "region-ru864")]
+from_region!(RU864);
+#[cfg(feature = "region-cn779")]
+from_region!(CN779);
 #[cfg(feature = "region-au915")]
 from_region!(AU915);
 #[cfg(feature = "region-eu868")]
 from_region!(EU868);
 #[cfg(feature = "region-eu433")]
 from_region!(EU433);
+#[cfg(feature = "region-cn470")]
+from_region!(CN470);
 #[cfg(feature = "region-us915")]
 from_region!(US915);
 
@@ -551,6 +982,10 @@ pub(crate) trait RegionHandler {
 
     fn channel_mask_validate(&self, channel_mask: &ChannelMask<9>, dr: Option<DR>) -> bool;
 
+    /// Re-enable the default uplink channels of the region, used as the
+    /// last step of ADR backoff.
+    fn enable_default_channels(&mut self);
+
     fn handle_new_channel(
         &mut self,
         index: u8,
@@ -558,6 +993,10 @@ pub(crate) trait RegionHandler {
         data_rates: Option<DataRateRange>,
     ) -> (bool, bool);
 
+    /// Set the RX1 downlink frequency of an existing channel. Returns whether the frequency is
+    /// valid and whether the uplink channel exists.
+    fn handle_dl_channel(&mut self, index: u8, freq: u32) -> (bool, bool);
+
     fn get_default_datarate(&self) -> DR {
         DR::_0
     }
@@ -566,10 +1005,29 @@ pub(crate) trait RegionHandler {
         rng: &mut RNG,
         datarate: DR,
         frame: &Frame,
-    ) -> (Datarate, u32);
+        now_ms: u64,
+    ) -> Option<(Datarate, u32)>;
+
+    /// Milliseconds until a channel usable for the frame at `datarate` is no longer blocked by
+    /// regional duty-cycle limits. Channel selection skips blocked channels.
+    fn time_until_channel_available(&self, _frame: &Frame, _datarate: DR, _now_ms: u64) -> u64 {
+        0
+    }
+    fn register_transmission(&mut self, _frequency: u32, _now_ms: u64, _airtime_ms: u64) {}
 
+    /// Frequency of the channel used for the previous transmission
+    fn get_last_tx_frequency(&self) -> u32;
+    /// Index of the channel used for the previous transmission
+    fn get_last_tx_channel(&self) -> u8;
     fn get_rx_frequency(&self, frame: &Frame, window: &Window) -> u32;
-    fn get_rx_datarate(&self, datarate: DR, frame: &Frame, window: &Window) -> Datarate;
+    fn datarates(&self) -> &'static [Option<Datarate>; NUM_DATARATES as usize];
+    /// RX1 data rate for the uplink data rate and a valid RX1DROffset
+    fn get_rx1_datarate(&self, tx_datarate: DR, rx1_dr_offset: u8) -> DR;
+    fn get_default_rx2_datarate(&self) -> DR;
+    /// Whether RX1DROffset is supported by the region
+    fn rx1_dr_offset_valid(&self, rx1_dr_offset: u8) -> bool;
+    /// Check whether data rate may be used in RX2
+    fn check_rx2_data_rate(&self, data_rate: u8) -> Option<DR>;
     fn get_coding_rate(&self) -> CodingRate {
         DEFAULT_CODING_RATE
     }
@@ -578,9 +1036,23 @@ pub(crate) trait RegionHandler {
 
     fn frequency_valid(&self, freq: u32) -> bool;
 
+    /// Whether the network may set dwell time and MaxEIRP with `TXParamSetupReq`
+    fn supports_tx_param_setup(&self) -> bool;
+
+    fn max_eirp_at(&self, frequency: u32) -> Option<u8>;
+
+    fn listen_before_talk(&self) -> Option<Lbt>;
+
+    #[cfg(feature = "class-b")]
+    fn beacon_channel(&self) -> Option<BeaconChannel>;
+
     /// Whether region supports modifying channel plan
     /// with `NewChannelReq`/`DlSettingsReq` MAC commands
     fn has_fixed_channel_plan(&self) -> bool;
+
+    /// Write the channel plan state configured by the network to a snapshot.
+    fn write_snapshot(&self, w: &mut Writer);
+    fn restore_snapshot(&mut self, r: &mut Reader<'_>) -> snapshot::Result;
 }
 
 #[cfg(test)]
@@ -624,4 +1096,280 @@ mod tests {
         assert!(!r.frequency_valid(901_900_000));
         assert!(!r.frequency_valid(928_000_001));
     }
+
+    #[test]
+    #[cfg(feature = "region-us915")]
+    fn test_fixed_us915_rx_datarates() {
+        let r = Configuration::new(Region::US915);
+        let rx1_sf = |tx_dr, rx1_dr_offset| {
+            let rx_settings = RxSettings { rx1_dr_offset, ..Default::default() };
+            r.get_rx_datarate(tx_dr, &rx_settings, &Window::_1).spreading_factor
+        };
+        assert_eq!(rx1_sf(DR::_0, 0), SpreadingFactor::_10);
+        assert_eq!(rx1_sf(DR::_0, 3), SpreadingFactor::_12);
+        assert_eq!(rx1_sf(DR::_3, 2), SpreadingFactor::_9);
+        assert_eq!(rx1_sf(DR::_4, 1), SpreadingFactor::_7);
+        assert!(r.rx1_dr_offset_valid(3));
+        assert!(!r.rx1_dr_offset_valid(4));
+
+        // Only downlink data rates are accepted for RX2
+        assert_eq!(r.check_rx2_data_rate(2), None);
+        assert_eq!(r.check_rx2_data_rate(10), Some(DR::_10));
+        let rx_settings = RxSettings { rx2_data_rate: Some(DR::_10), ..Default::default() };
+        let dr = r.get_rx_datarate(DR::_0, &rx_settings, &Window::_2);
+        assert_eq!(dr.spreading_factor, SpreadingFactor::_10);
+        assert_eq!(dr.bandwidth, Bandwidth::_500KHz);
+    }
+
+    #[test]
+    #[cfg(feature = "region-as923-1")]
+    fn test_dynamic_as923_rx_datarates() {
+        let r = Configuration::new(Region::AS923_1);
+        let rx1_sf = |tx_dr, rx1_dr_offset| {
+            let rx_settings = RxSettings { rx1_dr_offset, ..Default::default() };
+            r.get_rx_datarate(tx_dr, &rx_settings, &Window::_1).spreading_factor
+        };
+        assert_eq!(rx1_sf(DR::_2, 1), SpreadingFactor::_11);
+        assert_eq!(rx1_sf(DR::_2, 5), SpreadingFactor::_12);
+        // Offsets 6 and 7 increase the data rate up to DR5
+        assert_eq!(rx1_sf(DR::_2, 7), SpreadingFactor::_8);
+        assert_eq!(rx1_sf(DR::_5, 6), SpreadingFactor::_7);
+        assert!(r.rx1_dr_offset_valid(7));
+
+        // RX2 defaults to DR2
+        let dr = r.get_rx_datarate(DR::_5, &RxSettings::default(), &Window::_2);
+        assert_eq!(dr.spreading_factor, SpreadingFactor::_10);
+    }
+
+    #[test]
+    #[cfg(feature = "region-as923-1")]
+    fn test_dynamic_as923_dwell_time() {
+        let mut r = Configuration::new(Region::AS923_1);
+        r.set_tx_params(TxParams {
+            uplink_dwell_time: true,
+            downlink_dwell_time: true,
+            max_eirp: Some(14),
+        });
+        assert_eq!(r.check_data_rate(1), None);
+        assert_eq!(r.get_min_datarate(), DR::_2);
+        assert_eq!(r.check_rx2_data_rate(1), None);
+        assert_eq!(r.max_payload_length(DR::_2, false), 19);
+        // TXPower is relative to MaxEIRP
+        assert_eq!(r.check_tx_power(0), Some(Some(14)));
+        assert_eq!(r.check_tx_power(1), Some(Some(12)));
+        // RX1 data rate is raised to DR2
+        let rx_settings = RxSettings { rx1_dr_offset: 2, ..Default::default() };
+        let dr = r.get_rx_datarate(DR::_2, &rx_settings, &Window::_1);
+        assert_eq!(dr.spreading_factor, SpreadingFactor::_10);
+    }
+
+    #[test]
+    #[cfg(feature = "region-kr920")]
+    fn test_dynamic_kr920() {
+        let mut r = Configuration::new(Region::KR920);
+        assert!(r.frequency_valid(920_900_000));
+        assert!(r.frequency_valid(923_300_000));
+        assert!(!r.frequency_valid(920_800_000));
+        assert_eq!(r.get_rx_frequency(&Frame::Data, &Window::_2), 921_900_000);
+        let dr = r.get_rx_datarate(DR::_5, &RxSettings::default(), &Window::_2);
+        assert_eq!(dr.spreading_factor, SpreadingFactor::_12);
+        assert_eq!(r.max_payload_length(DR::_2, false), 59);
+        assert_eq!(r.max_payload_length(DR::_5, false), 250);
+
+        let mut rng = rand::rngs::OsRng;
+        let tx_config = r.create_tx_config(&mut rng, DR::_5, &Frame::Data, 0).unwrap();
+        assert_eq!(tx_config.pw, 14);
+        assert_eq!(tx_config.lbt, Some(Lbt { threshold_dbm: -65, scan_time_us: 5_000 }));
+
+        // Frequencies below 922 MHz are limited to 10 dBm
+        let range = DataRateRange::new_range(DR::_0, DR::_5);
+        assert_eq!(r.handle_new_channel(3, 921_100_000, Some(range)), (true, true));
+        r.channel_mask_set(ChannelMask::new_from_raw(&[0b1000, 0, 0, 0, 0, 0, 0, 0, 0]));
+        let tx_config = r.create_tx_config(&mut rng, DR::_5, &Frame::Data, 0).unwrap();
+        assert_eq!(tx_config.rf.frequency, 921_100_000);
+        assert_eq!(tx_config.pw, 10);
+    }
+
+    #[test]
+    #[cfg(feature = "region-cn470")]
+    fn test_fixed_cn470_join_plans() {
+        let mut r = Configuration::new(Region::CN470);
+        let mut rng = rand::rngs::OsRng;
+        // Join-requests cycle through the uplink channels of the four plans
+        let plans = [
+            470_300_000..=509_700_000,
+            476_900_000..=503_100_000,
+            470_300_000..=479_700_000,
+            480_300_000..=489_700_000,
+        ];
+        for plan in plans.iter().cycle().take(8) {
+            let tx_config = r.create_tx_config(&mut rng, DR::_2, &Frame::Join, 0).unwrap();
+            assert!(plan.contains(&tx_config.rf.frequency));
+            assert_eq!(tx_config.rf.bb.sf, SpreadingFactor::_10);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "region-cn470")]
+    fn test_fixed_cn470_26mhz_plan() {
+        let mut cn470 = CN470::new();
+        cn470.set_channel_plan(Cn470Plan::_26MHzB);
+        let mut r: Configuration = cn470.into();
+        let mut rng = rand::rngs::OsRng;
+        // Only uplink channels 0-47 exist, channel 34 is 487.1 MHz
+        r.channel_mask_set(ChannelMask::new_from_raw(&[0, 0, 0, 0, 0b100, 0, 0xFF, 0xFF, 0xFF]));
+        for _ in 0..10 {
+            let tx_config = r.create_tx_config(&mut rng, DR::_5, &Frame::Join, 0).unwrap();
+            assert!((480_300_000..=489_700_000).contains(&tx_config.rf.frequency));
+            let tx_config = r.create_tx_config(&mut rng, DR::_5, &Frame::Data, 0).unwrap();
+            assert_eq!(tx_config.rf.frequency, 487_100_000);
+            assert_eq!(tx_config.pw, 19);
+        }
+        // Uplink channel 34 is answered on downlink channel 10
+        assert_eq!(r.get_rx_frequency(&Frame::Data, &Window::_1), 502_100_000);
+        assert_eq!(r.get_rx_frequency(&Frame::Data, &Window::_2), 502_500_000);
+        let dr = r.get_rx_datarate(DR::_5, &RxSettings::default(), &Window::_2);
+        assert_eq!(dr.spreading_factor, SpreadingFactor::_11);
+        let rx_settings = RxSettings { rx1_dr_offset: 3, ..Default::default() };
+        let dr = r.get_rx_datarate(DR::_4, &rx_settings, &Window::_1);
+        assert_eq!(dr.spreading_factor, SpreadingFactor::_11);
+        assert_eq!(r.check_rx2_data_rate(0), Some(DR::_0));
+        assert_eq!(r.max_payload_length(DR::_1, false), 31);
+
+        // LinkADRReq ChMaskCntl 0-2 address the 48 channels, 6 enables all of them
+        let mut channel_mask = r.channel_mask_get();
+        assert_eq!(r.channel_mask_update(&mut channel_mask, 3, ChannelMask::default()), None);
+        assert!(r.channel_mask_update(&mut channel_mask, 6, ChannelMask::default()).is_some());
+        assert!(r.channel_mask_validate(&channel_mask, Some(DR::_5)));
+    }
+
+    #[test]
+    #[cfg(feature = "region-ru864")]
+    fn test_dynamic_ru864() {
+        let mut r = Configuration::new(Region::RU864);
+        assert!(r.frequency_valid(864_000_000));
+        assert!(!r.frequency_valid(870_000_001));
+        assert_eq!(r.get_rx_frequency(&Frame::Data, &Window::_2), 869_100_000);
+        let dr = r.get_rx_datarate(DR::_5, &RxSettings::default(), &Window::_2);
+        assert_eq!(dr.spreading_factor, SpreadingFactor::_12);
+        assert_eq!(r.check_tx_power(7), Some(Some(2)));
+        assert_eq!(r.check_tx_power(8), None);
+
+        let mut rng = rand::rngs::OsRng;
+        let tx_config = r.create_tx_config(&mut rng, DR::_5, &Frame::Join, 0).unwrap();
+        assert!([868_900_000, 869_100_000].contains(&tx_config.rf.frequency));
+        assert_eq!(tx_config.pw, 16);
+        // Both default channels share a band with 1% duty cycle
+        r.register_transmission(&Frame::Data, &tx_config, 20, 0);
+        let airtime_ms = tx_config.rf.bb.time_on_air_us(Some(8), true, 20).div_ceil(1000) as u64;
+        assert_eq!(r.time_until_available(&Frame::Data, DR::_5, 0), airtime_ms * 100);
+    }
+
+    #[test]
+    #[cfg(feature = "region-cn779")]
+    fn test_dynamic_cn779() {
+        let mut r = Configuration::new(Region::CN779);
+        assert!(r.frequency_valid(779_000_000));
+        assert!(!r.frequency_valid(787_000_001));
+        assert_eq!(r.get_rx_frequency(&Frame::Data, &Window::_2), 786_000_000);
+        assert_eq!(r.max_payload_length(DR::_2, false), 59);
+        assert_eq!(r.check_tx_power(5), Some(Some(2)));
+        assert_eq!(r.check_tx_power(6), None);
+
+        let mut rng = rand::rngs::OsRng;
+        let tx_config = r.create_tx_config(&mut rng, DR::_5, &Frame::Data, 0).unwrap();
+        assert!((779_500_000..=779_900_000).contains(&tx_config.rf.frequency));
+        assert_eq!(tx_config.pw, 12);
+    }
+
+    #[test]
+    #[cfg(feature = "region-eu868")]
+    fn test_dynamic_eu868_duty_cycle() {
+        let mut r = Configuration::new(Region::EU868);
+        let mut rng = rand::rngs::OsRng;
+        let tx_config = r.create_tx_config(&mut rng, DR::_5, &Frame::Data, 0).unwrap();
+        r.register_transmission(&Frame::Data, &tx_config, 20, 0);
+        let airtime_ms = tx_config.rf.bb.time_on_air_us(Some(8), true, 20).div_ceil(1000) as u64;
+        // Default channels share the g1 sub-band with 1% duty cycle
+        assert_eq!(r.time_until_available(&Frame::Data, DR::_5, 0), airtime_ms * 100);
+        assert_eq!(r.time_until_available(&Frame::Join, DR::_5, 0), airtime_ms * 100);
+        assert_eq!(r.time_until_available(&Frame::Data, DR::_5, airtime_ms * 100), 0);
+
+        // A channel in the g3 sub-band (10%) is still available
+        let range = DataRateRange::new_range(DR::_0, DR::_5);
+        assert_eq!(r.handle_new_channel(3, 869_525_000, Some(range)), (true, true));
+        assert_eq!(r.time_until_available(&Frame::Data, DR::_5, 0), 0);
+        for _ in 0..10 {
+            let tx_config = r.create_tx_config(&mut rng, DR::_5, &Frame::Data, 0).unwrap();
+            assert_eq!(tx_config.rf.frequency, 869_525_000);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "region-eu868")]
+    fn test_dynamic_channel_datarate_range() {
+        let mut r = Configuration::new(Region::EU868);
+        let mut rng = rand::rngs::OsRng;
+        let range = DataRateRange::new_range(DR::_4, DR::_5);
+        assert_eq!(r.handle_new_channel(3, 867_100_000, Some(range)), (true, true));
+        // Only the new channel supports DR4 and DR5, the default ones DR0 to DR5
+        r.channel_mask_set(ChannelMask::new_from_raw(&[0b1001, 0, 0, 0, 0, 0, 0, 0, 0]));
+        for _ in 0..10 {
+            let tx_config = r.create_tx_config(&mut rng, DR::_2, &Frame::Data, 0).unwrap();
+            assert_eq!(tx_config.rf.frequency, 868_100_000);
+        }
+
+        // No enabled channel supports DR2
+        let channel_mask = ChannelMask::new_from_raw(&[0b1000, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert!(!r.channel_mask_validate(&channel_mask, Some(DR::_2)));
+        assert!(r.channel_mask_validate(&channel_mask, Some(DR::_5)));
+        r.channel_mask_set(channel_mask);
+        assert!(r.create_tx_config(&mut rng, DR::_2, &Frame::Data, 0).is_none());
+        let tx_config = r.create_tx_config(&mut rng, DR::_5, &Frame::Data, 0).unwrap();
+        assert_eq!(tx_config.rf.frequency, 867_100_000);
+    }
+
+    #[test]
+    #[cfg(feature = "region-eu868")]
+    fn test_dynamic_duty_cycle_datarate_range() {
+        let mut r = Configuration::new(Region::EU868);
+        let mut rng = rand::rngs::OsRng;
+        // A channel in the g3 sub-band (10%) only supporting DR4 and DR5
+        let range = DataRateRange::new_range(DR::_4, DR::_5);
+        assert_eq!(r.handle_new_channel(3, 869_525_000, Some(range)), (true, true));
+        let tx_config = r.create_tx_config(&mut rng, DR::_2, &Frame::Data, 0).unwrap();
+        r.register_transmission(&Frame::Data, &tx_config, 20, 0);
+        let airtime_ms = tx_config.rf.bb.time_on_air_us(Some(8), true, 20).div_ceil(1000) as u64;
+
+        // The default channels are blocked, the free channel doesn't support DR2
+        assert_eq!(r.time_until_available(&Frame::Data, DR::_2, 0), airtime_ms * 100);
+        assert!(r.create_tx_config(&mut rng, DR::_2, &Frame::Data, 0).is_none());
+        assert_eq!(r.time_until_available(&Frame::Data, DR::_5, 0), 0);
+        let tx_config = r.create_tx_config(&mut rng, DR::_5, &Frame::Data, 0).unwrap();
+        assert_eq!(tx_config.rf.frequency, 869_525_000);
+    }
+
+    #[test]
+    #[cfg(feature = "region-us915")]
+    fn test_aggregated_duty_cycle_and_join_backoff() {
+        let mut r = Configuration::new(Region::US915);
+        let mut rng = rand::rngs::OsRng;
+        let tx_config = r.create_tx_config(&mut rng, DR::_0, &Frame::Join, 0).unwrap();
+        r.register_transmission(&Frame::Join, &tx_config, 23, 0);
+        let airtime_ms = tx_config.rf.bb.time_on_air_us(Some(8), true, 23).div_ceil(1000) as u64;
+        // Join-requests are limited to 1% during the first hour, data frames are not affected
+        assert_eq!(r.time_until_available(&Frame::Join, DR::_0, 0), airtime_ms * 100);
+        assert_eq!(r.time_until_available(&Frame::Data, DR::_0, 0), 0);
+        // 0.1% after the first hour
+        let now = 2 * 3_600_000;
+        r.register_transmission(&Frame::Join, &tx_config, 23, now);
+        assert_eq!(r.time_until_available(&Frame::Join, DR::_0, now), airtime_ms * 1000);
+        r.reset_join_backoff();
+        assert_eq!(r.time_until_available(&Frame::Join, DR::_0, now), 0);
+
+        // MaxDCycle=3 limits the aggregated duty cycle to 1/8
+        r.set_max_duty_cycle(3);
+        r.register_transmission(&Frame::Data, &tx_config, 23, now);
+        assert_eq!(r.time_until_available(&Frame::Data, DR::_0, now), airtime_ms * 8);
+    }
 }
@@ -2,7 +2,7 @@
 //!
 //! This crate uses the random number generator for exactly two things:
 //!
-//! * Generating DevNonces for join requests
+//! * Generating DevNonces for join requests, when random DevNonces are used
 //! * Selecting random channels when transmitting uplinks.
 //!
 //! The good news is that both these operations don't require true
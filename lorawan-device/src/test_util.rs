@@ -26,7 +26,6 @@ use std::{
 #[derive(Debug, Clone)]
 pub struct Uplink {
     data: Vec<u8>,
-    #[allow(unused)]
     tx_config: TxConfig,
 }
 
@@ -39,6 +38,10 @@ impl Uplink {
         Ok(Self { data, tx_config })
     }
 
+    pub fn get_tx_config(&self) -> &TxConfig {
+        &self.tx_config
+    }
+
     pub fn get_payload(&mut self) -> PhyPayload<&mut [u8], DefaultFactory> {
         match parse(self.data.as_mut_slice()) {
             Ok(p) => p,
@@ -154,6 +157,15 @@ pub fn handle_data_uplink_with_link_adr_req<const FCNT_UP: u16, const FCNT_DOWN:
 
             let mut phy = lorawan::creator::DataPayloadCreator::new(rx_buffer).unwrap();
             phy.set_confirmed(uplink.is_confirmed());
+            // Acknowledge confirmed uplinks
+            phy.set_fctrl(&parser::FCtrl::new(
+                if uplink.is_confirmed() {
+                    0x20
+                } else {
+                    0
+                },
+                false,
+            ));
             phy.set_f_port(4);
             phy.set_dev_addr(&[0; 4]);
             phy.set_uplink(false);
@@ -218,6 +230,15 @@ pub fn handle_data_uplink_with_link_adr_ans(
             phy.set_uplink(false);
             //phy.set_f_port(3);
             phy.set_fcnt(1);
+            // Acknowledge confirmed uplinks
+            phy.set_fctrl(&parser::FCtrl::new(
+                if uplink.is_confirmed() {
+                    0x20
+                } else {
+                    0
+                },
+                false,
+            ));
             // zero out rx_buffer
             let finished =
                 phy.build(&[], [], &get_key().into(), &get_key().into(), &DefaultFactory).unwrap();
@@ -1,16 +1,20 @@
 //! Provides types and methods for creating LoRaWAN payloads.
 //!
 //! See [JoinAcceptCreator.new](struct.JoinAcceptCreator.html#method.new) for an example.
-use super::keys::{AppKey, AppSKey, CryptoFactory, Decrypter, NwkSKey, AES128};
+use super::keys::{
+    AppEui, AppKey, AppSKey, CryptoFactory, Decrypter, FNwkSIntKey, JSIntKey, Mac, NwkKey,
+    NwkSEncKey, NwkSKey, SNwkSIntKey, AES128,
+};
 use super::maccommands::{mac_commands_len, SerializableMacCommand};
 use super::parser;
 use super::securityhelpers;
 use crate::packet_length::phy::join::{
-    JOIN_ACCEPT_LEN, JOIN_ACCEPT_WITH_CFLIST_LEN, JOIN_REQUEST_LEN,
+    JOIN_ACCEPT_LEN, JOIN_ACCEPT_WITH_CFLIST_LEN, JOIN_REQUEST_LEN, REJOIN_REQUEST_0_2_LEN,
+    REJOIN_REQUEST_1_LEN,
 };
 use crate::packet_length::phy::mac::fhdr::FOPTS_MAX_LEN;
 use crate::packet_length::phy::{MIC_LEN, PHY_PAYLOAD_MIN_LEN};
-use crate::types::{DLSettings, Frequency};
+use crate::types::{ChannelMask, DLSettings, Frequency, JoinReqType, RejoinType};
 
 #[derive(Debug, PartialEq)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
@@ -20,6 +24,7 @@ pub enum Error {
     MacCommandTooBigForFOpts,
     DataAndMacCommandsInPayloadNotAllowed,
     FRMPayloadWithFportZero,
+    InvalidRejoinType,
 }
 
 /// Helper trait to provide dummy Creator implementation for
@@ -179,7 +184,30 @@ impl<D: AsMut<[u8]>> JoinAcceptCreator<D> {
             d[15 + i * 3] = ((v >> 16) & 0xff) as u8;
         });
         // set cflist type
-        d[JOIN_ACCEPT_WITH_CFLIST_LEN - 1] = 0;
+        d[28] = 0;
+        self.with_c_f_list = true;
+
+        Ok(self)
+    }
+
+    /// Sets the CFList of the JoinAccept to a channel mask (CFList type 1).
+    ///
+    /// # Argument
+    ///
+    /// * channel_mask - mask of the channels enabled on the device.
+    pub fn set_c_f_list_channel_mask(
+        &mut self,
+        channel_mask: &ChannelMask<9>,
+    ) -> Result<&mut Self, Error> {
+        let d = self.data.as_mut();
+        if d.len() < JOIN_ACCEPT_WITH_CFLIST_LEN {
+            return Err(Error::BufferTooShort);
+        }
+        d[13..22].copy_from_slice(channel_mask.as_ref());
+        // RFU
+        d[22..28].fill(0);
+        // set cflist type
+        d[28] = 1;
         self.with_c_f_list = true;
 
         Ok(self)
@@ -192,6 +220,52 @@ impl<D: AsMut<[u8]>> JoinAcceptCreator<D> {
     ///
     /// * key - the key to be used for encryption and setting the MIC.
     pub fn build<F: CryptoFactory>(&mut self, key: &AES128, factory: &F) -> Result<&[u8], Error> {
+        self.encrypt(key, factory, |d| set_mic(d, key, factory))
+    }
+
+    /// Provides the binary representation of the encrypted join accept physical payload for a
+    /// LoRaWAN 1.1 end-device, with the MIC set.
+    ///
+    /// If OptNeg is set in the DLSettings, the MIC is calculated with the JSIntKey and covers
+    /// the JoinReqType, JoinEUI and DevNonce. Otherwise it is calculated with `key`, as in
+    /// LoRaWAN 1.0.
+    ///
+    /// # Argument
+    ///
+    /// * key - the key to be used for encryption: the NwkKey when answering a join request.
+    /// * js_int_key - the JSIntKey of the end-device.
+    /// * join_req_type - the type of request which is answered.
+    /// * join_eui - the JoinEUI of the end-device.
+    /// * dev_nonce - the DevNonce of the request which is answered.
+    pub fn build_1_1<F: CryptoFactory, T: AsRef<[u8]>>(
+        &mut self,
+        key: &AES128,
+        js_int_key: &JSIntKey,
+        join_req_type: JoinReqType,
+        join_eui: &AppEui,
+        dev_nonce: &parser::DevNonce<T>,
+        factory: &F,
+    ) -> Result<&[u8], Error> {
+        self.encrypt(key, factory, |d| {
+            if !DLSettings::new(d[11]).opt_neg() {
+                return set_mic(d, key, factory);
+            }
+            let len = d.len();
+            let mut mac = factory.new_mac(&js_int_key.0);
+            mac.input(&[join_req_type.value()]);
+            mac.input(join_eui.as_ref());
+            mac.input(dev_nonce.as_ref());
+            let mic = securityhelpers::calculate_mic(&d[..len - MIC_LEN], mac);
+            d[len - MIC_LEN..].copy_from_slice(&mic.0[..]);
+        })
+    }
+
+    fn encrypt<F: CryptoFactory>(
+        &mut self,
+        key: &AES128,
+        factory: &F,
+        set_mic: impl FnOnce(&mut [u8]),
+    ) -> Result<&[u8], Error> {
         let required_len = if self.with_c_f_list {
             JOIN_ACCEPT_WITH_CFLIST_LEN
         } else {
@@ -206,7 +280,7 @@ impl<D: AsMut<[u8]>> JoinAcceptCreator<D> {
             } else {
                 &mut self.data.as_mut()[..JOIN_ACCEPT_LEN]
             };
-            set_mic(d, key, factory);
+            set_mic(d);
             let aes_enc = factory.new_dec(key);
             for i in 0..(d.len() >> 4) {
                 let start = (i << 4) + 1;
@@ -311,6 +385,131 @@ impl<D: AsMut<[u8]>> JoinRequestCreator<D> {
         set_mic(&mut d[..JOIN_REQUEST_LEN], &key.0, factory);
         &d[..JOIN_REQUEST_LEN]
     }
+
+    /// Provides the binary representation of the JoinRequest physical payload of a LoRaWAN 1.1
+    /// end-device, with the MIC set using the NwkKey.
+    pub fn build_with_nwk_key<F: CryptoFactory>(&mut self, key: &NwkKey, factory: &F) -> &[u8] {
+        let d = self.data.as_mut();
+        set_mic(&mut d[..JOIN_REQUEST_LEN], &key.0, factory);
+        &d[..JOIN_REQUEST_LEN]
+    }
+}
+
+/// RejoinRequestCreator serves for creating binary representation of Physical
+/// Payload of RejoinRequest of any type.
+///
+/// # Example
+///
+/// ```
+/// let mut buf = [0u8; 100];
+/// let mut phy = lorawan::creator::RejoinRequestCreator::new(
+///     &mut buf,
+///     lorawan::types::RejoinType::Type0,
+/// )
+/// .unwrap();
+/// let key = lorawan::keys::AES128([7; 16]);
+/// phy.set_net_id(&[1; 3]).unwrap();
+/// phy.set_dev_eui(&[2; 8]);
+/// phy.set_rj_count(3);
+/// let payload = phy.build(&key, &lorawan::default_crypto::DefaultFactory);
+/// ```
+pub struct RejoinRequestCreator<D> {
+    data: D,
+    rejoin_type: RejoinType,
+}
+
+impl<D: AsMut<[u8]>> RejoinRequestCreator<D> {
+    /// Creates a well initialized RejoinRequestCreator of the given type.
+    pub fn new(mut data: D, rejoin_type: RejoinType) -> Result<Self, Error> {
+        let d = data.as_mut();
+        if d.len() < Self::payload_len(rejoin_type) {
+            return Err(Error::BufferTooShort);
+        }
+        d[0] = 0xc0;
+        d[1] = rejoin_type.value();
+        Ok(Self { data, rejoin_type })
+    }
+
+    fn payload_len(rejoin_type: RejoinType) -> usize {
+        match rejoin_type {
+            RejoinType::Type1 => REJOIN_REQUEST_1_LEN,
+            _ => REJOIN_REQUEST_0_2_LEN,
+        }
+    }
+
+    /// Sets the network ID of a type 0 or type 2 RejoinRequest to the provided value.
+    ///
+    /// # Argument
+    ///
+    /// * net_id - instance of lorawan::parser::NwkAddr or anything that can be converted into it.
+    pub fn set_net_id<H: AsRef<[u8]>, T: Into<parser::NwkAddr<H>>>(
+        &mut self,
+        net_id: T,
+    ) -> Result<&mut Self, Error> {
+        if self.rejoin_type == RejoinType::Type1 {
+            return Err(Error::InvalidRejoinType);
+        }
+        let converted = net_id.into();
+        self.data.as_mut()[2..5].copy_from_slice(converted.as_ref());
+
+        Ok(self)
+    }
+
+    /// Sets the join EUI of a type 1 RejoinRequest to the provided value.
+    ///
+    /// # Argument
+    ///
+    /// * join_eui - instance of lorawan::parser::EUI64 or anything that can be converted into it.
+    pub fn set_join_eui<H: AsRef<[u8]>, T: Into<parser::EUI64<H>>>(
+        &mut self,
+        join_eui: T,
+    ) -> Result<&mut Self, Error> {
+        if self.rejoin_type != RejoinType::Type1 {
+            return Err(Error::InvalidRejoinType);
+        }
+        let converted = join_eui.into();
+        self.data.as_mut()[2..10].copy_from_slice(converted.as_ref());
+
+        Ok(self)
+    }
+
+    /// Sets the device EUI of the RejoinRequest to the provided value.
+    ///
+    /// # Argument
+    ///
+    /// * dev_eui - instance of lorawan::parser::EUI64 or anything that can be converted into it.
+    pub fn set_dev_eui<H: AsRef<[u8]>, T: Into<parser::EUI64<H>>>(
+        &mut self,
+        dev_eui: T,
+    ) -> &mut Self {
+        let converted = dev_eui.into();
+        let end = Self::payload_len(self.rejoin_type) - MIC_LEN - 2;
+        self.data.as_mut()[end - 8..end].copy_from_slice(converted.as_ref());
+
+        self
+    }
+
+    /// Sets the counter of the RejoinRequest: RJcount0 for type 0 and type 2, RJcount1 for
+    /// type 1.
+    pub fn set_rj_count(&mut self, rj_count: u16) -> &mut Self {
+        let offset = Self::payload_len(self.rejoin_type) - MIC_LEN - 2;
+        self.data.as_mut()[offset..offset + 2].copy_from_slice(&rj_count.to_le_bytes());
+
+        self
+    }
+
+    /// Provides the binary representation of the RejoinRequest physical payload
+    /// with the MIC set.
+    ///
+    /// # Argument
+    ///
+    /// * key - SNwkSIntKey for type 0 and type 2 RejoinRequests, JSIntKey for type 1.
+    pub fn build<F: CryptoFactory>(&mut self, key: &AES128, factory: &F) -> &[u8] {
+        let len = Self::payload_len(self.rejoin_type);
+        let d = self.data.as_mut();
+        set_mic(&mut d[..len], key, factory);
+        &d[..len]
+    }
 }
 
 /// DataPayloadCreator serves for creating binary representation of Physical
@@ -337,6 +536,9 @@ pub struct DataPayloadCreator<D> {
     data: D,
     data_f_port: Option<u8>,
     fcnt: u32,
+    conf_fcnt: u16,
+    tx_dr: u8,
+    tx_ch: u8,
 }
 
 impl<D: AsMut<[u8]>> DataPayloadCreator<D> {
@@ -349,7 +551,14 @@ impl<D: AsMut<[u8]>> DataPayloadCreator<D> {
             return Err(Error::BufferTooShort);
         }
         d[0] = 0x40;
-        Ok(DataPayloadCreator { data, data_f_port: None, fcnt: 0 })
+        Ok(DataPayloadCreator {
+            data,
+            data_f_port: None,
+            fcnt: 0,
+            conf_fcnt: 0,
+            tx_dr: 0,
+            tx_ch: 0,
+        })
     }
 
     /// Sets whether the packet is uplink or downlink.
@@ -438,6 +647,32 @@ impl<D: AsMut<[u8]>> DataPayloadCreator<D> {
         self
     }
 
+    /// Sets ConfFCnt of a LoRaWAN 1.1 frame, ie: the frame counter of the confirmed frame that is
+    /// acknowledged. It is covered by the MIC if the ACK bit is set.
+    ///
+    /// # Argument
+    ///
+    /// * conf_fcnt - the frame counter of the acknowledged frame, truncated to u16.
+    pub fn set_conf_fcnt(&mut self, conf_fcnt: u16) -> &mut Self {
+        self.conf_fcnt = conf_fcnt;
+
+        self
+    }
+
+    /// Sets the data rate and the channel index of a LoRaWAN 1.1 uplink transmission, which are
+    /// covered by the MIC.
+    ///
+    /// # Argument
+    ///
+    /// * tx_dr - the data rate of the transmission.
+    /// * tx_ch - the index of the channel used for the transmission.
+    pub fn set_tx_dr_ch(&mut self, tx_dr: u8, tx_ch: u8) -> &mut Self {
+        self.tx_dr = tx_dr;
+        self.tx_ch = tx_ch;
+
+        self
+    }
+
     /// Whether a set of mac commands can be piggybacked.
     pub fn can_piggyback(cmds: &[&dyn SerializableMacCommand]) -> bool {
         mac_commands_len(cmds) <= PIGGYBACK_MAC_COMMANDS_MAX_LEN
@@ -485,11 +720,104 @@ impl<D: AsMut<[u8]>> DataPayloadCreator<D> {
         app_skey: &AppSKey,
         factory: &F,
     ) -> Result<&[u8], Error> {
+        let last_filled =
+            self.assemble(payload, mac_cmds.as_ref(), &nwk_skey.0, &app_skey.0, false, factory)?;
+        let d = self.data.as_mut();
+
+        // MIC set
+        let mic = securityhelpers::calculate_data_mic(
+            &d[..last_filled],
+            factory.new_mac(&nwk_skey.0),
+            self.fcnt,
+        );
+        d[last_filled..last_filled + MIC_LEN].copy_from_slice(&mic.0);
+
+        Ok(&d[..last_filled + MIC_LEN])
+    }
+
+    /// Provides the binary representation of a LoRaWAN 1.1 DataPayload physical payload with the
+    /// MIC set, and FOpts and FRMPayload encrypted.
+    ///
+    /// The frame counter of downlinks has to be AFCntDown if FPort is greater than 0 and NFCntDown
+    /// otherwise. For uplinks, the data rate and channel of the transmission have to be set with
+    /// [`set_tx_dr_ch`](Self::set_tx_dr_ch). If the frame acknowledges a confirmed frame,
+    /// its frame counter has to be set with [`set_conf_fcnt`](Self::set_conf_fcnt).
+    ///
+    /// # Argument
+    ///
+    /// * payload - the FRMPayload (application) to be sent.
+    /// * mac_cmds - the MAC commands to be sent, either in FOpts or in FRMPayload with FPort 0.
+    /// * f_nwk_s_int_key - the key used for the MIC of uplinks, together with s_nwk_s_int_key.
+    /// * s_nwk_s_int_key - the key used for the MIC.
+    /// * nwk_s_enc_key - the key used for MAC command encryption.
+    /// * app_skey - the key used for payload encryption if fport is not 0.
+    #[allow(clippy::too_many_arguments)]
+    pub fn build_1_1<F: CryptoFactory, M: AsRef<[u8]>>(
+        &mut self,
+        payload: &[u8],
+        mac_cmds: M,
+        f_nwk_s_int_key: &FNwkSIntKey,
+        s_nwk_s_int_key: &SNwkSIntKey,
+        nwk_s_enc_key: &NwkSEncKey,
+        app_skey: &AppSKey,
+        factory: &F,
+    ) -> Result<&[u8], Error> {
+        let last_filled = self.assemble(
+            payload,
+            mac_cmds.as_ref(),
+            &nwk_s_enc_key.0,
+            &app_skey.0,
+            true,
+            factory,
+        )?;
+        let d = self.data.as_mut();
+        let uplink = d[0] & 0x20 == 0;
+        // ConfFCnt is only used when acknowledging a frame
+        let conf_fcnt = if d[5] & 0x20 != 0 {
+            self.conf_fcnt
+        } else {
+            0
+        };
+
+        let mic = if uplink {
+            securityhelpers::calculate_uplink_data_mic_1_1(
+                &d[..last_filled],
+                factory.new_mac(&f_nwk_s_int_key.0),
+                factory.new_mac(&s_nwk_s_int_key.0),
+                self.fcnt,
+                conf_fcnt,
+                self.tx_dr,
+                self.tx_ch,
+            )
+        } else {
+            securityhelpers::calculate_downlink_data_mic_1_1(
+                &d[..last_filled],
+                factory.new_mac(&s_nwk_s_int_key.0),
+                self.fcnt,
+                conf_fcnt,
+            )
+        };
+        d[last_filled..last_filled + MIC_LEN].copy_from_slice(&mic.0);
+
+        Ok(&d[..last_filled + MIC_LEN])
+    }
+
+    /// Fills in FOpts, FPort and the encrypted FRMPayload, returning the length of the frame
+    /// without the MIC. FOpts are encrypted as well for LoRaWAN 1.1 (`encrypt_fopts`).
+    fn assemble<F: CryptoFactory>(
+        &mut self,
+        payload: &[u8],
+        mac_cmds: &[u8],
+        nwk_enc_key: &AES128,
+        app_skey: &AES128,
+        encrypt_fopts: bool,
+        factory: &F,
+    ) -> Result<usize, Error> {
         let d = self.data.as_mut();
         let mut last_filled = 8; // MHDR + FHDR without the FOpts
         let has_fport = self.data_f_port.is_some();
         let has_fport_zero = has_fport && self.data_f_port.unwrap() == 0;
-        let mac_cmds_len = mac_cmds.as_ref().len();
+        let mac_cmds_len = mac_cmds.len();
         // Set MAC Commands
         if mac_cmds_len > FOPTS_MAX_LEN && !has_fport_zero {
             return Err(Error::MacCommandTooBigForFOpts);
@@ -511,7 +839,18 @@ impl<D: AsMut<[u8]>> DataPayloadCreator<D> {
             }
             d[5] |= mac_cmds_len as u8 & 0x0f;
             // copy mac commmands into d
-            d[last_filled..last_filled + mac_cmds_len].copy_from_slice(mac_cmds.as_ref());
+            d[last_filled..last_filled + mac_cmds_len].copy_from_slice(mac_cmds);
+            if encrypt_fopts {
+                // Downlinks with FPort > 0 use AFCntDown
+                let afcnt_down = d[0] & 0x20 != 0 && has_fport;
+                securityhelpers::encrypt_fopts(
+                    d,
+                    mac_cmds_len,
+                    self.fcnt,
+                    afcnt_down,
+                    &factory.new_enc(nwk_enc_key),
+                );
+            }
             last_filled += mac_cmds_len;
         }
 
@@ -523,14 +862,14 @@ impl<D: AsMut<[u8]>> DataPayloadCreator<D> {
             last_filled += 1;
         }
 
-        let mut enc_key = app_skey.0;
+        let mut enc_key = app_skey;
         if mac_cmds_len > 0 && has_fport_zero {
-            enc_key = nwk_skey.0;
+            enc_key = nwk_enc_key;
             payload_len = mac_cmds_len;
             if d.len() < last_filled + payload_len + MIC_LEN {
                 return Err(Error::BufferTooShort);
             }
-            d[last_filled..last_filled + payload_len].copy_from_slice(mac_cmds.as_ref());
+            d[last_filled..last_filled + payload_len].copy_from_slice(mac_cmds);
         } else {
             if d.len() < last_filled + payload_len + MIC_LEN {
                 return Err(Error::BufferTooShort);
@@ -544,18 +883,10 @@ impl<D: AsMut<[u8]>> DataPayloadCreator<D> {
             last_filled,
             last_filled + payload_len,
             self.fcnt,
-            &factory.new_enc(&enc_key),
+            &factory.new_enc(enc_key),
         );
         last_filled += payload_len;
 
-        // MIC set
-        let mic = securityhelpers::calculate_data_mic(
-            &d[..last_filled],
-            factory.new_mac(&nwk_skey.0),
-            self.fcnt,
-        );
-        d[last_filled..last_filled + MIC_LEN].copy_from_slice(&mic.0);
-
-        Ok(&d[..last_filled + MIC_LEN])
+        Ok(last_filled)
     }
 }
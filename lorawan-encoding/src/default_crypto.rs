@@ -2,7 +2,7 @@
 use super::keys::*;
 use super::parser::{
     DecryptedDataPayload, DecryptedJoinAcceptPayload, EncryptedDataPayload,
-    EncryptedJoinAcceptPayload, JoinRequestPayload,
+    EncryptedJoinAcceptPayload, JoinRequestPayload, RejoinRequestPayload,
 };
 use crate::parser::Error;
 use aes::cipher::generic_array::GenericArray;
@@ -81,6 +81,27 @@ impl<T: AsRef<[u8]>> JoinRequestPayload<T, DefaultFactory> {
     }
 }
 
+impl<T: AsRef<[u8]>> RejoinRequestPayload<T, DefaultFactory> {
+    /// Creates a new RejoinRequestPayload if the provided data is acceptable.
+    ///
+    /// # Argument
+    ///
+    /// * data - the bytes for the payload.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let data = vec![
+    ///     0xc0, 0x00, 0x04, 0x05, 0x06, 0x05, 0x04, 0x03, 0x02, 0x05, 0x04, 0x03, 0x02, 0x01, 0x00,
+    ///     0x84, 0x7b, 0x9a, 0xc7,
+    /// ];
+    /// let phy = lorawan::parser::RejoinRequestPayload::new(data);
+    /// ```
+    pub fn new(data: T) -> Result<Self, Error> {
+        Self::new_with_factory(data, DefaultFactory)
+    }
+}
+
 impl<T: AsRef<[u8]> + AsMut<[u8]>> EncryptedJoinAcceptPayload<T, DefaultFactory> {
     /// Creates a new EncryptedJoinAcceptPayload if the provided data is acceptable.
     ///
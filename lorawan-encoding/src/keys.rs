@@ -82,6 +82,72 @@ lorawan_key!(
 #[deprecated(since = "0.9.1", note = "Please use `NwkSKey` instead")]
 pub type NewSKey = NwkSKey;
 
+lorawan_key!(
+    /// The [`NwkKey`] is the LoRaWAN 1.1 network root key (AES-128) specific to the end-device.
+    ///
+    /// It is used for the MIC of join-requests and to derive the network session keys, while
+    /// the [`AppKey`] is only used to derive the [`AppSKey`].
+    pub struct NwkKey(AES128);
+);
+
+impl NwkKey {
+    /// JSIntKey = aes128_encrypt(NwkKey, 0x06 | DevEUI | pad16)
+    pub fn derive_js_int_key<F: CryptoFactory>(&self, crypto: &F, dev_eui: &DevEui) -> JSIntKey {
+        JSIntKey(self.derive_with_dev_eui(crypto, 0x06, dev_eui))
+    }
+
+    /// JSEncKey = aes128_encrypt(NwkKey, 0x05 | DevEUI | pad16)
+    pub fn derive_js_enc_key<F: CryptoFactory>(&self, crypto: &F, dev_eui: &DevEui) -> JSEncKey {
+        JSEncKey(self.derive_with_dev_eui(crypto, 0x05, dev_eui))
+    }
+
+    fn derive_with_dev_eui<F: CryptoFactory>(
+        &self,
+        crypto: &F,
+        first: u8,
+        dev_eui: &DevEui,
+    ) -> AES128 {
+        let aes_enc = crypto.new_enc(&self.0);
+        let mut bytes: [u8; 16] = [0; 16];
+        bytes[0] = first;
+        bytes[1..9].copy_from_slice(dev_eui.as_ref());
+        aes_enc.encrypt_block(&mut bytes);
+        AES128(bytes)
+    }
+}
+
+lorawan_key!(
+    /// The join server integrity key ([`JSIntKey`]) is derived from the [`NwkKey`] and used for
+    /// the MIC of LoRaWAN 1.1 join-accepts and rejoin-requests of type 1.
+    pub struct JSIntKey(AES128);
+);
+
+lorawan_key!(
+    /// The join server encryption key ([`JSEncKey`]) is derived from the [`NwkKey`] and used to
+    /// encrypt join-accepts answering rejoin-requests.
+    pub struct JSEncKey(AES128);
+);
+
+lorawan_key!(
+    /// The forwarding network session integrity key ([`FNwkSIntKey`]) is a LoRaWAN 1.1 network
+    /// session key (AES-128) used for the MIC of uplinks. It takes the role of the LoRaWAN 1.0
+    /// [`NwkSKey`].
+    pub struct FNwkSIntKey(AES128);
+);
+
+lorawan_key!(
+    /// The serving network session integrity key ([`SNwkSIntKey`]) is a LoRaWAN 1.1 network
+    /// session key (AES-128) used for the MIC of downlinks and, together with the
+    /// [`FNwkSIntKey`], of uplinks.
+    pub struct SNwkSIntKey(AES128);
+);
+
+lorawan_key!(
+    /// The network session encryption key ([`NwkSEncKey`]) is a LoRaWAN 1.1 network session key
+    /// (AES-128) used to encrypt MAC commands, both in FOpts and in FRMPayload with FPort 0.
+    pub struct NwkSEncKey(AES128);
+);
+
 lorawan_key!(
     pub struct McKey(AES128);
 );
@@ -375,4 +441,27 @@ mod test {
             mc_net_s_key
         )
     }
+
+    #[test]
+    fn nwk_key_to_js_keys() {
+        let nwk_key = NwkKey::from([
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd,
+            0xee, 0xff,
+        ]);
+        let dev_eui = DevEui::from([0x05, 0x04, 0x03, 0x02, 0x05, 0x04, 0x03, 0x02]);
+        assert_eq!(
+            JSIntKey(AES128([
+                0xa6, 0xd0, 0x44, 0x39, 0x87, 0xeb, 0xb2, 0x09, 0xbd, 0x96, 0x53, 0x7a, 0xde, 0x22,
+                0x7a, 0xfa
+            ])),
+            nwk_key.derive_js_int_key(&DefaultFactory, &dev_eui)
+        );
+        assert_eq!(
+            JSEncKey(AES128([
+                0x24, 0x93, 0x27, 0x03, 0xd3, 0xeb, 0xa5, 0x38, 0xad, 0x47, 0x60, 0xf6, 0xc4, 0x8c,
+                0x4d, 0xe6
+            ])),
+            nwk_key.derive_js_enc_key(&DefaultFactory, &dev_eui)
+        );
+    }
 }
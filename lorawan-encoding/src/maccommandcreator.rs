@@ -1,5 +1,5 @@
 use super::maccommands::{mac_commands_len, SerializableMacCommand};
-use crate::types::{ChannelMask, DLSettings, DataRateRange, Frequency, Redundancy};
+use crate::types::{ChannelMask, DLSettings, DataRateRange, Frequency, Redundancy, RejoinType};
 
 #[derive(Debug, PartialEq)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
@@ -10,6 +10,7 @@ pub enum Error {
     DelayOutOfRange,
     MaxEirpOutOfRange,
     NanoSecondsOutOfRange,
+    PeriodicityOutOfRange,
     BufferTooShort,
 }
 
@@ -570,6 +571,188 @@ impl DlChannelAnsCreator {
     }
 }
 
+#[doc(inline)]
+pub use crate::maccommands::RekeyIndCreator;
+
+impl RekeyIndCreator {
+    /// Sets the minor version of LoRaWAN implemented by the end-device.
+    ///
+    /// # Argument
+    ///
+    /// * minor - the minor version, eg: 1 for LoRaWAN 1.1.
+    pub fn set_dev_lorawan_version(&mut self, minor: u8) -> &mut Self {
+        self.data[1] = minor & 0x0f;
+        self
+    }
+}
+
+#[doc(inline)]
+pub use crate::maccommands::RekeyConfCreator;
+
+impl RekeyConfCreator {
+    /// Sets the minor version of LoRaWAN implemented by the network server.
+    ///
+    /// # Argument
+    ///
+    /// * minor - the minor version, eg: 1 for LoRaWAN 1.1.
+    pub fn set_serv_lorawan_version(&mut self, minor: u8) -> &mut Self {
+        self.data[1] = minor & 0x0f;
+        self
+    }
+}
+
+#[doc(inline)]
+pub use crate::maccommands::ForceRejoinReqCreator;
+
+impl ForceRejoinReqCreator {
+    /// Sets the delay between retransmissions of the rejoin-request.
+    ///
+    /// # Argument
+    ///
+    /// * period - the delay is 32 s * 2^period plus a random delay of up to 32 s.
+    pub fn set_period(&mut self, period: u8) -> &mut Self {
+        self.data[2] &= 0xc7;
+        self.data[2] |= (period & 0x07) << 3;
+        self
+    }
+
+    /// Sets the number of retransmissions of the rejoin-request.
+    pub fn set_max_retries(&mut self, max_retries: u8) -> &mut Self {
+        self.data[2] &= 0xf8;
+        self.data[2] |= max_retries & 0x07;
+        self
+    }
+
+    /// Sets the type of the rejoin-request to be transmitted, either type 0 or type 2.
+    pub fn set_rejoin_type(&mut self, rejoin_type: RejoinType) -> &mut Self {
+        self.data[1] &= 0x8f;
+        self.data[1] |= rejoin_type.value() << 4;
+        self
+    }
+
+    /// Sets the data rate of the rejoin-request.
+    pub fn set_data_rate(&mut self, data_rate: u8) -> &mut Self {
+        self.data[1] &= 0xf0;
+        self.data[1] |= data_rate & 0x0f;
+        self
+    }
+}
+
+#[doc(inline)]
+pub use crate::maccommands::RejoinParamSetupReqCreator;
+
+impl RejoinParamSetupReqCreator {
+    /// Sets the maximum time between type 0 rejoin-requests to 2^(max_time_n + 10) seconds.
+    pub fn set_max_time_n(&mut self, max_time_n: u8) -> &mut Self {
+        self.data[1] &= 0x0f;
+        self.data[1] |= max_time_n << 4;
+        self
+    }
+
+    /// Sets the maximum number of uplinks between type 0 rejoin-requests to
+    /// 2^(max_count_n + 4).
+    pub fn set_max_count_n(&mut self, max_count_n: u8) -> &mut Self {
+        self.data[1] &= 0xf0;
+        self.data[1] |= max_count_n & 0x0f;
+        self
+    }
+}
+
+#[doc(inline)]
+pub use crate::maccommands::RejoinParamSetupAnsCreator;
+
+impl RejoinParamSetupAnsCreator {
+    /// Sets whether the end-device is able to send rejoin-requests based on time as well.
+    pub fn set_time_ack(&mut self, ack: bool) -> &mut Self {
+        self.data[1] = ack as u8;
+        self
+    }
+}
+
+#[doc(inline)]
+pub use crate::maccommands::PingSlotInfoReqCreator;
+
+impl PingSlotInfoReqCreator {
+    /// Sets the periodicity of the ping slots.
+    ///
+    /// # Argument
+    ///
+    /// * periodicity - ping slots are opened every 2^periodicity seconds, at most 7.
+    pub fn set_periodicity(&mut self, periodicity: u8) -> Result<&mut Self, Error> {
+        if periodicity > 7 {
+            return Err(Error::PeriodicityOutOfRange);
+        }
+        self.data[1] = periodicity;
+        Ok(self)
+    }
+}
+
+#[doc(inline)]
+pub use crate::maccommands::PingSlotInfoAnsCreator;
+
+#[doc(inline)]
+pub use crate::maccommands::PingSlotChannelReqCreator;
+
+impl PingSlotChannelReqCreator {
+    /// Sets the frequency of the ping slots, 0 restores the region default.
+    pub fn set_frequency<'a, T: Into<Frequency<'a>>>(&mut self, frequency: T) -> &mut Self {
+        let converted = frequency.into();
+        self.data[1..4].copy_from_slice(converted.as_ref());
+        self
+    }
+
+    /// Sets the data rate of the ping slots.
+    pub fn set_data_rate(&mut self, data_rate: u8) -> Result<&mut Self, Error> {
+        if data_rate > 0x0f {
+            return Err(Error::InvalidDataRate);
+        }
+        self.data[4] = data_rate;
+        Ok(self)
+    }
+}
+
+#[doc(inline)]
+pub use crate::maccommands::PingSlotChannelAnsCreator;
+
+impl PingSlotChannelAnsCreator {
+    /// Sets whether the ping slot frequency is usable by the device.
+    pub fn set_channel_frequency_ack(&mut self, ack: bool) -> &mut Self {
+        self.data[1] &= 0xfe;
+        self.data[1] |= ack as u8;
+        self
+    }
+
+    /// Sets whether the ping slot data rate is usable by the device.
+    pub fn set_data_rate_ack(&mut self, ack: bool) -> &mut Self {
+        self.data[1] &= 0xfd;
+        self.data[1] |= (ack as u8) << 1;
+        self
+    }
+}
+
+#[doc(inline)]
+pub use crate::maccommands::BeaconFreqReqCreator;
+
+impl BeaconFreqReqCreator {
+    /// Sets the frequency of the beacons, 0 restores the region default.
+    pub fn set_frequency<'a, T: Into<Frequency<'a>>>(&mut self, frequency: T) -> &mut Self {
+        let converted = frequency.into();
+        self.data[1..4].copy_from_slice(converted.as_ref());
+        self
+    }
+}
+
+#[doc(inline)]
+pub use crate::maccommands::BeaconFreqAnsCreator;
+
+impl BeaconFreqAnsCreator {
+    /// Sets whether the beacon frequency is usable by the device.
+    pub fn set_beacon_frequency_ack(&mut self, ack: bool) -> &mut Self {
+        self.data[1] = ack as u8;
+        self
+    }
+}
+
 #[doc(inline)]
 pub use crate::maccommands::DeviceTimeAnsCreator;
 #[doc(inline)]
@@ -25,6 +25,8 @@ pub use crate::types::Frequency;
 #[doc(hidden)]
 pub use crate::types::Redundancy;
 
+use crate::types::RejoinType;
+
 #[derive(Debug, PartialEq)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
 pub enum Error {
@@ -88,10 +90,37 @@ pub enum DownlinkMacCommand<'a> {
     #[cmd(cid = 0x0A, len = 4)]
     DlChannelReq(DlChannelReqPayload<'a>),
 
+    // LoRaWAN 1.1+ commands
+    /// RekeyConf payload handling (LoRaWAN 1.1+)
+    #[cmd(cid = 0x0B, len = 1)]
+    RekeyConf(RekeyConfPayload<'a>),
+
     // LoRaWAN 1.0.3+ commands
     /// DeviceTimeAns payload handling (LoRaWAN 1.0.3+)
     #[cmd(cid = 0x0D, len = 5)]
     DeviceTimeAns(DeviceTimeAnsPayload<'a>),
+
+    // LoRaWAN 1.1+ commands
+    /// ForceRejoinReq payload handling (LoRaWAN 1.1+)
+    #[cmd(cid = 0x0E, len = 2)]
+    ForceRejoinReq(ForceRejoinReqPayload<'a>),
+
+    /// RejoinParamSetupReq payload handling (LoRaWAN 1.1+)
+    #[cmd(cid = 0x0F, len = 1)]
+    RejoinParamSetupReq(RejoinParamSetupReqPayload<'a>),
+
+    // LoRaWAN 1.0.3+ Class B commands
+    /// PingSlotInfoAns payload handling (LoRaWAN 1.0.3+)
+    #[cmd(cid = 0x10, len = 0)]
+    PingSlotInfoAns(PingSlotInfoAnsPayload),
+
+    /// PingSlotChannelReq payload handling (LoRaWAN 1.0.3+)
+    #[cmd(cid = 0x11, len = 4)]
+    PingSlotChannelReq(PingSlotChannelReqPayload<'a>),
+
+    /// BeaconFreqReq payload handling (LoRaWAN 1.0.3+)
+    #[cmd(cid = 0x13, len = 3)]
+    BeaconFreqReq(BeaconFreqReqPayload<'a>),
 }
 
 #[derive(Debug, PartialEq, CommandHandler)]
@@ -136,10 +165,33 @@ pub enum UplinkMacCommand<'a> {
     #[cmd(cid = 0x0A, len = 1)]
     DlChannelAns(DlChannelAnsPayload<'a>),
 
+    // LoRaWAN 1.1+ commands
+    /// RekeyInd payload handling (LoRaWAN 1.1+)
+    #[cmd(cid = 0x0B, len = 1)]
+    RekeyInd(RekeyIndPayload<'a>),
+
     // 1.0.3+
     /// DeviceTimeReq payload handling (LoRaWAN 1.0.3+)
     #[cmd(cid = 0x0D, len = 0)]
     DeviceTimeReq(DeviceTimeReqPayload),
+
+    // LoRaWAN 1.1+ commands
+    /// RejoinParamSetupAns payload handling (LoRaWAN 1.1+)
+    #[cmd(cid = 0x0F, len = 1)]
+    RejoinParamSetupAns(RejoinParamSetupAnsPayload<'a>),
+
+    // LoRaWAN 1.0.3+ Class B commands
+    /// PingSlotInfoReq payload handling (LoRaWAN 1.0.3+)
+    #[cmd(cid = 0x10, len = 1)]
+    PingSlotInfoReq(PingSlotInfoReqPayload<'a>),
+
+    /// PingSlotChannelAns payload handling (LoRaWAN 1.0.3+)
+    #[cmd(cid = 0x11, len = 1)]
+    PingSlotChannelAns(PingSlotChannelAnsPayload<'a>),
+
+    /// BeaconFreqAns payload handling (LoRaWAN 1.0.3+)
+    #[cmd(cid = 0x13, len = 1)]
+    BeaconFreqAns(BeaconFreqAnsPayload<'a>),
 }
 
 macro_rules! create_ack_fn {
@@ -465,9 +517,128 @@ impl DlChannelAnsPayload<'_> {
     }
 }
 
+impl RekeyIndPayload<'_> {
+    /// The minor version of LoRaWAN implemented by the end-device, eg: 1 for LoRaWAN 1.1.
+    pub fn dev_lorawan_version(&self) -> u8 {
+        self.0[0] & 0x0f
+    }
+}
+
+impl RekeyConfPayload<'_> {
+    /// The minor version of LoRaWAN implemented by the network server, eg: 1 for LoRaWAN 1.1.
+    pub fn serv_lorawan_version(&self) -> u8 {
+        self.0[0] & 0x0f
+    }
+}
+
+impl ForceRejoinReqPayload<'_> {
+    fn value(&self) -> u16 {
+        u16::from_le_bytes([self.0[0], self.0[1]])
+    }
+
+    /// Delay between retransmissions of the rejoin-request, 32 s * 2^Period plus a random delay
+    /// of up to 32 s.
+    pub fn period(&self) -> u8 {
+        ((self.value() >> 11) & 0x07) as u8
+    }
+
+    /// Number of retransmissions of the rejoin-request.
+    pub fn max_retries(&self) -> u8 {
+        ((self.value() >> 8) & 0x07) as u8
+    }
+
+    /// Type of the rejoin-request to be transmitted. Values 0 and 1 both request a type 0
+    /// rejoin-request.
+    pub fn rejoin_type(&self) -> Result<RejoinType, Error> {
+        match (self.value() >> 4) & 0x07 {
+            0 | 1 => Ok(RejoinType::Type0),
+            2 => Ok(RejoinType::Type2),
+            _ => Err(Error::RFU),
+        }
+    }
+
+    /// Data rate of the rejoin-request.
+    pub fn data_rate(&self) -> u8 {
+        (self.value() & 0x0f) as u8
+    }
+}
+
+impl RejoinParamSetupReqPayload<'_> {
+    /// A type 0 rejoin-request has to be sent at least every 2^(MaxTimeN + 10) seconds.
+    pub fn max_time_n(&self) -> u8 {
+        self.0[0] >> 4
+    }
+
+    /// A type 0 rejoin-request has to be sent at least every 2^(MaxCountN + 4) uplinks.
+    pub fn max_count_n(&self) -> u8 {
+        self.0[0] & 0x0f
+    }
+}
+
+impl RejoinParamSetupAnsPayload<'_> {
+    create_ack_fn!(
+        /// Whether the end-device is able to send rejoin-requests based on time as well.
+        time_ack,
+        0
+    );
+}
+
+impl PingSlotInfoReqPayload<'_> {
+    /// Ping slots are opened every 2^periodicity seconds.
+    pub fn periodicity(&self) -> u8 {
+        self.0[0] & 0x07
+    }
+}
+
+impl PingSlotChannelReqPayload<'_> {
+    /// The frequency of the ping slots, 0 restores the region default.
+    pub fn frequency(&self) -> Frequency<'_> {
+        Frequency::new_from_raw(&self.0[0..3])
+    }
+
+    /// The data rate of the ping slots.
+    pub fn data_rate(&self) -> u8 {
+        self.0[3] & 0x0f
+    }
+}
+
+impl PingSlotChannelAnsPayload<'_> {
+    create_ack_fn!(
+        /// Channel frequency ok
+        channel_freq_ack,
+        0
+    );
+
+    create_ack_fn!(
+        /// Data rate ok
+        data_rate_ack,
+        1
+    );
+
+    /// Whether the device has accepted the new ping slot channel.
+    pub fn ack(&self) -> bool {
+        self.0[0] & 0x03 == 0x03
+    }
+}
+
+impl BeaconFreqReqPayload<'_> {
+    /// The frequency of the beacons, 0 restores the region default.
+    pub fn frequency(&self) -> Frequency<'_> {
+        Frequency::new_from_raw(&self.0[0..3])
+    }
+}
+
+impl BeaconFreqAnsPayload<'_> {
+    create_ack_fn!(
+        /// Beacon frequency ok
+        beacon_freq_ack,
+        0
+    );
+}
+
 impl DeviceTimeAnsPayload<'_> {
     pub fn seconds(&self) -> u32 {
-        u32::from_le_bytes([self.0[3], self.0[2], self.0[1], self.0[0]])
+        u32::from_le_bytes([self.0[0], self.0[1], self.0[2], self.0[3]])
     }
     //raw value in 1/256 seconds
     pub fn nano_seconds(&self) -> u32 {
@@ -21,8 +21,11 @@
 //! }
 //! ```
 
-use super::keys::{AppKey, AppSKey, CryptoFactory, Encrypter, NwkSKey, AES128, MIC};
-use crate::types::{ChannelMask, DLSettings, Frequency};
+use super::keys::{
+    AppEui, AppKey, AppSKey, CryptoFactory, Encrypter, FNwkSIntKey, JSEncKey, JSIntKey, Mac,
+    NwkKey, NwkSEncKey, NwkSKey, SNwkSIntKey, AES128, MIC,
+};
+use crate::types::{ChannelMask, DLSettings, Frequency, JoinReqType, RejoinType};
 
 use super::securityhelpers;
 
@@ -127,11 +130,12 @@ macro_rules! fixed_len_struct {
 
 /// PhyPayload is a type that represents a physical LoRaWAN payload.
 ///
-/// It can either be JoinRequest, JoinAccept, or DataPayload.
+/// It can either be JoinRequest, JoinAccept, RejoinRequest or DataPayload.
 #[derive(Debug, PartialEq, Eq)]
 pub enum PhyPayload<T, F> {
     JoinRequest(JoinRequestPayload<T, F>),
     JoinAccept(JoinAcceptPayload<T, F>),
+    RejoinRequest(RejoinRequestPayload<T, F>),
     Data(DataPayload<T, F>),
 }
 
@@ -142,6 +146,9 @@ impl<T: defmt::Format, F> defmt::Format for PhyPayload<T, F> {
             PhyPayload::JoinRequest(r) => {
                 defmt::write!(f, "JoinRequestPayload({})", r.0);
             }
+            PhyPayload::RejoinRequest(r) => {
+                defmt::write!(f, "RejoinRequestPayload({})", r.0);
+            }
             PhyPayload::JoinAccept(a) => match a {
                 JoinAcceptPayload::Encrypted(data) => {
                     defmt::write!(f, "JoinAcceptPayload::Encrypted({})", data.0);
@@ -167,6 +174,7 @@ impl<T: AsRef<[u8]>, F> AsRef<[u8]> for PhyPayload<T, F> {
         match self {
             PhyPayload::JoinRequest(jr) => jr.as_bytes(),
             PhyPayload::JoinAccept(ja) => ja.as_bytes(),
+            PhyPayload::RejoinRequest(rr) => rr.as_bytes(),
             PhyPayload::Data(data) => data.as_bytes(),
         }
     }
@@ -322,6 +330,99 @@ impl<T: AsRef<[u8]>, F: CryptoFactory> JoinRequestPayload<T, F> {
     }
 }
 
+/// RejoinRequestPayload represents a LoRaWAN 1.1 RejoinRequest of any type.
+///
+/// It can be built either directly through the [new](#method.new) or using the
+/// [parse](fn.parse.html) function.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RejoinRequestPayload<T, F>(T, F);
+
+impl<T: AsRef<[u8]>, F> AsPhyPayloadBytes for RejoinRequestPayload<T, F> {
+    fn as_bytes(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+impl<T: AsRef<[u8]>, F: CryptoFactory> RejoinRequestPayload<T, F> {
+    /// Creates a new RejoinRequestPayload if the provided data is acceptable.
+    ///
+    /// # Argument
+    ///
+    /// * data - the bytes for the payload.
+    /// * factory - the factory that shall be used to create object for crypto functions.
+    pub fn new_with_factory(data: T, factory: F) -> Result<Self, Error> {
+        if !Self::can_build_from(data.as_ref()) {
+            Err(Error::InvalidData)
+        } else {
+            Ok(Self(data, factory))
+        }
+    }
+
+    fn can_build_from(bytes: &[u8]) -> bool {
+        let len = match bytes.get(MHDR_LEN).map(|t| RejoinType::try_from(*t)) {
+            Some(Ok(RejoinType::Type1)) => REJOIN_REQUEST_1_LEN,
+            Some(Ok(_)) => REJOIN_REQUEST_0_2_LEN,
+            _ => return false,
+        };
+        bytes.len() == len && MHDR(bytes[0]).mtype() == MType::RejoinRequest
+    }
+
+    /// Gives the type of the RejoinRequest.
+    pub fn rejoin_type(&self) -> RejoinType {
+        // The type has been validated when building the payload
+        RejoinType::try_from(self.0.as_ref()[MHDR_LEN]).unwrap()
+    }
+
+    /// Gives the NetID of type 0 and type 2 RejoinRequests.
+    pub fn net_id(&self) -> Option<NwkAddr<&[u8]>> {
+        const OFFSET: usize = MHDR_LEN + REJOIN_TYPE_LEN;
+        match self.rejoin_type() {
+            RejoinType::Type1 => None,
+            _ => Some(NwkAddr::new_from_raw(&self.0.as_ref()[OFFSET..OFFSET + NET_ID_LEN])),
+        }
+    }
+
+    /// Gives the JoinEUI of type 1 RejoinRequests.
+    pub fn join_eui(&self) -> Option<EUI64<&[u8]>> {
+        const OFFSET: usize = MHDR_LEN + REJOIN_TYPE_LEN;
+        match self.rejoin_type() {
+            RejoinType::Type1 => {
+                Some(EUI64::new_from_raw(&self.0.as_ref()[OFFSET..OFFSET + JOIN_EUI_LEN]))
+            }
+            _ => None,
+        }
+    }
+
+    /// Gives the DEV EUI of the RejoinRequest.
+    pub fn dev_eui(&self) -> EUI64<&[u8]> {
+        let d = self.0.as_ref();
+        let end = d.len() - MIC_LEN - RJ_COUNT_LEN;
+        EUI64::new_from_raw(&d[end - DEV_EUI_LEN..end])
+    }
+
+    /// Gives the counter of the RejoinRequest: RJcount0 for type 0 and type 2, RJcount1 for
+    /// type 1.
+    pub fn rj_count(&self) -> u16 {
+        let d = self.0.as_ref();
+        let offset = d.len() - MIC_LEN - RJ_COUNT_LEN;
+        u16::from_le_bytes([d[offset], d[offset + 1]])
+    }
+
+    /// Verifies that the RejoinRequest has correct MIC.
+    ///
+    /// # Argument
+    ///
+    /// * key - SNwkSIntKey for type 0 and type 2 RejoinRequests, JSIntKey for type 1.
+    pub fn validate_mic(&self, key: &AES128) -> bool {
+        self.mic() == self.calculate_mic(key)
+    }
+
+    fn calculate_mic(&self, key: &AES128) -> MIC {
+        let d = self.0.as_ref();
+        securityhelpers::calculate_mic(&d[..d.len() - MIC_LEN], self.1.new_mac(key))
+    }
+}
+
 /// EncryptedJoinAcceptPayload represents an encrypted JoinAccept.
 ///
 /// It can be built either directly through the [new](#method.new) or using the
@@ -378,11 +479,30 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>, F: CryptoFactory> EncryptedJoinAcceptPayload<
     /// ]);
     /// let decrypted = phy.unwrap().decrypt(&key);
     /// ```
-    pub fn decrypt(mut self, key: &AppKey) -> DecryptedJoinAcceptPayload<T, F> {
+    pub fn decrypt(self, key: &AppKey) -> DecryptedJoinAcceptPayload<T, F> {
+        self.decrypt_with_key(&key.0)
+    }
+
+    /// Decrypts the join-accept answering a join-request of a LoRaWAN 1.1 end-device, which is
+    /// encrypted with the NwkKey.
+    ///
+    /// Please note that it does not verify the mic.
+    pub fn decrypt_with_nwk_key(self, key: &NwkKey) -> DecryptedJoinAcceptPayload<T, F> {
+        self.decrypt_with_key(&key.0)
+    }
+
+    /// Decrypts the join-accept answering a rejoin-request, which is encrypted with the JSEncKey.
+    ///
+    /// Please note that it does not verify the mic.
+    pub fn decrypt_with_js_enc_key(self, key: &JSEncKey) -> DecryptedJoinAcceptPayload<T, F> {
+        self.decrypt_with_key(&key.0)
+    }
+
+    fn decrypt_with_key(mut self, key: &AES128) -> DecryptedJoinAcceptPayload<T, F> {
         {
             let bytes = self.0.as_mut();
             let len = bytes.len();
-            let aes_enc = self.1.new_enc(&key.0);
+            let aes_enc = self.1.new_enc(key);
 
             for i in 0..(len >> 4) {
                 let start = (i << 4) + 1;
@@ -495,6 +615,133 @@ impl<T: AsRef<[u8]>, F: CryptoFactory> DecryptedJoinAcceptPayload<T, F> {
         AppSKey(self.derive_session_key(0x2, dev_nonce, &key.0))
     }
 
+    /// Verifies the MIC of a join-accept received by a LoRaWAN 1.1 end-device.
+    ///
+    /// If the network server implements LoRaWAN 1.1 (OptNeg is set), the MIC is calculated with
+    /// the JSIntKey and also covers the JoinReqType, JoinEUI and DevNonce of the request that is
+    /// answered. Otherwise, it is calculated with the NwkKey as in LoRaWAN 1.0.
+    pub fn validate_mic_1_1<TT: AsRef<[u8]>>(
+        &self,
+        nwk_key: &NwkKey,
+        js_int_key: &JSIntKey,
+        join_req_type: JoinReqType,
+        join_eui: &AppEui,
+        dev_nonce: &DevNonce<TT>,
+    ) -> bool {
+        self.mic()
+            == self.calculate_mic_1_1(nwk_key, js_int_key, join_req_type, join_eui, dev_nonce)
+    }
+
+    /// Calculates the MIC of a join-accept for a LoRaWAN 1.1 end-device, see
+    /// [`validate_mic_1_1`](Self::validate_mic_1_1).
+    pub fn calculate_mic_1_1<TT: AsRef<[u8]>>(
+        &self,
+        nwk_key: &NwkKey,
+        js_int_key: &JSIntKey,
+        join_req_type: JoinReqType,
+        join_eui: &AppEui,
+        dev_nonce: &DevNonce<TT>,
+    ) -> MIC {
+        let d = self.0.as_ref();
+        if !self.opt_neg() {
+            return securityhelpers::calculate_mic(
+                &d[..d.len() - MIC_LEN],
+                self.1.new_mac(&nwk_key.0),
+            );
+        }
+        let mut mac = self.1.new_mac(&js_int_key.0);
+        mac.input(&[join_req_type.value()]);
+        mac.input(join_eui.as_ref());
+        mac.input(dev_nonce.as_ref());
+        securityhelpers::calculate_mic(&d[..d.len() - MIC_LEN], mac)
+    }
+
+    /// Computes the LoRaWAN 1.1 forwarding network session integrity key, which is used for
+    /// the MIC of uplinks.
+    ///
+    /// If OptNeg is not set, the network server implements LoRaWAN 1.0 and the key is derived
+    /// the same way as the LoRaWAN 1.0 NwkSKey, using the NwkKey.
+    pub fn derive_fnwksintkey<TT: AsRef<[u8]>>(
+        &self,
+        nwk_key: &NwkKey,
+        join_eui: &AppEui,
+        dev_nonce: &DevNonce<TT>,
+    ) -> FNwkSIntKey {
+        FNwkSIntKey(self.derive_session_key_1_1(0x1, join_eui, dev_nonce, &nwk_key.0))
+    }
+
+    /// Computes the LoRaWAN 1.1 serving network session integrity key, which is used for the
+    /// MIC of downlinks and uplinks. It equals the FNwkSIntKey if OptNeg is not set.
+    pub fn derive_snwksintkey<TT: AsRef<[u8]>>(
+        &self,
+        nwk_key: &NwkKey,
+        join_eui: &AppEui,
+        dev_nonce: &DevNonce<TT>,
+    ) -> SNwkSIntKey {
+        let first_byte = if self.opt_neg() {
+            0x3
+        } else {
+            0x1
+        };
+        SNwkSIntKey(self.derive_session_key_1_1(first_byte, join_eui, dev_nonce, &nwk_key.0))
+    }
+
+    /// Computes the LoRaWAN 1.1 network session encryption key, which is used to encrypt MAC
+    /// commands. It equals the FNwkSIntKey if OptNeg is not set.
+    pub fn derive_nwksenckey<TT: AsRef<[u8]>>(
+        &self,
+        nwk_key: &NwkKey,
+        join_eui: &AppEui,
+        dev_nonce: &DevNonce<TT>,
+    ) -> NwkSEncKey {
+        let first_byte = if self.opt_neg() {
+            0x4
+        } else {
+            0x1
+        };
+        NwkSEncKey(self.derive_session_key_1_1(first_byte, join_eui, dev_nonce, &nwk_key.0))
+    }
+
+    /// Computes the application session key for a LoRaWAN 1.1 end-device, which is derived from
+    /// the AppKey if OptNeg is set and from the NwkKey otherwise.
+    pub fn derive_appskey_1_1<TT: AsRef<[u8]>>(
+        &self,
+        app_key: &AppKey,
+        nwk_key: &NwkKey,
+        join_eui: &AppEui,
+        dev_nonce: &DevNonce<TT>,
+    ) -> AppSKey {
+        let key = if self.opt_neg() {
+            &app_key.0
+        } else {
+            &nwk_key.0
+        };
+        AppSKey(self.derive_session_key_1_1(0x2, join_eui, dev_nonce, key))
+    }
+
+    fn derive_session_key_1_1<TT: AsRef<[u8]>>(
+        &self,
+        first_byte: u8,
+        join_eui: &AppEui,
+        dev_nonce: &DevNonce<TT>,
+        key: &AES128,
+    ) -> AES128 {
+        if !self.opt_neg() {
+            return self.derive_session_key(first_byte, dev_nonce, key);
+        }
+        let cipher = self.1.new_enc(key);
+
+        // note: JoinNonce is 24 bits, JoinEUI is 64 bits, DevNonce is 16 bits
+        let mut block = [0u8; 16];
+        block[0] = first_byte;
+        block[1..4].copy_from_slice(self.app_nonce().as_ref());
+        block[4..12].copy_from_slice(join_eui.as_ref());
+        block[12..14].copy_from_slice(dev_nonce.as_ref());
+
+        cipher.encrypt_block(&mut block);
+        AES128(block)
+    }
+
     fn derive_session_key<TT: AsRef<[u8]>>(
         &self,
         first_byte: u8,
@@ -559,6 +806,12 @@ impl<T: AsRef<[u8]>, F> DecryptedJoinAcceptPayload<T, F> {
         DLSettings::new(self.0.as_ref()[OFFSET])
     }
 
+    /// Whether the network server implements LoRaWAN 1.1 or later, as indicated by the OptNeg
+    /// bit of DLSettings.
+    pub fn opt_neg(&self) -> bool {
+        self.dl_settings().opt_neg()
+    }
+
     /// Gives the RX delay of the JoinAccept.
     pub fn rx_delay(&self) -> u8 {
         const OFFSET: usize =
@@ -716,6 +969,73 @@ impl<T: AsRef<[u8]>, F: CryptoFactory> EncryptedDataPayload<T, F> {
         let d = self.0.as_ref();
         securityhelpers::calculate_data_mic(&d[..d.len() - MIC_LEN], self.1.new_mac(key), fcnt)
     }
+
+    /// Verifies that a LoRaWAN 1.1 downlink has correct MIC.
+    ///
+    /// # Argument
+    ///
+    /// * s_nwk_s_int_key - the serving network session integrity key.
+    /// * fcnt - the AFCntDown or NFCntDown of the downlink.
+    /// * conf_fcnt - the frame counter of the confirmed uplink, which is only covered by the MIC
+    ///   if the downlink acknowledges it.
+    pub fn validate_downlink_mic_1_1(
+        &self,
+        s_nwk_s_int_key: &AES128,
+        fcnt: u32,
+        conf_fcnt: u16,
+    ) -> bool {
+        let d = self.0.as_ref();
+        let conf_fcnt = if self.fhdr().fctrl().ack() {
+            conf_fcnt
+        } else {
+            0
+        };
+        self.mic()
+            == securityhelpers::calculate_downlink_data_mic_1_1(
+                &d[..d.len() - MIC_LEN],
+                self.1.new_mac(s_nwk_s_int_key),
+                fcnt,
+                conf_fcnt,
+            )
+    }
+
+    /// Verifies that a LoRaWAN 1.1 uplink has correct MIC.
+    ///
+    /// # Argument
+    ///
+    /// * f_nwk_s_int_key - the forwarding network session integrity key.
+    /// * s_nwk_s_int_key - the serving network session integrity key.
+    /// * fcnt - the FCntUp of the uplink.
+    /// * conf_fcnt - the frame counter of the confirmed downlink, which is only covered by the MIC
+    ///   if the uplink acknowledges it.
+    /// * tx_dr - the data rate of the transmission.
+    /// * tx_ch - the index of the channel used for the transmission.
+    pub fn validate_uplink_mic_1_1(
+        &self,
+        f_nwk_s_int_key: &AES128,
+        s_nwk_s_int_key: &AES128,
+        fcnt: u32,
+        conf_fcnt: u16,
+        tx_dr: u8,
+        tx_ch: u8,
+    ) -> bool {
+        let d = self.0.as_ref();
+        let conf_fcnt = if self.fhdr().fctrl().ack() {
+            conf_fcnt
+        } else {
+            0
+        };
+        self.mic()
+            == securityhelpers::calculate_uplink_data_mic_1_1(
+                &d[..d.len() - MIC_LEN],
+                self.1.new_mac(f_nwk_s_int_key),
+                self.1.new_mac(s_nwk_s_int_key),
+                fcnt,
+                conf_fcnt,
+                tx_dr,
+                tx_ch,
+            )
+    }
 }
 
 impl<T: AsRef<[u8]> + AsMut<[u8]>, F: CryptoFactory> EncryptedDataPayload<T, F> {
@@ -782,6 +1102,34 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>, F: CryptoFactory> EncryptedDataPayload<T, F>
         Ok(DecryptedDataPayload(self.0))
     }
 
+    /// Decrypts a LoRaWAN 1.1 EncryptedDataPayload, including the MAC commands in FOpts.
+    ///
+    /// It works like [decrypt](#method.decrypt), with the network session encryption key taking
+    /// the role of nwk_skey. If nwk_s_enc_key is None, FOpts are left encrypted.
+    ///
+    /// # Argument
+    ///
+    /// * nwk_s_enc_key - the key used to decrypt the mac commands.
+    /// * app_skey - the Application Session key used to decrypt the application payload in case the
+    ///   payload is transporting that.
+    /// * fcnt - the counter used to encrypt the payload, ie: AFCntDown or NFCntDown for downlinks.
+    pub fn decrypt_1_1<'a>(
+        mut self,
+        nwk_s_enc_key: Option<&'a AES128>,
+        app_skey: Option<&'a AES128>,
+        fcnt: u32,
+    ) -> Result<DecryptedDataPayload<T>, Error> {
+        let fopts_len = self.fhdr_length() - 7;
+        if let (Some(key), true) = (nwk_s_enc_key, fopts_len > 0) {
+            let full_fcnt = compute_fcnt(fcnt, self.fhdr().fcnt());
+            // Downlinks with FPort > 0 use AFCntDown
+            let afcnt_down = !self.is_uplink() && self.f_port().is_some();
+            let enc = self.1.new_enc(key);
+            securityhelpers::encrypt_fopts(self.0.as_mut(), fopts_len, full_fcnt, afcnt_down, &enc);
+        }
+        self.decrypt(nwk_s_enc_key, app_skey, fcnt)
+    }
+
     /// Verifies the mic and decrypts the EncryptedDataPayload payload if mic matches.
     ///
     /// This is helper method that combines validate_mic and decrypt. In case the mic is fine, it
@@ -912,6 +1260,9 @@ where
         MType::JoinAccept => Ok(PhyPayload::JoinAccept(JoinAcceptPayload::Encrypted(
             EncryptedJoinAcceptPayload::new_with_factory(data, factory)?,
         ))),
+        MType::RejoinRequest => {
+            Ok(PhyPayload::RejoinRequest(RejoinRequestPayload::new_with_factory(data, factory)?))
+        }
         MType::UnconfirmedDataUp
         | MType::ConfirmedDataUp
         | MType::UnconfirmedDataDown
@@ -940,7 +1291,7 @@ impl MHDR {
             3 => MType::UnconfirmedDataDown,
             4 => MType::ConfirmedDataUp,
             5 => MType::ConfirmedDataDown,
-            6 => MType::RFU,
+            6 => MType::RejoinRequest,
             _ => MType::Proprietary,
         }
     }
@@ -970,7 +1321,7 @@ pub enum MType {
     UnconfirmedDataDown,
     ConfirmedDataUp,
     ConfirmedDataDown,
-    RFU,
+    RejoinRequest,
     Proprietary,
 }
 
@@ -1151,6 +1502,16 @@ impl FCtrl {
         self.0 & (1 << 5) != 0
     }
 
+    /// Set the ClassB bit of an uplink, signaling that the device is in Class B mode.
+    pub fn set_class_b(&mut self) {
+        self.0 |= 1 << 4;
+    }
+
+    /// Gives whether an uplink signals Class B mode.
+    pub fn class_b(&self) -> bool {
+        self.1 && self.0 & (1 << 4) != 0
+    }
+
     /// Gives whether there are more payloads pending.
     pub fn f_pending(&self) -> bool {
         !self.1 && self.0 & (1 << 4) != 0
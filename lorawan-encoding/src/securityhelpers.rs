@@ -11,6 +11,49 @@ pub fn calculate_data_mic<M: keys::Mac>(data: &[u8], key: M, fcnt: u32) -> keys:
     calculate_mic_with_header(&header[..], data, key)
 }
 
+/// calculate_downlink_data_mic_1_1 computes the MIC of a LoRaWAN 1.1 downlink, which covers
+/// ConfFCnt.
+pub fn calculate_downlink_data_mic_1_1<M: keys::Mac>(
+    data: &[u8],
+    key: M,
+    fcnt: u32,
+    conf_fcnt: u16,
+) -> keys::MIC {
+    let mut header = [0; 16];
+
+    generate_helper_block(data, 0x49, fcnt, &mut header[..16]);
+    header[1..3].copy_from_slice(&conf_fcnt.to_le_bytes());
+    header[15] = data.len() as u8;
+
+    calculate_mic_with_header(&header[..], data, key)
+}
+
+/// calculate_uplink_data_mic_1_1 computes the MIC of a LoRaWAN 1.1 uplink, which consists of
+/// two bytes calculated with SNwkSIntKey, covering ConfFCnt and the data rate and channel of the
+/// transmission, and two bytes calculated with FNwkSIntKey.
+pub fn calculate_uplink_data_mic_1_1<M: keys::Mac>(
+    data: &[u8],
+    f_key: M,
+    s_key: M,
+    fcnt: u32,
+    conf_fcnt: u16,
+    tx_dr: u8,
+    tx_ch: u8,
+) -> keys::MIC {
+    let mut b0 = [0; 16];
+    generate_helper_block(data, 0x49, fcnt, &mut b0[..16]);
+    b0[15] = data.len() as u8;
+
+    let mut b1 = b0;
+    b1[1..3].copy_from_slice(&conf_fcnt.to_le_bytes());
+    b1[3] = tx_dr;
+    b1[4] = tx_ch;
+
+    let cmac_f = calculate_mic_with_header(&b0[..], data, f_key);
+    let cmac_s = calculate_mic_with_header(&b1[..], data, s_key);
+    keys::MIC([cmac_s.0[0], cmac_s.0[1], cmac_f.0[0], cmac_f.0[1]])
+}
+
 fn generate_helper_block(data: &[u8], first: u8, fcnt: u32, res: &mut [u8]) {
     res[0] = first;
     // res[1..5] are 0
@@ -69,3 +112,30 @@ pub fn encrypt_frm_data_payload(
         phy_payload[start + i] ^= s[j]
     }
 }
+
+/// encrypt_fopts encrypts (or decrypts) the FOpts field of a LoRaWAN 1.1 data frame.
+///
+/// As amended by the LoRaWAN 1.1 errata, the fifth byte of the A block identifies the frame
+/// counter: 0x02 for downlinks using AFCntDown, 0x01 otherwise.
+pub fn encrypt_fopts(
+    phy_payload: &mut [u8],
+    fopts_len: usize,
+    fcnt: u32,
+    afcnt_down: bool,
+    aes_enc: &dyn keys::Encrypter,
+) {
+    let mut a = [0u8; 16];
+    generate_helper_block(phy_payload, 0x01, fcnt, &mut a[..]);
+    a[4] = if afcnt_down {
+        0x02
+    } else {
+        0x01
+    };
+    a[15] = 0x01;
+    aes_enc.encrypt_block(&mut a);
+
+    // FOpts follows MHDR, DevAddr, FCtrl and FCnt
+    for (byte, s) in phy_payload[8..8 + fopts_len].iter_mut().zip(a.iter()) {
+        *byte ^= s;
+    }
+}
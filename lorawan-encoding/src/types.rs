@@ -145,6 +145,7 @@ impl<const N: usize> AsRef<[u8]> for ChannelMask<N> {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 /// `DR` is a number from `0..=15` to indicate region-specific DataRate.
 /// Value `0xf` (decimal 15) has special meaning of no-op to continue with
@@ -247,6 +248,77 @@ impl From<u8> for DataRateRange {
     }
 }
 
+/// JoinReqType identifies the request answered by a LoRaWAN 1.1 join-accept, which is covered by
+/// its MIC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum JoinReqType {
+    JoinRequest,
+    RejoinRequest0,
+    RejoinRequest1,
+    RejoinRequest2,
+}
+
+impl JoinReqType {
+    /// The value of JoinReqType used for the MIC calculation.
+    pub fn value(&self) -> u8 {
+        match self {
+            JoinReqType::JoinRequest => 0xff,
+            JoinReqType::RejoinRequest0 => 0x00,
+            JoinReqType::RejoinRequest1 => 0x01,
+            JoinReqType::RejoinRequest2 => 0x02,
+        }
+    }
+}
+
+/// RejoinType of a LoRaWAN 1.1 rejoin-request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RejoinType {
+    /// Sent periodically to check the session context, or to request a new session with a
+    /// possibly different DevAddr.
+    Type0,
+    /// Sent to restore a lost session context, eg: when roaming between networks.
+    Type1,
+    /// Sent to rekey the session or to change the radio parameters of the join-accept.
+    Type2,
+}
+
+impl RejoinType {
+    /// The integer value of the RejoinType.
+    pub fn value(&self) -> u8 {
+        match self {
+            RejoinType::Type0 => 0,
+            RejoinType::Type1 => 1,
+            RejoinType::Type2 => 2,
+        }
+    }
+}
+
+impl TryFrom<u8> for RejoinType {
+    type Error = Error;
+
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
+        match v {
+            0 => Ok(RejoinType::Type0),
+            1 => Ok(RejoinType::Type1),
+            2 => Ok(RejoinType::Type2),
+            _ => Err(Error::RFU),
+        }
+    }
+}
+
+impl From<RejoinType> for JoinReqType {
+    fn from(v: RejoinType) -> Self {
+        match v {
+            RejoinType::Type0 => JoinReqType::RejoinRequest0,
+            RejoinType::Type1 => JoinReqType::RejoinRequest1,
+            RejoinType::Type2 => JoinReqType::RejoinRequest2,
+        }
+    }
+}
+
 /// DLSettings represents LoRaWAN DLSettings.
 #[derive(Debug, PartialEq, Eq)]
 pub struct DLSettings(u8);
@@ -268,6 +340,12 @@ impl DLSettings {
         self.0 & 0x0f
     }
 
+    /// OptNeg bit of a LoRaWAN 1.1 join-accept, set when the network server implements
+    /// LoRaWAN 1.1 or later. Always unset in LoRaWAN 1.0.
+    pub fn opt_neg(&self) -> bool {
+        self.0 & 0x80 != 0
+    }
+
     /// The integer value of the DL Settings.
     pub fn raw_value(&self) -> u8 {
         self.0
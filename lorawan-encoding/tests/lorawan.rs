@@ -4,7 +4,7 @@ use lorawan::keys::*;
 use lorawan::maccommandcreator::*;
 use lorawan::maccommands::*;
 use lorawan::parser::*;
-use lorawan::types::{DLSettings, Frequency};
+use lorawan::types::{ChannelMask, DLSettings, Frequency, JoinReqType, RejoinType};
 
 fn phy_join_request_payload() -> Vec<u8> {
     let mut res = Vec::new();
@@ -117,7 +117,7 @@ fn test_mhdr_mtype() {
         (0x60, MType::UnconfirmedDataDown),
         (0x80, MType::ConfirmedDataUp),
         (0xa0, MType::ConfirmedDataDown),
-        (0xc0, MType::RFU),
+        (0xc0, MType::RejoinRequest),
         (0xe0, MType::Proprietary),
     ];
     for (v, expected) in &examples {
@@ -249,6 +249,8 @@ fn test_dl_settings() {
     let dl_settings = DLSettings::new(0xcb);
     assert_eq!(dl_settings.rx1_dr_offset(), 4);
     assert_eq!(dl_settings.rx2_data_rate(), 11);
+    assert!(dl_settings.opt_neg());
+    assert!(!DLSettings::new(0x4b).opt_neg());
 }
 
 #[test]
@@ -466,6 +468,8 @@ fn test_fctrl_uplink_complete() {
     assert!(uplink_fctrl.ack());
     assert!(uplink_fctrl.adr());
     assert!(uplink_fctrl.adr_ack_req());
+    assert!(uplink_fctrl.class_b());
+    assert!(!uplink_fctrl.f_pending());
     assert_eq!(uplink_fctrl.f_opts_len(), 15);
     assert_eq!(uplink_fctrl.raw_value(), byte);
 }
@@ -474,6 +478,15 @@ fn test_fctrl_uplink_complete() {
 fn test_fctrl_downlink_complete() {
     let downlink_fctrl = FCtrl::new(0xff, false);
     assert!(downlink_fctrl.f_pending());
+    assert!(!downlink_fctrl.class_b());
+}
+
+#[test]
+fn test_fctrl_set_class_b() {
+    let mut fctrl = FCtrl::new(0, true);
+    fctrl.set_class_b();
+    assert_eq!(fctrl.raw_value(), 0x10);
+    assert!(fctrl.class_b());
 }
 
 #[test]
@@ -720,6 +733,25 @@ fn test_join_accept_creator_with_cflist() {
     assert_eq!(decrypted.c_f_list(), Some(CfList::DynamicChannel(freqs)))
 }
 
+#[test]
+#[cfg(feature = "default-crypto")]
+fn test_join_accept_creator_with_channel_mask_cflist() {
+    let mut buf = [0u8; 17 + 16];
+    let mut phy = JoinAcceptCreator::new(&mut buf[..]).unwrap();
+    let key: AppKey = AppKey::from(app_key());
+    let channel_mask = ChannelMask::new_from_raw(&[0xFF, 0, 0, 0, 0, 0, 0, 0, 0x01]);
+    phy.set_app_nonce(&[0xc7, 0x0b, 0x57])
+        .set_net_id(&[0x01, 0x11, 0x22])
+        .set_dev_addr(&[0x80, 0x19, 0x03, 0x02])
+        .set_c_f_list_channel_mask(&channel_mask)
+        .unwrap();
+    phy.build(key.inner(), &DefaultFactory).unwrap();
+    let encrypted = EncryptedJoinAcceptPayload::new(buf).unwrap();
+    let decrypted = encrypted.decrypt(&key);
+    assert!(decrypted.validate_mic(&key));
+    assert_eq!(decrypted.c_f_list(), Some(CfList::FixedChannel(channel_mask)))
+}
+
 #[test]
 fn test_join_request_creator() {
     let buf = [0u8; 23];
@@ -780,6 +812,379 @@ fn test_derive_appskey() {
     assert_eq!(appskey, expect);
 }
 
+fn dev_eui_1_1() -> DevEui {
+    DevEui::from([0x05, 0x04, 0x03, 0x02, 0x05, 0x04, 0x03, 0x02])
+}
+
+fn join_eui_1_1() -> AppEui {
+    AppEui::from([0x04, 0x03, 0x02, 0x01, 0x04, 0x03, 0x02, 0x01])
+}
+
+fn app_key_1_1() -> AppKey {
+    AppKey::from([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16])
+}
+
+fn f_nwk_s_int_key_1_1() -> FNwkSIntKey {
+    FNwkSIntKey::from([
+        0xc5, 0x59, 0xf8, 0xbe, 0x81, 0x56, 0x4e, 0xf1, 0xd2, 0xe7, 0x50, 0x89, 0x1d, 0x67, 0x66,
+        0x21,
+    ])
+}
+
+fn s_nwk_s_int_key_1_1() -> SNwkSIntKey {
+    SNwkSIntKey::from([
+        0xd9, 0xe9, 0x14, 0xc0, 0xa9, 0x41, 0x90, 0x4a, 0x9b, 0xf2, 0xc6, 0x37, 0x0f, 0xc3, 0x67,
+        0x26,
+    ])
+}
+
+fn nwk_s_enc_key_1_1() -> NwkSEncKey {
+    NwkSEncKey::from([
+        0xf1, 0x36, 0x2d, 0xc2, 0x84, 0x79, 0x8d, 0x4f, 0x09, 0x64, 0x66, 0xad, 0xfc, 0x0b, 0x5d,
+        0x20,
+    ])
+}
+
+fn app_s_key_1_1() -> AppSKey {
+    AppSKey::from([
+        0x1a, 0x20, 0x43, 0xc5, 0xb0, 0xbf, 0xfa, 0x54, 0x95, 0xe2, 0x02, 0xd1, 0x1f, 0x00, 0xcc,
+        0x78,
+    ])
+}
+
+#[test]
+fn test_join_accept_1_1_opt_neg() {
+    let nwk_key = NwkKey::from(app_key());
+    let js_int_key = nwk_key.derive_js_int_key(&DefaultFactory, &dev_eui_1_1());
+    let dev_nonce = DevNonce::new(&[0x2d, 0x10][..]).unwrap();
+    let data = vec![
+        0x20, 0x61, 0x57, 0x2a, 0x09, 0xde, 0xfc, 0x74, 0x8f, 0xa9, 0x7d, 0x7f, 0x68, 0xa4, 0x76,
+        0x2e, 0xce,
+    ];
+    let join_accept = EncryptedJoinAcceptPayload::new(data).unwrap().decrypt_with_nwk_key(&nwk_key);
+    assert!(join_accept.opt_neg());
+    let join_eui = join_eui_1_1();
+    assert!(join_accept.validate_mic_1_1(
+        &nwk_key,
+        &js_int_key,
+        JoinReqType::JoinRequest,
+        &join_eui,
+        &dev_nonce
+    ));
+    // JoinReqType is covered by the MIC
+    assert!(!join_accept.validate_mic_1_1(
+        &nwk_key,
+        &js_int_key,
+        JoinReqType::RejoinRequest0,
+        &join_eui,
+        &dev_nonce
+    ));
+    assert_eq!(
+        join_accept.derive_fnwksintkey(&nwk_key, &join_eui, &dev_nonce),
+        f_nwk_s_int_key_1_1()
+    );
+    assert_eq!(
+        join_accept.derive_snwksintkey(&nwk_key, &join_eui, &dev_nonce),
+        s_nwk_s_int_key_1_1()
+    );
+    assert_eq!(join_accept.derive_nwksenckey(&nwk_key, &join_eui, &dev_nonce), nwk_s_enc_key_1_1());
+    assert_eq!(
+        join_accept.derive_appskey_1_1(&app_key_1_1(), &nwk_key, &join_eui, &dev_nonce),
+        app_s_key_1_1()
+    );
+}
+
+#[test]
+fn test_join_accept_creator_1_1() {
+    let nwk_key = NwkKey::from(app_key());
+    let js_int_key = nwk_key.derive_js_int_key(&DefaultFactory, &dev_eui_1_1());
+    let dev_nonce = DevNonce::new(&[0x2d, 0x10][..]).unwrap();
+    let mut buf = [0; 17];
+    let mut phy = JoinAcceptCreator::new(&mut buf[..]).unwrap();
+    phy.set_app_nonce(&[0x01, 0x02, 0x03])
+        .set_net_id(&[0x04, 0x05, 0x06])
+        .set_dev_addr(&[0x01, 0x02, 0x03, 0x04])
+        .set_dl_settings(0x92)
+        .set_rx_delay(1);
+    let payload = phy
+        .build_1_1(
+            nwk_key.inner(),
+            &js_int_key,
+            JoinReqType::JoinRequest,
+            &join_eui_1_1(),
+            &dev_nonce,
+            &DefaultFactory,
+        )
+        .unwrap();
+    assert_eq!(
+        payload,
+        &[
+            0x20, 0x61, 0x57, 0x2a, 0x09, 0xde, 0xfc, 0x74, 0x8f, 0xa9, 0x7d, 0x7f, 0x68, 0xa4,
+            0x76, 0x2e, 0xce,
+        ]
+    );
+}
+
+#[test]
+fn test_join_accept_1_1_without_opt_neg() {
+    let nwk_key = NwkKey::from(app_key());
+    let js_int_key = nwk_key.derive_js_int_key(&DefaultFactory, &dev_eui_1_1());
+    let dev_nonce = DevNonce::new(&[0x2d, 0x10][..]).unwrap();
+    let data = vec![
+        0x20, 0x6a, 0x4a, 0x75, 0xac, 0x08, 0x9a, 0xac, 0x7c, 0x3a, 0x3a, 0x9e, 0x68, 0xda, 0x64,
+        0xff, 0xe9,
+    ];
+    let join_accept = EncryptedJoinAcceptPayload::new(data).unwrap().decrypt_with_nwk_key(&nwk_key);
+    assert!(!join_accept.opt_neg());
+    let join_eui = join_eui_1_1();
+    assert!(join_accept.validate_mic_1_1(
+        &nwk_key,
+        &js_int_key,
+        JoinReqType::JoinRequest,
+        &join_eui,
+        &dev_nonce
+    ));
+    // A LoRaWAN 1.0 network server uses a single network session key derived from the NwkKey
+    let expect = [
+        0x6e, 0x02, 0xd6, 0x1c, 0xe6, 0x9f, 0xb7, 0x4e, 0xb7, 0x24, 0x4c, 0x6e, 0xe1, 0x62, 0x58,
+        0x90,
+    ];
+    assert_eq!(
+        join_accept.derive_fnwksintkey(&nwk_key, &join_eui, &dev_nonce),
+        FNwkSIntKey::from(expect)
+    );
+    assert_eq!(
+        join_accept.derive_snwksintkey(&nwk_key, &join_eui, &dev_nonce),
+        SNwkSIntKey::from(expect)
+    );
+    assert_eq!(
+        join_accept.derive_nwksenckey(&nwk_key, &join_eui, &dev_nonce),
+        NwkSEncKey::from(expect)
+    );
+    assert_eq!(
+        join_accept.derive_appskey_1_1(&app_key_1_1(), &nwk_key, &join_eui, &dev_nonce),
+        AppSKey::from([
+            0x9a, 0x08, 0x52, 0x82, 0x47, 0xb5, 0x3b, 0xf5, 0x8a, 0xa4, 0x33, 0x14, 0xe7, 0xf1,
+            0x28, 0x3d,
+        ])
+    );
+}
+
+#[test]
+fn test_data_payload_uplink_creator_1_1() {
+    let mut buf = [0u8; 255];
+    let mut phy = DataPayloadCreator::new(&mut buf[..]).unwrap();
+    let fctrl = FCtrl::new(0xa0, true); // ADR and ACK
+    phy.set_confirmed(false)
+        .set_uplink(true)
+        .set_f_port(1)
+        .set_dev_addr(&[1, 2, 3, 4])
+        .set_fctrl(&fctrl)
+        .set_fcnt(0x10203)
+        .set_conf_fcnt(0x1234)
+        .set_tx_dr_ch(5, 3);
+    let expected = [
+        0x40, 0x01, 0x02, 0x03, 0x04, 0xa2, 0x03, 0x02, 0xea, 0x3d, 0x01, 0xd1, 0x25, 0x96, 0x7f,
+        0x07, 0x56, 0x5a, 0x95, 0x62,
+    ];
+    let built = phy
+        .build_1_1(
+            b"hello",
+            [0x02, 0x0d],
+            &f_nwk_s_int_key_1_1(),
+            &s_nwk_s_int_key_1_1(),
+            &nwk_s_enc_key_1_1(),
+            &app_s_key_1_1(),
+            &DefaultFactory,
+        )
+        .unwrap();
+    assert_eq!(built, &expected[..]);
+
+    let mut data = expected.to_vec();
+    let phy = EncryptedDataPayload::new(&mut data[..]).unwrap();
+    let (f_key, s_key) = (f_nwk_s_int_key_1_1(), s_nwk_s_int_key_1_1());
+    assert!(phy.validate_uplink_mic_1_1(f_key.inner(), s_key.inner(), 0x10203, 0x1234, 5, 3));
+    // The data rate and channel of the transmission are covered by the MIC
+    assert!(!phy.validate_uplink_mic_1_1(f_key.inner(), s_key.inner(), 0x10203, 0x1234, 5, 4));
+    let decrypted = phy
+        .decrypt_1_1(Some(nwk_s_enc_key_1_1().inner()), Some(app_s_key_1_1().inner()), 0x10203)
+        .unwrap();
+    assert_eq!(decrypted.fhdr().data(), &[0x02, 0x0d]);
+    assert_eq!(decrypted.frm_payload(), FRMPayload::Data(b"hello"));
+}
+
+#[test]
+fn test_data_payload_downlink_1_1() {
+    let mut data = vec![
+        0xa0, 0x01, 0x02, 0x03, 0x04, 0x23, 0x05, 0x00, 0xac, 0x5c, 0x13, 0x02, 0xe4, 0x5f, 0x75,
+        0xaa, 0xe6, 0xc9,
+    ];
+    let phy = EncryptedDataPayload::new(&mut data[..]).unwrap();
+    let s_key = s_nwk_s_int_key_1_1();
+    // ConfFCnt is the frame counter of the acknowledged uplink
+    assert!(phy.validate_downlink_mic_1_1(s_key.inner(), 5, 0x0203));
+    assert!(!phy.validate_downlink_mic_1_1(s_key.inner(), 5, 0x0204));
+    let decrypted = phy
+        .decrypt_1_1(Some(nwk_s_enc_key_1_1().inner()), Some(app_s_key_1_1().inner()), 5)
+        .unwrap();
+    assert_eq!(decrypted.fhdr().data(), &[0x02, 0x0a, 0x03]);
+    assert_eq!(decrypted.frm_payload(), FRMPayload::Data(b"hi"));
+
+    // Build the same downlink, which uses AFCntDown as it has FPort > 0
+    let mut buf = [0u8; 255];
+    let mut phy = DataPayloadCreator::new(&mut buf[..]).unwrap();
+    phy.set_confirmed(true)
+        .set_uplink(false)
+        .set_f_port(2)
+        .set_dev_addr(&[1, 2, 3, 4])
+        .set_fctrl(&FCtrl::new(0x20, false))
+        .set_fcnt(5)
+        .set_conf_fcnt(0x0203);
+    let built = phy
+        .build_1_1(
+            b"hi",
+            [0x02, 0x0a, 0x03],
+            &f_nwk_s_int_key_1_1(),
+            &s_key,
+            &nwk_s_enc_key_1_1(),
+            &app_s_key_1_1(),
+            &DefaultFactory,
+        )
+        .unwrap();
+    assert_eq!(
+        built,
+        &[
+            0xa0, 0x01, 0x02, 0x03, 0x04, 0x23, 0x05, 0x00, 0xac, 0x5c, 0x13, 0x02, 0xe4, 0x5f,
+            0x75, 0xaa, 0xe6, 0xc9
+        ][..]
+    );
+}
+
+#[test]
+fn test_join_request_creator_with_nwk_key() {
+    let mut phy = JoinRequestCreator::new(vec![0; 23]).unwrap();
+    phy.set_app_eui(&[0x04, 0x03, 0x02, 0x01, 0x04, 0x03, 0x02, 0x01])
+        .set_dev_eui(&[0x05, 0x04, 0x03, 0x02, 0x05, 0x04, 0x03, 0x02])
+        .set_dev_nonce(&[0x2d, 0x10]);
+    assert_eq!(
+        phy.build_with_nwk_key(&NwkKey::from([1; 16]), &DefaultFactory),
+        &phy_join_request_payload()[..]
+    );
+}
+
+fn phy_rejoin_request_0_payload() -> Vec<u8> {
+    vec![
+        0xc0, 0x00, 0x04, 0x05, 0x06, 0x05, 0x04, 0x03, 0x02, 0x05, 0x04, 0x03, 0x02, 0x01, 0x00,
+        0x84, 0x7b, 0x9a, 0xc7,
+    ]
+}
+
+fn phy_rejoin_request_1_payload() -> Vec<u8> {
+    vec![
+        0xc0, 0x01, 0x04, 0x03, 0x02, 0x01, 0x04, 0x03, 0x02, 0x01, 0x05, 0x04, 0x03, 0x02, 0x05,
+        0x04, 0x03, 0x02, 0x02, 0x00, 0xef, 0x62, 0xfd, 0x90,
+    ]
+}
+
+#[test]
+fn test_parse_rejoin_request_payload() {
+    let phy = parse(phy_rejoin_request_0_payload());
+    assert_eq!(
+        phy,
+        Ok(PhyPayload::RejoinRequest(
+            RejoinRequestPayload::new(phy_rejoin_request_0_payload()).unwrap()
+        ))
+    );
+    // Type 1 RejoinRequests are longer than type 0 and type 2
+    let mut bytes = phy_rejoin_request_1_payload();
+    bytes[1] = 2;
+    assert!(parse(bytes).is_err());
+    let mut bytes = phy_rejoin_request_0_payload();
+    bytes[1] = 3;
+    assert!(parse(bytes).is_err());
+}
+
+#[test]
+fn test_rejoin_request_0_payload() {
+    let phy = RejoinRequestPayload::new(phy_rejoin_request_0_payload()).unwrap();
+    assert_eq!(phy.mhdr().mtype(), MType::RejoinRequest);
+    assert_eq!(phy.rejoin_type(), RejoinType::Type0);
+    assert_eq!(phy.net_id(), Some(NwkAddr::new(&[0x04, 0x05, 0x06][..]).unwrap()));
+    assert_eq!(phy.join_eui(), None);
+    assert_eq!(phy.dev_eui(), EUI64::new(dev_eui_1_1().as_ref()).unwrap());
+    assert_eq!(phy.rj_count(), 1);
+    assert!(phy.validate_mic(s_nwk_s_int_key_1_1().inner()));
+    assert!(!phy.validate_mic(f_nwk_s_int_key_1_1().inner()));
+}
+
+#[test]
+fn test_rejoin_request_1_payload() {
+    let js_int_key = NwkKey::from(app_key()).derive_js_int_key(&DefaultFactory, &dev_eui_1_1());
+    let phy = RejoinRequestPayload::new(phy_rejoin_request_1_payload()).unwrap();
+    assert_eq!(phy.rejoin_type(), RejoinType::Type1);
+    assert_eq!(phy.net_id(), None);
+    assert_eq!(phy.join_eui(), Some(EUI64::new(join_eui_1_1().as_ref()).unwrap()));
+    assert_eq!(phy.dev_eui(), EUI64::new(dev_eui_1_1().as_ref()).unwrap());
+    assert_eq!(phy.rj_count(), 2);
+    assert!(phy.validate_mic(js_int_key.inner()));
+}
+
+#[test]
+fn test_rejoin_request_creator() {
+    let mut buf = [0; 19];
+    let mut phy = RejoinRequestCreator::new(&mut buf[..], RejoinType::Type0).unwrap();
+    assert_eq!(
+        phy.set_join_eui(join_eui_1_1()).err(),
+        Some(lorawan::creator::Error::InvalidRejoinType)
+    );
+    phy.set_net_id(&[0x04, 0x05, 0x06]).unwrap().set_dev_eui(dev_eui_1_1()).set_rj_count(1);
+    assert_eq!(
+        phy.build(s_nwk_s_int_key_1_1().inner(), &DefaultFactory),
+        &phy_rejoin_request_0_payload()[..]
+    );
+
+    assert!(RejoinRequestCreator::new(&mut buf[..], RejoinType::Type1).is_err());
+    let js_int_key = NwkKey::from(app_key()).derive_js_int_key(&DefaultFactory, &dev_eui_1_1());
+    let mut buf = [0; 24];
+    let mut phy = RejoinRequestCreator::new(&mut buf[..], RejoinType::Type1).unwrap();
+    assert_eq!(
+        phy.set_net_id(&[0x04, 0x05, 0x06]).err(),
+        Some(lorawan::creator::Error::InvalidRejoinType)
+    );
+    phy.set_join_eui(join_eui_1_1()).unwrap().set_dev_eui(dev_eui_1_1()).set_rj_count(2);
+    assert_eq!(phy.build(js_int_key.inner(), &DefaultFactory), &phy_rejoin_request_1_payload()[..]);
+}
+
+#[test]
+fn test_join_accept_answering_rejoin_request() {
+    let nwk_key = NwkKey::from(app_key());
+    let js_int_key = nwk_key.derive_js_int_key(&DefaultFactory, &dev_eui_1_1());
+    let js_enc_key = nwk_key.derive_js_enc_key(&DefaultFactory, &dev_eui_1_1());
+    let data = vec![
+        0x20, 0xa0, 0xb8, 0x35, 0x25, 0x32, 0xa3, 0x4f, 0x3a, 0xcf, 0x1c, 0xde, 0x05, 0x09, 0xf4,
+        0x71, 0x9e,
+    ];
+    let join_accept =
+        EncryptedJoinAcceptPayload::new(data).unwrap().decrypt_with_js_enc_key(&js_enc_key);
+    // RJcount0 takes the place of the DevNonce
+    let rj_count = DevNonce::from(1u16.to_le_bytes());
+    let join_eui = join_eui_1_1();
+    assert!(join_accept.validate_mic_1_1(
+        &nwk_key,
+        &js_int_key,
+        JoinReqType::RejoinRequest0,
+        &join_eui,
+        &rj_count
+    ));
+    assert_eq!(
+        join_accept.derive_fnwksintkey(&nwk_key, &join_eui, &rj_count),
+        FNwkSIntKey::from([
+            0x78, 0x36, 0x8d, 0x5f, 0xc1, 0x48, 0x59, 0x61, 0x23, 0xa2, 0x09, 0x38, 0x71, 0xbe,
+            0xf0, 0xf1,
+        ])
+    );
+}
+
 #[test]
 fn incorrect_frmpayload_with_maccommands() {
     let cmds: Vec<_> = MacCommandIterator::<DownlinkMacCommand>::new(&[3, 0xc0, 0, 0]).collect();
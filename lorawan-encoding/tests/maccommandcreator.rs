@@ -170,6 +170,20 @@ fn test_dl_channel_req_creator() {
     assert_eq!(res, [DlChannelReqPayload::cid(), 0x03, 0x12, 0x34, 0x56]);
 }
 
+#[test]
+fn test_rekey_ind_creator() {
+    let mut creator = RekeyIndCreator::new();
+    let res = creator.set_dev_lorawan_version(1).build();
+    assert_eq!(res, [RekeyIndPayload::cid(), 0x01]);
+}
+
+#[test]
+fn test_rekey_conf_creator() {
+    let mut creator = RekeyConfCreator::new();
+    let res = creator.set_serv_lorawan_version(1).build();
+    assert_eq!(res, [RekeyConfPayload::cid(), 0x01]);
+}
+
 #[test]
 fn test_device_time_req_creator() {
     let creator = DeviceTimeReqCreator::new();
@@ -186,6 +200,67 @@ fn test_device_time_ans_creator() {
     assert_eq!(res, [DeviceTimeAnsPayload::cid(), 64, 226, 1, 0, 31]);
 }
 
+#[test]
+fn test_force_rejoin_req_creator() {
+    let mut creator = ForceRejoinReqCreator::new();
+    let res = creator
+        .set_period(3)
+        .set_max_retries(2)
+        .set_rejoin_type(lorawan::types::RejoinType::Type2)
+        .set_data_rate(5)
+        .build();
+    assert_eq!(res, [ForceRejoinReqPayload::cid(), 0x25, 0x1a]);
+}
+
+#[test]
+fn test_rejoin_param_setup_req_creator() {
+    let mut creator = RejoinParamSetupReqCreator::new();
+    let res = creator.set_max_time_n(5).set_max_count_n(10).build();
+    assert_eq!(res, [RejoinParamSetupReqPayload::cid(), 0x5a]);
+}
+
+#[test]
+fn test_rejoin_param_setup_ans_creator() {
+    let mut creator = RejoinParamSetupAnsCreator::new();
+    let res = creator.set_time_ack(true).build();
+    assert_eq!(res, [RejoinParamSetupAnsPayload::cid(), 0x01]);
+}
+
+#[test]
+fn test_ping_slot_info_req_creator() {
+    let mut creator = PingSlotInfoReqCreator::new();
+    assert!(matches!(
+        creator.set_periodicity(8),
+        Err(lorawan::maccommandcreator::Error::PeriodicityOutOfRange)
+    ));
+    let res = creator.set_periodicity(5).unwrap().build();
+    assert_eq!(res, [PingSlotInfoReqPayload::cid(), 0x05]);
+}
+
+#[test]
+fn test_ping_slot_channel_req_creator() {
+    let mut creator = PingSlotChannelReqCreator::new();
+    let res = creator.set_frequency(&[0x18, 0x4f, 0x84]).set_data_rate(3).unwrap().build();
+    assert_eq!(res, [PingSlotChannelReqPayload::cid(), 0x18, 0x4f, 0x84, 0x03]);
+}
+
+#[test]
+fn test_ping_slot_channel_ans_creator() {
+    let mut creator = PingSlotChannelAnsCreator::new();
+    let res = creator.set_channel_frequency_ack(true).set_data_rate_ack(true).build();
+    assert_eq!(res, [PingSlotChannelAnsPayload::cid(), 0x03]);
+}
+
+#[test]
+fn test_beacon_freq_creators() {
+    let mut creator = BeaconFreqReqCreator::new();
+    let res = creator.set_frequency(&[0x18, 0x4f, 0x84]).build();
+    assert_eq!(res, [BeaconFreqReqPayload::cid(), 0x18, 0x4f, 0x84]);
+    let mut creator = BeaconFreqAnsCreator::new();
+    let res = creator.set_beacon_frequency_ack(true).build();
+    assert_eq!(res, [BeaconFreqAnsPayload::cid(), 0x01]);
+}
+
 #[test]
 fn test_build_mac_commands() {
     let rx_timing_setup_req =
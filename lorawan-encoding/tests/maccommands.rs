@@ -1,6 +1,6 @@
 use lorawan::maccommandcreator::*;
 use lorawan::maccommands::*;
-use lorawan::types::{DLSettings, DataRateRange, Frequency, Redundancy};
+use lorawan::types::{DLSettings, DataRateRange, Frequency, Redundancy, RejoinType};
 
 macro_rules! test_helper {
     ( $cmd:ident, $data:ident, $name:ident, $type:ident, $size:expr, $( ( $method:ident, $val:expr ) ,)*) => {{
@@ -244,6 +244,25 @@ fn test_dl_channel_ans() {
     );
 }
 
+#[test]
+fn test_rekey_ind() {
+    let data = [0x1];
+    test_helper!(UplinkMacCommand, data, RekeyInd, RekeyIndPayload, 1, (dev_lorawan_version, 1),);
+}
+
+#[test]
+fn test_rekey_conf() {
+    let data = [0x1];
+    test_helper!(
+        DownlinkMacCommand,
+        data,
+        RekeyConf,
+        RekeyConfPayload,
+        1,
+        (serv_lorawan_version, 1),
+    );
+}
+
 #[test]
 fn test_parse_mac_commands_empty_downlink() {
     assert_eq!(parse_downlink_mac_commands(&[]).count(), 0);
@@ -262,11 +281,130 @@ fn test_device_time_ans() {
         DeviceTimeAns,
         DeviceTimeAnsPayload,
         5,
-        (seconds, 16909060),
+        (seconds, 0x04030201),
         (nano_seconds, 0x5 * 3906250),
     );
 }
 
+#[test]
+fn test_force_rejoin_req() {
+    let data = [0x25, 0x1a];
+    test_helper!(
+        DownlinkMacCommand,
+        data,
+        ForceRejoinReq,
+        ForceRejoinReqPayload,
+        2,
+        (period, 3),
+        (max_retries, 2),
+        (rejoin_type, Ok(RejoinType::Type2)),
+        (data_rate, 5),
+    );
+    // RejoinType 1 requests a type 0 rejoin-request as well
+    let res = ForceRejoinReqPayload::new(&[0x10, 0x00]).unwrap();
+    assert_eq!(res.rejoin_type(), Ok(RejoinType::Type0));
+    let res = ForceRejoinReqPayload::new(&[0x30, 0x00]).unwrap();
+    assert_eq!(res.rejoin_type(), Err(lorawan::maccommands::Error::RFU));
+}
+
+#[test]
+fn test_rejoin_param_setup_req() {
+    let data = [0x5a];
+    test_helper!(
+        DownlinkMacCommand,
+        data,
+        RejoinParamSetupReq,
+        RejoinParamSetupReqPayload,
+        1,
+        (max_time_n, 5),
+        (max_count_n, 10),
+    );
+}
+
+#[test]
+fn test_rejoin_param_setup_ans() {
+    let data = [0x1];
+    test_helper!(
+        UplinkMacCommand,
+        data,
+        RejoinParamSetupAns,
+        RejoinParamSetupAnsPayload,
+        1,
+        (time_ack, true),
+    );
+}
+
+#[test]
+fn test_ping_slot_info_req() {
+    let data = [0x05];
+    test_helper!(
+        UplinkMacCommand,
+        data,
+        PingSlotInfoReq,
+        PingSlotInfoReqPayload,
+        1,
+        (periodicity, 5),
+    );
+}
+
+#[test]
+fn test_ping_slot_info_ans() {
+    test_helper!(DownlinkMacCommand, PingSlotInfoAns, PingSlotInfoAnsPayload);
+}
+
+#[test]
+fn test_ping_slot_channel_req() {
+    let data = [0x18, 0x4f, 0x84, 0x03];
+    test_helper!(
+        DownlinkMacCommand,
+        data,
+        PingSlotChannelReq,
+        PingSlotChannelReqPayload,
+        4,
+        (frequency, Frequency::new(&[0x18, 0x4f, 0x84]).unwrap()),
+        (data_rate, 3),
+    );
+}
+
+#[test]
+fn test_ping_slot_channel_ans() {
+    let examples = [([0x00], false, false), ([0x01], true, false), ([0x03], true, true)];
+    assert!(PingSlotChannelAnsPayload::new(&[]).is_err());
+    for (v, e_freq, e_ack) in &examples {
+        let res = PingSlotChannelAnsPayload::new(&v[..]).unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res.channel_freq_ack(), *e_freq);
+        assert_eq!(res.data_rate_ack(), *e_ack);
+        assert_eq!(res.ack(), *e_ack);
+    }
+}
+
+#[test]
+fn test_beacon_freq_req() {
+    let data = [0x18, 0x4f, 0x84];
+    test_helper!(
+        DownlinkMacCommand,
+        data,
+        BeaconFreqReq,
+        BeaconFreqReqPayload,
+        3,
+        (frequency, Frequency::new(&data[..]).unwrap()),
+    );
+}
+
+#[test]
+fn test_beacon_freq_ans() {
+    let data = [0x01];
+    test_helper!(
+        UplinkMacCommand,
+        data,
+        BeaconFreqAns,
+        BeaconFreqAnsPayload,
+        1,
+        (beacon_freq_ack, true),
+    );
+}
+
 #[test]
 fn test_parse_mac_commands_empty_uplink() {
     assert_eq!(parse_uplink_mac_commands(&[]).count(), 0);